@@ -0,0 +1,343 @@
+use super::board::Board;
+use super::coord::Coord;
+use super::mov::Mov;
+
+// Attack maps are u64 bitmaps of squares: bit y * 8 + x is set for the square (y, x)
+
+// diagonals first, then straight lines
+const DIRECTIONS: [(i8, i8); 8] = [(1, 1), (1, -1), (-1, 1), (-1, -1), (1, 0), (-1, 0), (0, 1), (0, -1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+// material values of the exchange, by piece >> 1 (empty, p, k, n, b, r, q);
+// the king is worth more than everything else together, so it never captures into a defended square
+pub const SEE_VALUES: [f32; 7] = [0.0, 1.0, 100.0, 3.0, 3.0, 5.0, 9.0];
+
+impl Board {
+    pub fn square_bit(y: u8, x: u8) -> u64 {
+        1 << (y * 8 + x)
+    }
+
+    // all the squares with a piece on them
+    pub fn get_occupied(&self) -> u64 {
+        let mut occupied = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                if self.field[y as usize][x as usize] > 1 {
+                    occupied |= Self::square_bit(y, x);
+                }
+            }
+        }
+        occupied
+    }
+
+    // all the squares with a piece of the color on them (1 stands for WHITE, 0 stands for BLACK)
+    pub fn get_occupied_by(&self, color: bool) -> u64 {
+        let mut occupied = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = self.field[y as usize][x as usize];
+                if piece > 1 && (piece & 1 == 1) == color {
+                    occupied |= Self::square_bit(y, x);
+                }
+            }
+        }
+        occupied
+    }
+
+    // Squares attacked (or defended) by the piece on (y, x); sliders stop at the first square of occupied.
+    // Pawns attack their capture squares only, pushes are not attacks.
+    pub fn get_piece_attacks(&self, y: u8, x: u8, occupied: u64) -> u64 {
+        self.get_attacks_of(self.field[y as usize][x as usize], y, x, occupied)
+    }
+
+    // squares the piece would attack from (y, x), wherever it stands now
+    pub fn get_attacks_of(&self, piece: u8, y: u8, x: u8, occupied: u64) -> u64 {
+        let mut attacks = 0;
+        if piece < 2 {
+            return attacks;
+        }
+        match self.gpr(&(piece & 254)) {
+            'p' => {
+                let dy = if piece & 1 == 1 { 1 } else { -1 };
+                for dx in [-1, 1] {
+                    if let Some((ty, tx)) = Self::shift(y, x, dy, dx) {
+                        attacks |= Self::square_bit(ty, tx);
+                    }
+                }
+            },
+            'n' | 'k' => {
+                let steps = if piece & 254 == self.gpl(&'n') { KNIGHT_JUMPS } else { DIRECTIONS };
+                for (dy, dx) in steps {
+                    if let Some((ty, tx)) = Self::shift(y, x, dy, dx) {
+                        attacks |= Self::square_bit(ty, tx);
+                    }
+                }
+            },
+            slider => {
+                let directions = match slider {
+                    'b' => &DIRECTIONS[..4],
+                    'r' => &DIRECTIONS[4..],
+                    _ => &DIRECTIONS[..]
+                };
+                for &(dy, dx) in directions {
+                    let (mut ty, mut tx) = (y, x);
+                    while let Some((ny, nx)) = Self::shift(ty, tx, dy, dx) {
+                        (ty, tx) = (ny, nx);
+                        attacks |= Self::square_bit(ty, tx);
+                        if occupied & Self::square_bit(ty, tx) > 0 {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        attacks
+    }
+
+    // every square attacked by the color, including the ones with its own pieces (defended ones)
+    pub fn get_attack_map(&self, color: bool) -> u64 {
+        let occupied = self.get_occupied();
+        let mut attacks = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = self.field[y as usize][x as usize];
+                if piece > 1 && (piece & 1 == 1) == color {
+                    attacks |= self.get_piece_attacks(y, x, occupied);
+                }
+            }
+        }
+        attacks
+    }
+
+    // Pieces of both colors attacking (y, x), only the pieces of occupied are taken into account,
+    // so removing the pieces that have already captured there reveals the ones behind them
+    pub fn get_attackers_map(&self, y: u8, x: u8, occupied: u64) -> u64 {
+        let mut attackers = 0;
+        let mut add = |ty: u8, tx: u8, pieces: &[char]| {
+            let piece = self.field[ty as usize][tx as usize];
+            if occupied & Self::square_bit(ty, tx) > 0 && piece > 1 && pieces.contains(&self.gpr(&piece)) {
+                attackers |= Self::square_bit(ty, tx);
+            }
+        };
+        for (dy, dx) in KNIGHT_JUMPS {
+            if let Some((ty, tx)) = Self::shift(y, x, dy, dx) {
+                add(ty, tx, &['n', 'N']);
+            }
+        }
+        for (i, &(dy, dx)) in DIRECTIONS.iter().enumerate() {
+            let sliders: &[char] = if i < 4 { &['b', 'B', 'q', 'Q'] } else { &['r', 'R', 'q', 'Q'] };
+            if let Some((ty, tx)) = Self::shift(y, x, dy, dx) {
+                add(ty, tx, &['k', 'K']);
+                // pawns attack forward only: a white pawn from below, a black one from above
+                if i < 4 {
+                    add(ty, tx, if dy < 0 { &['P'] } else { &['p'] });
+                }
+            }
+            let (mut ty, mut tx) = (y, x);
+            while let Some((ny, nx)) = Self::shift(ty, tx, dy, dx) {
+                (ty, tx) = (ny, nx);
+                if occupied & Self::square_bit(ty, tx) > 0 {
+                    add(ty, tx, sliders);
+                    break;
+                }
+            }
+        }
+        attackers
+    }
+
+    // Pieces of the color attacking (y, x), the least valuable first. With x-rays it also includes the pieces
+    // behind the attackers of both colors (batteries), they come after the direct attackers.
+    pub fn get_attackers(&self, y: u8, x: u8, color_of_attacker: bool, xrays: bool) -> Vec<Coord> {
+        let mut occupied = self.get_occupied();
+        let own = self.get_occupied_by(color_of_attacker);
+        let mut attackers: Vec<Coord> = Vec::default();
+        loop {
+            let found = self.get_attackers_map(y, x, occupied);
+            let mut layer: Vec<Coord> = Self::get_coords(found & own);
+            layer.sort_by(|a, b| self.get_see_value(a.y(), a.x()).total_cmp(&self.get_see_value(b.y(), b.x())));
+            attackers.extend(layer);
+            if !xrays || found == 0 {
+                return attackers;
+            }
+            occupied &= !found;
+        }
+    }
+
+    // pieces defending the piece on (y, x), the same way as get_attackers(); none for an empty square
+    pub fn get_defenders(&self, y: u8, x: u8, xrays: bool) -> Vec<Coord> {
+        let piece = self.field[y as usize][x as usize];
+        if piece < 2 {
+            return Vec::default();
+        }
+        self.get_attackers(y, x, piece & 1 == 1, xrays)
+    }
+
+    // Pieces of the color pinned to their king, with the squares they may still move to:
+    // the line between the king and the pinner, including the pinner itself
+    pub fn get_pins(&self, color: bool) -> Vec<(Coord, u64)> {
+        let king = if color { self.white_king_location } else { self.black_king_location };
+        let mut pins: Vec<(Coord, u64)> = Vec::default();
+        for (i, &(dy, dx)) in DIRECTIONS.iter().enumerate() {
+            let sliders = if i < 4 { [self.gpl(&'b'), self.gpl(&'q')] } else { [self.gpl(&'r'), self.gpl(&'q')] };
+            let mut line: u64 = 0;
+            let mut pinned: Option<Coord> = None;
+            let (mut ty, mut tx) = (king.y(), king.x());
+            while let Some((ny, nx)) = Self::shift(ty, tx, dy, dx) {
+                (ty, tx) = (ny, nx);
+                line |= Self::square_bit(ty, tx);
+                let piece = self.field[ty as usize][tx as usize];
+                if piece < 2 {
+                    continue;
+                }
+                if (piece & 1 == 1) != color {
+                    if let Some(pinned) = pinned.filter(|_| sliders.contains(&(piece & 254))) {
+                        pins.push((pinned, line));
+                    }
+                    break;
+                } else if pinned.is_some() {
+                    break;
+                }
+                pinned = Some(Coord::new(ty, tx));
+            }
+        }
+        pins
+    }
+
+    // squares strictly between the two ones, if they are on the same line (otherwise none)
+    pub fn get_between(a: Coord, b: Coord) -> u64 {
+        let (dy, dx) = (b.y() as i8 - a.y() as i8, b.x() as i8 - a.x() as i8);
+        if !(dy == 0 || dx == 0 || dy.abs() == dx.abs()) {
+            return 0;
+        }
+        let mut between = 0;
+        let (mut y, mut x) = (a.y(), a.x());
+        while let Some((ny, nx)) = Self::shift(y, x, dy.signum(), dx.signum()) {
+            (y, x) = (ny, nx);
+            if (y, x) == (b.y(), b.x()) {
+                break;
+            }
+            between |= Self::square_bit(y, x);
+        }
+        between
+    }
+
+    // Static exchange evaluation: material won (or lost, if negative) by the side making the (pseudo-legal) move
+    // if both sides go on capturing on its square with the least valuable piece, and stop when it doesn't pay.
+    // Pins and checks are not taken into account.
+    pub fn see(&self, mov: &Mov) -> f32 {
+        let (y, x) = (mov.to.y(), mov.to.x());
+        let piece = self.field[mov.from.y() as usize][mov.from.x() as usize];
+        let mut occupied = self.get_occupied() & !Self::square_bit(mov.from.y(), mov.from.x());
+        let mut gains: Vec<f32> = vec![self.get_see_value(y, x)];
+        // value of the piece standing on the square, the next one to be captured
+        let mut on_square = SEE_VALUES[(piece >> 1) as usize];
+        if mov.data & 1 == 1 && piece & 254 == self.gpl(&'p') {
+            if mov.to.y() == 0 || mov.to.y() == 7 {
+                on_square = SEE_VALUES[(self.rtpv(mov.data) >> 1) as usize];
+                gains[0] += on_square - SEE_VALUES[1];
+            } else {
+                // en passant: the captured pawn is behind the square
+                occupied &= !Self::square_bit(mov.from.y(), x);
+                gains[0] = SEE_VALUES[1];
+            }
+        }
+        let mut color = piece & 1 == 0;
+        loop {
+            let attackers = self.get_attackers_map(y, x, occupied) & self.get_occupied_by(color);
+            let Some(attacker) = Self::get_coords(attackers).into_iter()
+                .min_by(|a, b| self.get_see_value(a.y(), a.x()).total_cmp(&self.get_see_value(b.y(), b.x()))) else {
+                break;
+            };
+            // gain of the capture, if the opponent goes on capturing
+            gains.push(on_square - gains.last().unwrap());
+            on_square = self.get_see_value(attacker.y(), attacker.x());
+            occupied &= !Self::square_bit(attacker.y(), attacker.x());
+            color = !color;
+        }
+        // every side may stop capturing instead
+        while gains.len() > 1 {
+            let last = gains.pop().unwrap();
+            let previous = gains.last_mut().unwrap();
+            *previous = -f32::max(-*previous, last);
+        }
+        gains[0]
+    }
+
+    fn get_see_value(&self, y: u8, x: u8) -> f32 {
+        SEE_VALUES[(self.field[y as usize][x as usize] >> 1) as usize]
+    }
+
+    pub fn get_coords(squares: u64) -> Vec<Coord> {
+        (0..64).filter(|i| squares & (1 << i) > 0).map(|i| Coord::new(i / 8, i % 8)).collect()
+    }
+
+    fn shift(y: u8, x: u8, dy: i8, dx: i8) -> Option<(u8, u8)> {
+        let (ty, tx) = (y as i8 + dy, x as i8 + dx);
+        if (0..8).contains(&ty) && (0..8).contains(&tx) {
+            Some((ty as u8, tx as u8))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, utils::utils::move_to_board};
+
+    fn coords(b: &Board, y: u8, x: u8, color: Option<bool>, xrays: bool) -> Vec<(u8, u8)> {
+        let found = match color {
+            Some(color) => b.get_attackers(y, x, color, xrays),
+            None => b.get_defenders(y, x, xrays)
+        };
+        found.iter().map(|c| (c.y(), c.x())).collect()
+    }
+
+    #[test]
+    fn test_attacks_attack_map_01() {
+        let b = Board::new();
+        // third rank is covered by pawns and knights, the pieces of the first rank defend each other except the rooks
+        let white = b.get_attack_map(true);
+        assert_eq!(white & 0xffffff, 0xffff7e);
+        assert_eq!(white >> 24, 0);
+        assert_eq!(b.get_attack_map(false), white.swap_bytes());
+    }
+
+    #[test]
+    fn test_attacks_attackers_01() {
+        // e4 pawn: attacked by the d5 pawn, the f6 knight and the c6 bishop behind d5,
+        // defended by the d2 knight, the e2 rook and the e1 queen behind it
+        let b = Board::parse_fen("4k3/8/2b2n2/3p4/4P3/8/3NR3/4Q1K1 w - - 0 1");
+        assert_eq!(coords(&b, 3, 4, Some(false), false), vec![(4, 3), (5, 5)]);
+        assert_eq!(coords(&b, 3, 4, Some(false), true), vec![(4, 3), (5, 5), (5, 2)]);
+        assert_eq!(coords(&b, 3, 4, None, true), vec![(1, 3), (1, 4), (0, 4)]);
+        assert_eq!(coords(&b, 4, 3, None, false), vec![(5, 2), (5, 5)]);
+        assert!(coords(&b, 2, 2, None, true).is_empty());
+    }
+
+    #[test]
+    fn test_attacks_see_01() {
+        // pawn takes a knight and wins it even after the recapture, rook takes it and loses the exchange
+        let b = Board::parse_fen("4k3/2p5/3n4/4P3/8/8/8/3RK3 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "e5d6")), 3.0);
+        assert_eq!(b.see(&move_to_board(&b, "d1d6")), -1.0);
+        // rook battery wins the pawn defended once, a single rook doesn't
+        let b = Board::parse_fen("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "d2d5")), 1.0);
+        let b = Board::parse_fen("3rk3/8/8/3p4/8/8/3R4/4K3 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "d2d5")), -4.0);
+        // quiet moves to a safe and to an attacked square, en passant
+        let b = Board::parse_fen("4k3/8/4p3/3pP3/8/8/8/2Q1K3 w - d6 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "c1f4")), 0.0);
+        assert_eq!(b.see(&move_to_board(&b, "c1c4")), -9.0);
+        assert_eq!(b.see(&move_to_board(&b, "e5d6")), 1.0);
+        // the king doesn't recapture a defended piece
+        let b = Board::parse_fen("4k3/4p3/8/8/8/8/4R3/4R1K1 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "e2e7")), 1.0);
+        let b = Board::parse_fen("4k3/4p3/8/8/8/8/4R3/6K1 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "e2e7")), -4.0);
+        // promotion with capture
+        let b = Board::parse_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(b.see(&move_to_board(&b, "a7b8q")), 13.0);
+    }
+}
@@ -1,4 +1,5 @@
 use std::char;
+use std::fmt;
 use std::cmp::{max, min};
 use std::vec::Vec;
 use crate::utils::utils::move_to_user;
@@ -6,8 +7,10 @@ use crate::utils::utils::move_to_user;
 use super::bimaps::Bimaps;
 use super::coord::Coord;
 use super::mov::{BoardMov, Mov};
+use super::zobrist::ZOBRIST;
 
 #[derive(PartialEq, Clone, Copy)]
+#[allow(clippy::enum_variant_names)]
 pub enum Check {
     Unknown,
     NotInCheck,
@@ -15,6 +18,64 @@ pub enum Check {
     InDoubleCheck
 }
 
+// reason why a move can't be made (see Board::validate_move)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum MoveError {
+    OutOfBoard,
+    NoPiece,
+    NotYourPiece,
+    OwnPieceCaptured,
+    // the piece doesn't move this way (or the way is blocked)
+    ImpossibleMove,
+    CastlingNotAllowed,
+    PromotionMissing,
+    // castling, en passant or promotion flag doesn't match the move
+    WrongFlags,
+    // captured piece doesn't match the one on the square
+    WrongCapture,
+    KingInCheck
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            MoveError::OutOfBoard => "square is out of the board",
+            MoveError::NoPiece => "there is no piece to move",
+            MoveError::NotYourPiece => "the piece belongs to the opponent",
+            MoveError::OwnPieceCaptured => "cannot capture an own piece",
+            MoveError::ImpossibleMove => "the piece cannot move there",
+            MoveError::CastlingNotAllowed => "castling is not allowed: no right, the way is blocked or attacked, or the king is in check",
+            MoveError::PromotionMissing => "promotion piece is missing",
+            MoveError::WrongFlags => "castling, en passant or promotion flag is wrong",
+            MoveError::WrongCapture => "captured piece is wrong",
+            MoveError::KingInCheck => "the king would be in check"
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+// part of legal moves to generate (see get_legal_moves_staged)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Stage {
+    All,
+    // captures (en passant as well) and promotions
+    Captures,
+    // the rest of moves
+    Quiets
+}
+
+impl Stage {
+    fn has_captures(self) -> bool {
+        self != Stage::Quiets
+    }
+
+    fn has_quiets(self) -> bool {
+        self != Stage::Captures
+    }
+}
+
 // question to myself: why pieces couldn't be classes?
 #[derive(Clone)]
 pub struct Board {
@@ -41,6 +102,8 @@ pub struct Board {
     // Additional information that's necessary in order to speedup the search of legal moves
     pub white_king_location: Coord,
     pub black_king_location: Coord,
+    // Zobrist hash of the position, updated incrementally by make_move/revert_move (see zobrist.rs)
+    pub hash: u64,
 
     // TODO: find a better way to store CONSTANT BIMAPS 
     // (they are not constant because Rust says so! shouldn't even be inside struct)
@@ -53,7 +116,7 @@ pub struct Board {
 impl Board {
     pub fn new() -> Self {
         let bimaps = Bimaps::init();
-        Self {
+        let mut board = Self {
             field: Board::get_default_board(&bimaps),
             history: Vec::new(),
             white_to_move: true,
@@ -63,11 +126,14 @@ impl Board {
             hno: 0,
             white_king_location: Coord::new(0, 4),
             black_king_location: Coord::new(7, 4),
+            hash: 0,
             bimaps
-        }
+        };
+        board.hash = ZOBRIST.hash(&board);
+        board
     }
 
-    pub fn parse_fen(FEN: &str) -> Self {
+    pub fn parse_fen(fen: &str) -> Self {
         let mut field: [[u8; 8]; 8] = [[0; 8]; 8];
         let history: Vec<BoardMov> = Vec::new();
         let mut white_to_move: bool = true;
@@ -79,7 +145,7 @@ impl Board {
         let mut black_king_location = Coord::new(7, 4);
         let bimaps = Bimaps::init();
 
-        let parts = FEN.split_ascii_whitespace();
+        let parts = fen.split_ascii_whitespace();
         let mut col: u8 = 0;
         let mut row: u8 = 7;
         let mut pn: u8 = 0;
@@ -135,10 +201,10 @@ impl Board {
                         if c == '-' {
                             break;
                         }
-                        row = c as u8 - b'b';
+                        col = c as u8 - b'a';
                         pn2 = 1;
                     } else {
-                        col = c as u8 - b'0';
+                        row = c as u8 - b'1';
                         en_passant.set(row, col);
                     }
                 }
@@ -153,15 +219,21 @@ impl Board {
             }
         }
 
-        Self { field, history, white_to_move, en_passant, castling, hmw, hno, white_king_location, black_king_location, bimaps }
+        let mut board = Self { field, history, white_to_move, en_passant, castling, hmw, hno, white_king_location, black_king_location, hash: 0, bimaps };
+        board.hash = ZOBRIST.hash(&board);
+        board
     }
 
     // Careful: this function WILL MAKE A MOVE without additional checks on if it's a legal move or not!
+    // (use try_make_move for the moves that come from outside)
     pub fn make_move(&mut self, mov: &Mov) {
         let piece = self.field[mov.from.y() as usize][mov.from.x() as usize];
+        let (squares, squares_len) = self.get_touched_squares(mov);
+        // keys of the state that is not on the squares, the new ones are added after the move
+        let state_key = ZOBRIST.castling(self.castling) ^ ZOBRIST.en_passant(self) ^ ZOBRIST.color();
 
         // make a move
-        self.history.push(BoardMov{mov: *mov, castling: self.castling, en_passant: self.en_passant, hmw: self.hmw});
+        self.history.push(BoardMov{mov: *mov, castling: self.castling, en_passant: self.en_passant, hmw: self.hmw, hash: self.hash});
         self.field[mov.to.y() as usize][mov.to.x() as usize] = self.field[mov.from.y() as usize][mov.from.x() as usize];
         self.field[mov.from.y() as usize][mov.from.x() as usize] = 0;
 
@@ -225,18 +297,16 @@ impl Board {
                 temp_en_passant.set(2, mov.from.x());
             }
         }
-        // watchout for a rook move that will prevent future castling as well
-        else if piece == self.gpl(&'r') && mov.from.y() == 7 {
-            if mov.from.x() == 0 {
-                self.castling &= 16;
-            } else if mov.from.x() == 7 {
-                self.castling &= 32;
-            }
-        } else if piece == self.gpl(&'R') && mov.from.y() == 0 {
-            if mov.from.x() == 0 {
-                self.castling &= 64;
-            } else if mov.from.x() == 7 {
-                self.castling &= 128;
+
+        // watchout for a move from or to the rook's initial square that will prevent future castling as well
+        // (so both rook moves and rook captures are covered)
+        for coord in [mov.from, mov.to] {
+            match (coord.y(), coord.x()) {
+                (0, 0) => self.castling &= !self.gcl(&'Q'),
+                (0, 7) => self.castling &= !self.gcl(&'K'),
+                (7, 0) => self.castling &= !self.gcl(&'q'),
+                (7, 7) => self.castling &= !self.gcl(&'k'),
+                _ => ()
             }
         }
 
@@ -247,11 +317,43 @@ impl Board {
         self.white_to_move = !self.white_to_move;
         self.hno += 1;
         self.en_passant = temp_en_passant;
+
+        // only the squares touched by the move are recalculated
+        self.hash ^= state_key ^ ZOBRIST.castling(self.castling) ^ ZOBRIST.en_passant(self);
+        for &(y, x, old) in &squares[..squares_len] {
+            let new = self.field[y][x];
+            if new != old {
+                self.hash ^= ZOBRIST.piece(old, y, x) ^ ZOBRIST.piece(new, y, x);
+            }
+        }
+        debug_assert_eq!(self.hash, ZOBRIST.hash(self), "incremental hash differs from the full one");
+    }
+
+    // squares (with pieces on them) that may change by the move: from, to, en passant capture and castling rook squares
+    pub fn get_touched_squares(&self, mov: &Mov) -> ([(usize, usize, u8); 7], usize) {
+        let (fy, fx, tx) = (mov.from.y() as usize, mov.from.x() as usize, mov.to.x() as usize);
+        let mut squares = [(0, 0, 0); 7];
+        let mut len = 0;
+        for (y, x) in [(fy, fx), (mov.to.y() as usize, tx), (fy, tx), (fy, 0), (fy, 3), (fy, 5), (fy, 7)] {
+            if !squares[..len].iter().any(|(sy, sx, _)| *sy == y && *sx == x) {
+                squares[len] = (y, x, self.field[y][x]);
+                len += 1;
+            }
+        }
+        (squares, len)
     }
 
     pub fn revert_move(&mut self) {
         let bmov: BoardMov = self.history.pop().unwrap();
         let mov: &Mov = &bmov.mov;
+        if mov.is_null() {
+            self.en_passant = bmov.en_passant;
+            self.hmw = bmov.hmw;
+            self.hash = bmov.hash;
+            self.hno -= 1;
+            self.white_to_move = !self.white_to_move;
+            return;
+        }
         let piece: u8 = self.field[mov.to.y() as usize][mov.to.x() as usize];
 
         self.field[mov.from.y() as usize][mov.from.x() as usize] = piece;
@@ -259,6 +361,7 @@ impl Board {
         self.castling = bmov.castling;
         self.en_passant = bmov.en_passant;
         self.hmw = bmov.hmw;
+        self.hash = bmov.hash;
 
         // reverse castling, revert kings locations
         if piece == self.gpl(&'k') {
@@ -299,135 +402,161 @@ impl Board {
         self.white_to_move = !self.white_to_move;
     }
 
-    pub fn get_legal_moves(&mut self, current_king_check_status: Option<Check>, save_opponent_king_check_status: Option<bool>) -> Vec<Mov> {
+    // Legal moves only: checkers and pinned pieces are found up front, so no move is made to see if it's legal.
+    // Known check status (check bits of the last move) saves the search of checkers when not in check.
+    pub fn get_legal_moves(&self, current_king_check_status: Option<Check>, save_opponent_king_check_status: Option<bool>) -> Vec<Mov> {
+        self.get_legal_moves_staged(current_king_check_status, save_opponent_king_check_status, Stage::All)
+    }
+
+    // Legal moves of the stage only: every piece generates the moves of the stage, so the capture stage
+    // doesn't walk the quiet moves (nor castlings), and the quiet one doesn't look for captures.
+    pub fn get_legal_moves_staged(&self, current_king_check_status: Option<Check>, save_opponent_king_check_status: Option<bool>, stage: Stage) -> Vec<Mov> {
         let mut moves: Vec<Mov> = Vec::default();
-        let check: Check = current_king_check_status.unwrap_or(Check::Unknown);
         let color_bit: u8 = self.white_to_move as u8;
         let save: bool = save_opponent_king_check_status.unwrap_or(false);
-        
-        match check {
-            Check::Unknown | Check::InCheck => {
-                // scan for any pseudo-legal moves
-                for y in 0..8 {
-                    for x in 0..8 {
-                        if self.field[y as usize][x as usize] > 1 {
-                            let piece = self.field[y as usize][x as usize] - color_bit;
-                            if piece == self.gpl(&'p') {
-                                self.add_legal_moves_p(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'k') {
-                                self.add_legal_moves_k(&mut moves, y, x, color_bit, Some(check));
-                            } else if piece == self.gpl(&'n') {
-                                self.add_legal_moves_n(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'b') {
-                                self.add_legal_moves_bq(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'r') {
-                                self.add_legal_moves_rq(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'q') {
-                                self.add_legal_moves_bq(&mut moves, y, x, color_bit);
-                                self.add_legal_moves_rq(&mut moves, y, x, color_bit);
-                            }
-                        }
-                    }
-                }
-                self.add_legal_moves_en_passant(&mut moves);
-                // make careful search if in check for each move for every piece!
-                let mut i = 0;
-                let mut len = moves.len();
-                while i < len {
-                    self.make_move(&moves[i]);
-                    let current_king: &Coord = self.get_current_king_coord(false);
-                    if self.is_under_attack(current_king.y(), current_king.x(), self.white_to_move, [true; 5]) {
-                        moves[i] = moves[len - 1];
-                        moves.pop();
-                        len -= 1;
-                    } else {
-                        if save {
-                            self.add_check_bits(&mut moves[i]);
-                        }
-                        i += 1;
-                    }
-                    self.revert_move();
-                }
-            },
-            Check::NotInCheck => {
-                // still scan for any pseudo-legal moves
-                for y in 0..8 {
-                    for x in 0..8 {
-                        if self.field[y as usize][x as usize] > 1 {
-                            let piece = self.field[y as usize][x as usize] - color_bit;
-                            if piece == self.gpl(&'p') {
-                                self.add_legal_moves_p(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'k') {
-                                self.add_legal_moves_k(&mut moves, y, x, color_bit, Some(Check::NotInCheck));
-                            } else if piece == self.gpl(&'n') {
-                                self.add_legal_moves_n(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'b') {
-                                self.add_legal_moves_bq(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'r') {
-                                self.add_legal_moves_rq(&mut moves, y, x, color_bit);
-                            } else if piece == self.gpl(&'q') {
-                                self.add_legal_moves_bq(&mut moves, y, x, color_bit);
-                                self.add_legal_moves_rq(&mut moves, y, x, color_bit);
-                            }
-                        }
-                    }
-                }
-                self.add_legal_moves_en_passant(&mut moves);
-                // make simple search on if in check
-                let mut i = 0;
-                let mut len = moves.len();
-                while i < len {
-                    self.make_move(&moves[i]);
-                    let current_king: &Coord = self.get_current_king_coord(false);
-
-                    // if it's not a king's move, b/r/q search will be sufficient, but otherwise...
-                    let mut checks = [true, true, false, false, false];
-                    if self.field[moves[i].to.y() as usize][moves[i].to.x() as usize] == self.gpl(&'k') + !self.white_to_move as u8 {
-                        checks[2] = true;
-                        checks[3] = true;
-                        checks[4] = true;
-                    }
+        let (legality, check) = self.get_legality(current_king_check_status);
 
-                    if self.is_under_attack(current_king.y(), current_king.x(), self.white_to_move, checks) {
-                        moves[i] = moves[len - 1];
-                        moves.pop();
-                        len -= 1;
-                    } else {
-                        if save {
-                            self.add_check_bits(&mut moves[i]);
-                        }
-                        i += 1;
-                    }
-                    self.revert_move();
-                }
-            },
-            Check::InDoubleCheck => {
-                // now only king can move
-                let current_king: Coord = *self.get_current_king_coord(false);
-                self.add_legal_moves_k(&mut moves, current_king.y(), current_king.x(), self.white_to_move as u8, Some(Check::InDoubleCheck));
-                // make full search on if in check
-                let mut i = 0;
-                let mut len = moves.len();
-                while i < len {
-                    self.make_move(&moves[i]);
-                    if self.is_under_attack(current_king.y(), current_king.x(), self.white_to_move, [true; 5]) {
-                        moves[i] = moves[len - 1];
-                        moves.pop();
-                        len -= 1;
-                    } else {
-                        if save {
-                            self.add_check_bits(&mut moves[i]);
-                        }
-                        i += 1;
+        if check == Check::InDoubleCheck {
+            // double check: only the king can move
+            self.add_legal_moves_k(&mut moves, legality.king.y(), legality.king.x(), color_bit, Some(Check::InDoubleCheck), stage);
+        } else {
+            // scan for pseudo-legal moves, in check the pieces besides the king only capture the checker or block its line
+            for y in 0..8 {
+                for x in 0..8 {
+                    if self.field[y as usize][x as usize] > 1 && self.field[y as usize][x as usize] & 1 == color_bit {
+                        self.add_piece_moves(&mut moves, y, x, check, stage, legality.evasions);
                     }
-                    self.revert_move();
                 }
             }
+            self.add_legal_moves_en_passant(&mut moves, stage);
         }
 
+        moves.retain(|mov| self.is_generated_move_legal(mov, &legality));
+        if save {
+            for mov in moves.iter_mut() {
+                self.add_check_bits(mov, legality.occupied);
+            }
+        }
         moves
     }
 
+    // Checkers and pinned pieces of the side to move, with its check status.
+    // Known check status (check bits of the last move) saves the search of checkers when not in check.
+    fn get_legality(&self, current_king_check_status: Option<Check>) -> (Legality, Check) {
+        let king: Coord = *self.get_current_king_coord(true);
+        let occupied = self.get_occupied();
+        let enemies = self.get_occupied_by(!self.white_to_move);
+        let checkers = match current_king_check_status.unwrap_or(Check::Unknown) {
+            Check::NotInCheck => 0,
+            _ => self.get_attackers_map(king.y(), king.x(), occupied) & enemies
+        };
+        // in check, other pieces may only capture the checker or block its line; in double check they can't move at all
+        let (evasions, check) = match checkers.count_ones() {
+            0 => (u64::MAX, Check::NotInCheck),
+            1 => (checkers | Self::get_between(king, Self::get_coords(checkers)[0]), Check::InCheck),
+            _ => (0, Check::InDoubleCheck)
+        };
+        (Legality { king, occupied, enemies, evasions, pins: self.get_pins(self.white_to_move) }, check)
+    }
+
+    // add pseudo-legal moves of the piece of the side to move on (y, x), en passant ones are added separately
+    // (only the ones to the target squares: evasions in check, see Legality)
+    fn add_piece_moves(&self, moves: &mut Vec<Mov>, y: u8, x: u8, check: Check, stage: Stage, targets: u64) {
+        let color_bit = self.field[y as usize][x as usize] & 1;
+        let piece = self.field[y as usize][x as usize] - color_bit;
+        if piece == self.gpl(&'p') {
+            self.add_legal_moves_p(moves, y, x, color_bit, stage, targets);
+        } else if piece == self.gpl(&'k') {
+            self.add_legal_moves_k(moves, y, x, color_bit, Some(check), stage);
+        } else if piece == self.gpl(&'n') {
+            self.add_legal_moves_n(moves, y, x, color_bit, stage, targets);
+        } else if piece == self.gpl(&'b') {
+            self.add_legal_moves_bq(moves, y, x, color_bit, stage, targets);
+        } else if piece == self.gpl(&'r') {
+            self.add_legal_moves_rq(moves, y, x, color_bit, stage, targets);
+        } else if piece == self.gpl(&'q') {
+            self.add_legal_moves_bq(moves, y, x, color_bit, stage, targets);
+            self.add_legal_moves_rq(moves, y, x, color_bit, stage, targets);
+        }
+    }
+
+    // true if the move (from the user, a protocol, a file) can be made on the board, see validate_move
+    pub fn is_legal(&self, mov: &Mov) -> bool {
+        self.validate_move(mov).is_ok()
+    }
+
+    // Check the move (as move_to_board makes it) and return the same move as generated by get_legal_moves,
+    // with its check bits. Check bits of the given move don't matter, everything else must be right.
+    pub fn validate_move(&self, mov: &Mov) -> Result<Mov, MoveError> {
+        let (from, to) = (mov.from, mov.to);
+        if from.y() > 7 || from.x() > 7 || to.y() > 7 || to.x() > 7 {
+            return Err(MoveError::OutOfBoard);
+        }
+        let piece = self.field[from.y() as usize][from.x() as usize];
+        if piece < 2 {
+            return Err(MoveError::NoPiece);
+        }
+        if piece & 1 != self.white_to_move as u8 {
+            return Err(MoveError::NotYourPiece);
+        }
+        let target = self.field[to.y() as usize][to.x() as usize];
+        if target > 1 && target & 1 == piece & 1 {
+            return Err(MoveError::OwnPieceCaptured);
+        }
+
+        let (legality, check) = self.get_legality(None);
+        let mut moves: Vec<Mov> = Vec::default();
+        self.add_piece_moves(&mut moves, from.y(), from.x(), if check == Check::InDoubleCheck { Check::InCheck } else { check }, Stage::All, u64::MAX);
+        self.add_legal_moves_en_passant(&mut moves, Stage::All);
+        let is_pawn = piece & 254 == self.gpl(&'p');
+        let is_castling = piece & 254 == self.gpl(&'k') && from.x().abs_diff(to.x()) == 2 && from.y() == to.y();
+        let promotes = is_pawn && (to.y() == 0 || to.y() == 7);
+        let Some(mut generated) = moves.into_iter()
+            .find(|m| m.from == from && m.to == to && (!promotes || self.rtpc(m.data) == self.rtpc(mov.data))) else {
+            return Err(if is_castling { MoveError::CastlingNotAllowed } else { MoveError::ImpossibleMove });
+        };
+        // flags must be the ones the board expects, make_move relies on them
+        if promotes && mov.data & 1 == 0 {
+            return Err(MoveError::PromotionMissing);
+        }
+        if generated.data & 1 != mov.data & 1 {
+            return Err(MoveError::WrongFlags);
+        }
+        if self.ptpv(generated.data) != self.ptpv(mov.data) {
+            return Err(MoveError::WrongCapture);
+        }
+        if !self.is_generated_move_legal(&generated, &legality) {
+            return Err(MoveError::KingInCheck);
+        }
+        self.add_check_bits(&mut generated, legality.occupied);
+        Ok(generated)
+    }
+
+    // make the move only if it's legal, otherwise the board stays as it is
+    pub fn try_make_move(&mut self, mov: &Mov) -> Result<(), MoveError> {
+        let mov = self.validate_move(mov)?;
+        self.make_move(&mov);
+        Ok(())
+    }
+
+    // pseudo-legal move of the side to move is legal if it doesn't leave its king in check
+    fn is_generated_move_legal(&self, mov: &Mov, legality: &Legality) -> bool {
+        let target = Self::square_bit(mov.to.y(), mov.to.x());
+        let piece = self.field[mov.from.y() as usize][mov.from.x() as usize];
+        if mov.from == legality.king {
+            // castlings are checked on generation, otherwise the square must be safe with the king gone from its own
+            let occupied = legality.occupied & !Self::square_bit(legality.king.y(), legality.king.x());
+            return mov.data & 1 == 1 || self.get_attackers_map(mov.to.y(), mov.to.x(), occupied) & legality.enemies & !target == 0;
+        }
+        if mov.data & 1 == 1 && piece & 254 == self.gpl(&'p') && mov.to.y() != 0 && mov.to.y() != 7 {
+            // en passant removes two pawns from the same rank, so it can't be told by the pins
+            let captured = Self::square_bit(mov.from.y(), mov.to.x());
+            let occupied = (legality.occupied & !Self::square_bit(mov.from.y(), mov.from.x()) & !captured) | target;
+            return self.get_attackers_map(legality.king.y(), legality.king.x(), occupied) & legality.enemies & !captured == 0;
+        }
+        legality.evasions & target > 0 && legality.pins.iter().all(|(pinned, line)| *pinned != mov.from || line & target > 0)
+    }
+
     pub fn get_current_king_coord(& self, is_current_color: bool) -> &Coord {
         // if white_to_move and current_color, we are searching for black pieces attacking white king
         let color = is_current_color ^ self.white_to_move;
@@ -454,7 +583,7 @@ impl Board {
     // check if opponent's knight is attacking this cell
     fn is_under_attack_n(& self, y: u8, x: u8, color_bit: u8) -> bool {
         for i in 1..3 {
-            if Self::in_bound(y + 3, x + i, i, 0) && self.field[(y + 3 - i) as usize][(x + i)  as usize] == self.gpl(&'b') + color_bit {
+            if Self::in_bound(y + 3, x + i, i, 0) && self.field[(y + 3 - i) as usize][(x + i)  as usize] == self.gpl(&'n') + color_bit {
                 return true;
             }
             if Self::in_bound(y, x + 3, i, i) && self.field[(y - i) as usize][(x + 3 - i) as usize] == self.gpl(&'n') + color_bit {
@@ -634,7 +763,7 @@ impl Board {
     }
 
     // if possible square for a knight is empty or has a piece of not a color_bit color, this move will be added
-    fn add_legal_moves_n(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8) {
+    fn add_legal_moves_n(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, stage: Stage, targets: u64) {
         let mut coord: Coord;
         let mut piece: u8;
         for i in 1..3 {
@@ -642,8 +771,10 @@ impl Board {
                 coord = Coord::new(y + 3 - i, x + i);
                 piece = self.field[coord.y() as usize][coord.x() as usize];
                 if piece < 2 {
-                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
-                } else if piece & 1 != color_bit {
+                    if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                    }
+                } else if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece) , from: Coord::new(y, x), to: coord});
                 }
             }
@@ -651,8 +782,10 @@ impl Board {
                 coord = Coord::new(y - i, x + 3 - i);
                 piece = self.field[coord.y() as usize][coord.x() as usize];
                 if piece < 2 {
-                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
-                } else if piece & 1 != color_bit {
+                    if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                    }
+                } else if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
             }
@@ -660,8 +793,10 @@ impl Board {
                 coord = Coord::new(y + i - 3, x - i);
                 piece = self.field[coord.y() as usize][coord.x() as usize];
                 if piece < 2 {
-                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
-                } else if piece & 1 != color_bit {
+                    if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                    }
+                } else if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
             }
@@ -669,8 +804,10 @@ impl Board {
                 coord = Coord::new(y + i, x + i - 3);
                 piece = self.field[coord.y() as usize][coord.x() as usize];
                 if piece < 2 {
-                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
-                } else if piece & 1 != color_bit {
+                    if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                    }
+                } else if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
             }
@@ -678,7 +815,7 @@ impl Board {
     }
 
     // add all possible diagonal moves from (y, x) to vec, including captures
-    fn add_legal_moves_bq(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8) {
+    fn add_legal_moves_bq(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, stage: Stage, targets: u64) {
         let mut i: u8 = 1;
         let mut coord: Coord;
         let mut piece: u8;
@@ -687,9 +824,11 @@ impl Board {
             piece = self.field[(y - i) as usize][(x - i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -701,9 +840,11 @@ impl Board {
             piece = self.field[(y - i) as usize][(x + i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -715,9 +856,11 @@ impl Board {
             piece = self.field[(y + i) as usize][(x + i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -729,9 +872,11 @@ impl Board {
             piece = self.field[(y + i) as usize][(x - i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -740,7 +885,7 @@ impl Board {
     }
 
     // add all possible straight moves from (y, x) to vec, including captures
-    fn add_legal_moves_rq(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8) {
+    fn add_legal_moves_rq(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, stage: Stage, targets: u64) {
         let mut i: u8 = 1;
         let mut coord: Coord;
         let mut piece: u8;
@@ -749,9 +894,11 @@ impl Board {
             piece = self.field[(y - i) as usize][x as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -763,9 +910,11 @@ impl Board {
             piece = self.field[(y + i) as usize][x as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -777,9 +926,11 @@ impl Board {
             piece = self.field[y as usize][(x + i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -791,9 +942,11 @@ impl Board {
             piece = self.field[y as usize][(x - i) as usize];
             i += 1;
             if piece < 2 {
-                vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                if stage.has_quiets() && Self::is_target(targets, coord.y(), coord.x()) {
+                    vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                }
             } else {
-                if piece & 1 != color_bit {
+                if piece & 1 != color_bit && stage.has_captures() && Self::is_target(targets, coord.y(), coord.x()) {
                     vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                 }
                 break;
@@ -802,7 +955,8 @@ impl Board {
     }
 
     // add all possible king moves from (y, x) to vec, including captures and castlings
-    fn add_legal_moves_k(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, check_status: Option<Check>) {
+    #[allow(clippy::collapsible_if)]
+    fn add_legal_moves_k(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, check_status: Option<Check>, stage: Stage) {
         let mut coord: Coord;
         let mut piece: u8;
         for i in 0..3 {
@@ -811,8 +965,10 @@ impl Board {
                     coord = Coord::new(y + i - 1, x + j - 1);
                     piece = self.field[(y + i - 1) as usize][(x + j - 1) as usize];
                     if piece < 2 {
-                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
-                    } else if piece & 1 != color_bit {
+                        if stage.has_quiets() {
+                            vec.push(Mov{data: 0, from: Coord::new(y, x), to: coord});
+                        }
+                    } else if piece & 1 != color_bit && stage.has_captures() {
                         vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: coord});
                     }
                 }
@@ -820,7 +976,7 @@ impl Board {
         }
         // this will generate not a pseudolegal move, maybe should be optimized and NOT check if king will be in check after castling
         let check: Check = check_status.unwrap_or(Check::Unknown);
-        if check == Check::NotInCheck || check == Check::Unknown {
+        if stage.has_quiets() && (check == Check::NotInCheck || check == Check::Unknown) {
             if color_bit == 1 {
                 if self.castling & self.gcl(&'K') > 0 && self.field[0][5] < 2 && self.field[0][6] < 2 {
                     if !(self.is_under_attack(0, 5, false, [true; 5]) || self.is_under_attack(0, 6, false, [true; 5])) {
@@ -856,13 +1012,17 @@ impl Board {
     }
 
     // add all possible pawn moves from (y, x) to vec, including captures, promotions and en passant
-    fn add_legal_moves_p(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8) {
+    fn add_legal_moves_p(& self, vec: &mut Vec<Mov>, y: u8, x: u8, color_bit: u8, stage: Stage, targets: u64) {
         let mut piece: u8;
         // it's not totally different, just vertical mirrored, TODO: make it more simple!
         if color_bit == 1 {
             // promotion, promotion x capture
             if y == 6 {
-                if self.field[7][x as usize] < 2 {
+                // promotions are in the capture stage
+                if !stage.has_captures() {
+                    return;
+                }
+                if self.field[7][x as usize] < 2 && Self::is_target(targets, 7, x) {
                     vec.push(Mov{data: self.grls(&'q') | 1, from: Coord::new(y, x), to: Coord::new(7, x)});
                     vec.push(Mov{data: self.grls(&'n') | 1, from: Coord::new(y, x), to: Coord::new(7, x)});
                     vec.push(Mov{data: self.grls(&'r') | 1, from: Coord::new(y, x), to: Coord::new(7, x)});
@@ -870,7 +1030,7 @@ impl Board {
                 }
                 if Self::in_bound_single(x, 1) {
                     piece = self.field[7][(x - 1) as usize];
-                    if piece > 1 && piece & 1 == 0 {
+                    if piece > 1 && piece & 1 == 0 && Self::is_target(targets, 7, x - 1) {
                         vec.push(Mov{data: self.grls(&'q') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x - 1)});
                         vec.push(Mov{data: self.grls(&'n') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x - 1)});
                         vec.push(Mov{data: self.grls(&'r') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x - 1)});
@@ -879,7 +1039,7 @@ impl Board {
                 }
                 if Self::in_bound_single(x + 1, 0) {
                     piece = self.field[7][(x + 1) as usize];
-                    if piece > 1 && piece & 1 == 0 {
+                    if piece > 1 && piece & 1 == 0 && Self::is_target(targets, 7, x + 1) {
                         vec.push(Mov{data: self.grls(&'q') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x + 1)});
                         vec.push(Mov{data: self.grls(&'n') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x + 1)});
                         vec.push(Mov{data: self.grls(&'r') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(7, x + 1)});
@@ -890,23 +1050,25 @@ impl Board {
                 // 1 move forward
                 // Note: this additional in_bound check might be useless (case: there is a pawn at y=8)
                 if Self::in_bound_single(y + 1, 0) {
-                    if self.field[(y + 1) as usize][x as usize] < 2 {
-                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(y + 1, x)});
+                    if self.field[(y + 1) as usize][x as usize] < 2 && stage.has_quiets() {
+                        if Self::is_target(targets, y + 1, x) {
+                            vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(y + 1, x)});
+                        }
                         // 2 moves forward
-                        if y == 1 && self.field[3][x as usize] < 2 {
+                        if y == 1 && self.field[3][x as usize] < 2 && Self::is_target(targets, 3, x) {
                             vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(3, x)});
                         }
                     }
                     // simple captures
                     if Self::in_bound_single(x, 1) {
                         piece = self.field[(y + 1) as usize][(x - 1) as usize];
-                        if piece > 1 && piece & 1 == 0 {
+                        if piece > 1 && piece & 1 == 0 && stage.has_captures() && Self::is_target(targets, y + 1, x - 1) {
                             vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: Coord::new(y + 1, x - 1)});
                         }
                     }
                     if Self::in_bound_single(x + 1, 0) {
                         piece = self.field[(y + 1) as usize][(x + 1) as usize];
-                        if piece > 1 && piece & 1 == 0 {
+                        if piece > 1 && piece & 1 == 0 && stage.has_captures() && Self::is_target(targets, y + 1, x + 1) {
                             vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to:  Coord::new(y + 1, x + 1)});
                         }
                     }
@@ -916,7 +1078,11 @@ impl Board {
             // basically copy-paste
             // promotion, promotion x capture
             if y == 1 {
-                if self.field[0][x as usize] < 2 {
+                // promotions are in the capture stage
+                if !stage.has_captures() {
+                    return;
+                }
+                if self.field[0][x as usize] < 2 && Self::is_target(targets, 0, x) {
                     vec.push(Mov{data: self.grls(&'q') | 1, from: Coord::new(y, x), to: Coord::new(0, x)});
                     vec.push(Mov{data: self.grls(&'n') | 1, from: Coord::new(y, x), to: Coord::new(0, x)});
                     vec.push(Mov{data: self.grls(&'r') | 1, from: Coord::new(y, x), to: Coord::new(0, x)});
@@ -924,7 +1090,7 @@ impl Board {
                 }
                 if Self::in_bound_single(x, 1) {
                     piece = self.field[0][(x - 1) as usize];
-                    if piece > 1 && piece & 1 == 1 {
+                    if piece > 1 && piece & 1 == 1 && Self::is_target(targets, 0, x - 1) {
                         vec.push(Mov{data: self.grls(&'q') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x - 1)});
                         vec.push(Mov{data: self.grls(&'n') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x - 1)});
                         vec.push(Mov{data: self.grls(&'r') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x - 1)});
@@ -933,7 +1099,7 @@ impl Board {
                 }
                 if Self::in_bound_single(x + 1, 0) {
                     piece = self.field[0][(x + 1) as usize];
-                    if piece > 1 && piece & 1 == 1 {
+                    if piece > 1 && piece & 1 == 1 && Self::is_target(targets, 0, x + 1) {
                         vec.push(Mov{data: self.grls(&'q') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x + 1)});
                         vec.push(Mov{data: self.grls(&'n') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x + 1)});
                         vec.push(Mov{data: self.grls(&'r') | self.psav(piece) | 1, from: Coord::new(y, x), to: Coord::new(0, x + 1)});
@@ -944,23 +1110,25 @@ impl Board {
                 // 1 move forward
                 // Note: this additional in_bound check might be useless (case: there is a pawn at y=8)
                 if Self::in_bound_single(y, 1) {
-                    if self.field[(y - 1) as usize][x as usize] < 2 {
-                        vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(y - 1, x)});
+                    if self.field[(y - 1) as usize][x as usize] < 2 && stage.has_quiets() {
+                        if Self::is_target(targets, y - 1, x) {
+                            vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(y - 1, x)});
+                        }
                         // 2 moves forward
-                        if y == 6 && self.field[4][x as usize] < 2 {
+                        if y == 6 && self.field[4][x as usize] < 2 && Self::is_target(targets, 4, x) {
                             vec.push(Mov{data: 0, from: Coord::new(y, x), to: Coord::new(4, x)});
                         }
                     }
                     // simple captures
                     if Self::in_bound_single(x, 1) {
                         piece = self.field[(y - 1) as usize][(x - 1) as usize];
-                        if piece > 1 && piece & 1 == 1 {
+                        if piece > 1 && piece & 1 == 1 && stage.has_captures() && Self::is_target(targets, y - 1, x - 1) {
                             vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: Coord::new(y - 1, x - 1)});
                         }
                     }
                     if Self::in_bound_single(x + 1, 0) {
                         piece = self.field[(y - 1) as usize][(x + 1) as usize];
-                        if piece > 1 && piece & 1 == 1 {
+                        if piece > 1 && piece & 1 == 1 && stage.has_captures() && Self::is_target(targets, y - 1, x + 1) {
                             vec.push(Mov{data: self.psav(piece), from: Coord::new(y, x), to: Coord::new(y - 1, x + 1)});
                         }
                     }
@@ -969,54 +1137,70 @@ impl Board {
         }
     }
 
+    // true if the side to move has a pawn next to the en passant square (a pin is not taken into account)
+    pub fn is_en_passant_possible(&self) -> bool {
+        let (y, x) = (self.en_passant.y(), self.en_passant.x() as usize);
+        let (row, pawn) = match y {
+            5 => (4, self.gpl(&'P')),
+            2 => (3, self.gpl(&'p')),
+            _ => return false
+        };
+        (x > 0 && self.field[row][x - 1] == pawn) || (x < 7 && self.field[row][x + 1] == pawn)
+    }
+
     // it's better to have it outside of add_legal_moves_p function
-    fn add_legal_moves_en_passant(& self, vec: &mut Vec<Mov>) {
-        if self.en_passant.y() < 8 {
+    fn add_legal_moves_en_passant(& self, vec: &mut Vec<Mov>, stage: Stage) {
+        if self.en_passant.y() < 8 && stage.has_captures() {
             if self.en_passant.y() == 5 {
                 if Board::in_bound_single(self.en_passant.x() + 1, 0) && self.field[4][self.en_passant.x() as usize + 1] == self.gpl(&'P') {
                     vec.push(Mov{ data: self.gpls(&'p') | 1, from: Coord::new(4, self.en_passant.x() + 1), to: self.en_passant });
-                } else if Board::in_bound_single(self.en_passant.x(), 1) && self.field[4][self.en_passant.x() as usize - 1] == self.gpl(&'P') {
+                }
+                if Board::in_bound_single(self.en_passant.x(), 1) && self.field[4][self.en_passant.x() as usize - 1] == self.gpl(&'P') {
                     vec.push(Mov{ data: self.gpls(&'p') | 1, from: Coord::new(4, self.en_passant.x() - 1), to: self.en_passant });
                 }
             } else if self.en_passant.y() == 2 {
                 if Board::in_bound_single(self.en_passant.x() + 1, 0) && self.field[3][self.en_passant.x() as usize + 1] == self.gpl(&'p') {
                     vec.push(Mov{ data: self.gpls(&'p') | 1, from: Coord::new(3, self.en_passant.x() + 1), to: self.en_passant });
-                } else if Board::in_bound_single(self.en_passant.x(), 1) && self.field[3][self.en_passant.x() as usize - 1] == self.gpl(&'p') {
+                }
+                if Board::in_bound_single(self.en_passant.x(), 1) && self.field[3][self.en_passant.x() as usize - 1] == self.gpl(&'p') {
                     vec.push(Mov{ data: self.gpls(&'p') | 1, from: Coord::new(3, self.en_passant.x() - 1), to: self.en_passant });
                 }
             }
         }
     }
 
-    // call this before reverting (a LEGAL move)
-    fn add_check_bits(& self, mov: &mut Mov) {
-        let piece = self.field[mov.to.y() as usize][mov.to.x() as usize] - !self.white_to_move as u8;
-        let current_king: &Coord = self.get_current_king_coord(true);
-        if piece == self.gpl(&'b') || piece == self.gpl(&'r') || piece == self.gpl(&'q') {
-            if self.is_under_attack(current_king.y(), current_king.x(), !self.white_to_move, [true, true, false, false, false]) {
-                mov.data |= self.bimaps.bit_check;
-                // temporary impossible to trace double check in this case, see TODOs
-            }
-        } else if piece == self.gpl(&'k') {
-            if self.is_under_attack(current_king.y(), current_king.x(), !self.white_to_move, [true, true, false, false, false]) {
-                mov.data |= self.bimaps.bit_check;
-            }
-        } else if piece == self.gpl(&'p') {
-            if self.is_under_attack(current_king.y(), current_king.x(), !self.white_to_move, [true, true, false, false, true]) {
-                mov.data |= self.bimaps.bit_check;
-            }
-        } else if piece == self.gpl(&'n') {
-            if self.is_under_attack(current_king.y(), current_king.x(), !self.white_to_move, [true, true, false, false, false]) {
-                mov.data |= self.bimaps.bit_check;
+    // Set check bits of a legal move of the side to move (before it's made): pieces checking the opponent's king
+    // after the move are counted, so discovered checks (en passant ones too), castling rook checks and double checks
+    // are all found
+    fn add_check_bits(& self, mov: &mut Mov, occupied: u64) {
+        let king: &Coord = self.get_current_king_coord(false);
+        let piece = self.field[mov.from.y() as usize][mov.from.x() as usize];
+        let mut occupied = (occupied & !Self::square_bit(mov.from.y(), mov.from.x())) | Self::square_bit(mov.to.y(), mov.to.x());
+        // pieces on their new squares: the moved one (promoted one) and the castling rook
+        let mut moved: Vec<(u8, Coord)> = vec![(piece, mov.to)];
+        if mov.data & 1 == 1 {
+            if piece & 254 == self.gpl(&'k') {
+                let (y, rook_x, rook_to_x) = if mov.to.x() == 6 { (mov.to.y(), 7, 5) } else { (mov.to.y(), 0, 3) };
+                occupied = (occupied & !Self::square_bit(y, rook_x)) | Self::square_bit(y, rook_to_x);
+                moved.push((self.field[y as usize][rook_x as usize], Coord::new(y, rook_to_x)));
+            } else if self.is_promotion(mov) {
+                moved[0].0 = self.rtpv(mov.data);
+            } else {
+                occupied &= !Self::square_bit(mov.from.y(), mov.to.x());
             }
-            if self.is_under_attack(current_king.y(), current_king.x(), !self.white_to_move, [false, false, true, false, false]) {
-                if mov.data & self.bimaps.bit_check > 0 {
-                    mov.data |= self.bimaps.bit_double_check;
-                } else {
-                    mov.data |= self.bimaps.bit_check;
-                }
+        }
+        // discovered ones: the rest of pieces stay where they are
+        let mut checkers = self.get_attackers_map(king.y(), king.x(), occupied) & self.get_occupied_by(self.white_to_move) & occupied;
+        for (piece, coord) in moved {
+            if self.get_attacks_of(piece, coord.y(), coord.x(), occupied) & Self::square_bit(king.y(), king.x()) > 0 {
+                checkers |= Self::square_bit(coord.y(), coord.x());
             }
         }
+        match checkers.count_ones() {
+            0 => (),
+            1 => mov.data |= self.bimaps.bit_check,
+            _ => mov.data |= self.bimaps.bit_check | self.bimaps.bit_double_check
+        }
     }
 
     // addon methods to simplify work with the board
@@ -1084,16 +1268,19 @@ impl Board {
         !(y > 7 + y_sub || x > 7 + x_sub || y_sub > y || x_sub > x)
     }
     
+    // true if the square is among the target ones (see add_piece_moves)
+    fn is_target(targets: u64, y: u8, x: u8) -> bool {
+        targets & Self::square_bit(y, x) > 0
+    }
+
     pub fn in_bound_single(val: u8, sub: u8) -> bool {
         !(val > 7 + sub || sub > val)
     }
     
     fn get_default_board(bimaps: &Bimaps) -> [[u8; 8]; 8] {
         let mut field = [[0; 8]; 8];
-        for i in 0..8 {
-            field[1][i] = *bimaps.pieces.get_by_left(&'P').unwrap();
-            field[6][i] = *bimaps.pieces.get_by_left(&'p').unwrap();
-        }
+        field[1] = [*bimaps.pieces.get_by_left(&'P').unwrap(); 8];
+        field[6] = [*bimaps.pieces.get_by_left(&'p').unwrap(); 8];
         field[0][0] = *bimaps.pieces.get_by_left(&'R').unwrap();
         field[0][1] = *bimaps.pieces.get_by_left(&'N').unwrap();
         field[0][2] = *bimaps.pieces.get_by_left(&'B').unwrap();
@@ -1146,6 +1333,17 @@ impl Board {
     }
 }
 
+// what legality of the generated moves depends on, found once per position
+struct Legality {
+    king: Coord,
+    occupied: u64,
+    enemies: u64,
+    // squares the pieces other than the king may move to
+    evasions: u64,
+    // pinned pieces with the squares they may move to (see get_pins)
+    pins: Vec<(Coord, u64)>
+}
+
 impl Default for Board {
     fn default() -> Self {
         Self::new()
@@ -1154,6 +1352,7 @@ impl Default for Board {
 
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::needless_borrow, clippy::len_zero, clippy::needless_range_loop)]
 mod tests {
     use super::*;
     use crate::utils::utils::move_to_board;
@@ -1162,53 +1361,178 @@ mod tests {
 
     #[test]
     fn test_board_get_legal_moves_01() {
-        let mut b = Board::parse_fen(&"r4nkr/1QRPPppq/2PB4/8/1n6/6N1/5PP1/1R4K1 w - - 0 1");
+        let b = Board::parse_fen(&"r4nkr/1QRPPppq/2PB4/8/1n6/6N1/5PP1/1R4K1 w - - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 42, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_02() {
-        let mut b = Board::parse_fen(&"r3k2r/pp1ppppp/8/8/2pP4/8/PPP1PPPP/R3K2R b KQkq d3 0 1");
+        let b = Board::parse_fen(&"r3k2r/pp1ppppp/8/8/2pP4/8/PPP1PPPP/R3K2R b KQkq d3 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 25, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_03() {
-        let mut b = Board::parse_fen(&"rnb1kb1r/pppppppp/4q3/8/8/3n4/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let b = Board::parse_fen(&"rnb1kb1r/pppppppp/4q3/8/8/3n4/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 1, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_04() {
-        let mut b = Board::parse_fen(&"rnbqkbnr/pp1ppppp/3N4/8/8/4Q3/PPPPPPPP/RNB1KB1R b KQkq - 0 1");
+        let b = Board::parse_fen(&"rnbqkbnr/pp1ppppp/3N4/8/8/4Q3/PPPPPPPP/RNB1KB1R b KQkq - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 0, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_05() {
-        let mut b = Board::parse_fen(&"r3k2r/pp1ppppp/8/8/2pP4/3n4/PPP1PPPP/R3K2R w KQkq - 0 1");
+        let b = Board::parse_fen(&"r3k2r/pp1ppppp/8/8/2pP4/3n4/PPP1PPPP/R3K2R w KQkq - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 5, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_06() {
-        let mut b = Board::parse_fen(&"5k2/5ppp/5PPP/8/8/8/4R3/4R1K1 w - - 0 1");
+        let b = Board::parse_fen(&"5k2/5ppp/5PPP/8/8/8/4R3/4R1K1 w - - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 27, true);
     }
 
     #[test]
     fn test_board_get_legal_moves_07() {
-        let mut b = Board::parse_fen(&"r3k2r/p3p2p/7n/3B4/8/8/P6P/R3K2R b KQkq - 0 1");
+        let b = Board::parse_fen(&"r3k2r/p3p2p/7n/3B4/8/8/P6P/R3K2R b KQkq - 0 1");
         let moves = b.get_legal_moves(None, None);
         assert_eq!(moves.len() == 17, true);
     }
 
+    // leaf nodes of the legal move tree; check bits of every move are compared with the position after it
+    fn perft(b: &mut Board, depth: u8) -> u64 {
+        let moves = b.get_legal_moves(None, Some(true));
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mov in moves {
+            b.make_move(&mov);
+            let king = *b.get_current_king_coord(true);
+            let checkers = b.get_attackers_map(king.y(), king.x(), b.get_occupied()) & b.get_occupied_by(!b.white_to_move);
+            let expected = match checkers.count_ones() {
+                0 => Check::NotInCheck,
+                1 => Check::InCheck,
+                _ => Check::InDoubleCheck
+            };
+            assert!(b.get_check(&mov.data) == expected, "wrong check bits of {}", move_to_user(b, &mov));
+            nodes += perft(b, depth - 1);
+            b.revert_move();
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_board_get_legal_moves_08() {
+        // well-known perft positions: pins, en passant discoveries, castling through attacks, promotions
+        for (fen, depth, nodes) in [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 3, 8902),
+            ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 3, 97862),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 4, 43238),
+            ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 3, 9467),
+            ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 3, 62379)
+        ] {
+            assert_eq!(perft(&mut Board::parse_fen(fen), depth), nodes, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_board_evasions_01() {
+        // the rook checks along the first rank: the knight only blocks it, the pawn can't help at all
+        let b = Board::parse_fen("4k3/8/8/8/8/2N5/7P/r3K3 w - - 0 1");
+        let (legality, check) = b.get_legality(None);
+        assert!(check == Check::InCheck);
+        let mut moves: Vec<Mov> = Vec::default();
+        b.add_piece_moves(&mut moves, 2, 2, check, Stage::All, legality.evasions);
+        b.add_piece_moves(&mut moves, 1, 7, check, Stage::All, legality.evasions);
+        let mut moves: Vec<String> = moves.iter().map(|m| move_to_user(&b, m)).collect();
+        moves.sort();
+        assert_eq!(moves, vec!["c3b1", "c3d1"]);
+        assert_eq!(b.get_legal_moves(None, None).len(), 5);
+    }
+
+    #[test]
+    fn test_board_get_legal_moves_staged_01() {
+        let b = Board::parse_fen("r3k2r/1P3ppp/7N/3pP3/8/8/8/4KB2 w kq d6 0 1");
+        let all: Vec<String> = b.get_legal_moves(None, Some(true)).iter().map(|m| move_to_user(&b, m)).collect();
+        let captures: Vec<String> = b.get_legal_moves_staged(None, Some(true), Stage::Captures).iter().map(|m| move_to_user(&b, m)).collect();
+        let quiets: Vec<String> = b.get_legal_moves_staged(None, Some(true), Stage::Quiets).iter().map(|m| move_to_user(&b, m)).collect();
+        // every move is in exactly one of the stages
+        assert_eq!([captures.clone(), quiets.clone()].concat().len(), all.len());
+        assert!(all.iter().all(|m| captures.contains(m) != quiets.contains(m)));
+        assert!(captures.contains(&"b7a8q".to_string()) && captures.contains(&"b7b8n".to_string()) && captures.contains(&"h6f7".to_string()) && captures.contains(&"e5d6".to_string()));
+        assert!(quiets.contains(&"f1b5".to_string()) && quiets.contains(&"e5e6".to_string()) && !captures.contains(&"e5e6".to_string()));
+    }
+
+    #[test]
+    fn test_board_validate_move_01() {
+        let b = Board::new();
+        assert!(b.is_legal(&move_to_board(&b, "e2e4")) && b.is_legal(&move_to_board(&b, "g1f3")));
+        assert_eq!(b.validate_move(&move_to_board(&b, "e2e5")).err(), Some(MoveError::ImpossibleMove));
+        assert_eq!(b.validate_move(&move_to_board(&b, "e7e5")).err(), Some(MoveError::NotYourPiece));
+        assert_eq!(b.validate_move(&move_to_board(&b, "e3e4")).err(), Some(MoveError::NoPiece));
+        assert_eq!(b.validate_move(&move_to_board(&b, "d1d2")).err(), Some(MoveError::OwnPieceCaptured));
+        assert_eq!(b.validate_move(&Mov { data: 0, from: Coord::new(1, 4), to: Coord::new(9, 4) }).err(), Some(MoveError::OutOfBoard));
+        assert_eq!(b.validate_move(&Mov { data: b.gpls(&'n'), from: Coord::new(1, 4), to: Coord::new(3, 4) }).err(), Some(MoveError::WrongCapture));
+        // pinned rook, castling through a piece, through an attacked square
+        let b = Board::parse_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert_eq!(b.validate_move(&move_to_board(&b, "e2d2")).err(), Some(MoveError::KingInCheck));
+        assert!(b.is_legal(&move_to_board(&b, "e2e7")));
+        let b = Board::parse_fen("4k3/8/8/8/8/8/8/R3KB1R w KQ - 0 1");
+        assert_eq!(b.validate_move(&move_to_board(&b, "e1g1")).err(), Some(MoveError::CastlingNotAllowed));
+        assert!(b.is_legal(&move_to_board(&b, "e1c1")));
+        let b = Board::parse_fen("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1");
+        assert_eq!(b.validate_move(&move_to_board(&b, "e1g1")).err(), Some(MoveError::CastlingNotAllowed));
+        assert_eq!(b.validate_move(&move_to_board(&b, "e1f1")).err(), Some(MoveError::KingInCheck));
+        assert!(b.is_legal(&move_to_board(&b, "e1f2")));
+        // promotion, en passant flag
+        let b = Board::parse_fen("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(b.validate_move(&move_to_board(&b, "b7b8")).err(), Some(MoveError::PromotionMissing));
+        assert!(b.get_check(&b.validate_move(&move_to_board(&b, "b7b8q")).unwrap().data) == Check::InCheck);
+        assert!(b.is_legal(&move_to_board(&b, "e5d6")));
+        assert_eq!(b.validate_move(&Mov { data: 0, from: Coord::new(4, 4), to: Coord::new(5, 3) }).err(), Some(MoveError::WrongFlags));
+    }
+
+    #[test]
+    fn test_board_try_make_move_01() {
+        let mut b = Board::parse_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1");
+        let hash = b.hash;
+        assert_eq!(b.try_make_move(&move_to_board(&b, "e2d2")), Err(MoveError::KingInCheck));
+        assert!(b.hash == hash && b.history.is_empty() && b.white_to_move);
+        assert!(b.try_make_move(&move_to_board(&b, "e1d1")).is_ok());
+        assert_eq!(b.try_make_move(&move_to_board(&b, "e7e2")), Ok(()));
+        assert_eq!(b.history.len(), 2);
+    }
+
+    #[test]
+    fn test_board_check_bits_01() {
+        let checks = |fen: &str, mov: &str| {
+            let b = Board::parse_fen(fen);
+            let mov = b.get_legal_moves(None, Some(true)).into_iter().find(|m| move_to_user(&b, m) == mov).unwrap();
+            b.get_check(&mov.data)
+        };
+        // castling rook checks
+        assert!(checks("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1") == Check::InCheck);
+        assert!(checks("5k2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1") == Check::InCheck);
+        // discovered check, discovered double check
+        assert!(checks("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1", "e4c5") == Check::InCheck);
+        assert!(checks("4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1", "e4d6") == Check::InDoubleCheck);
+        // en passant removes both pawns from the rook's way
+        assert!(checks("8/8/8/k2pP2R/8/8/8/4K3 w - d6 0 1", "e5d6") == Check::InCheck);
+        // promotion piece gives check, or doesn't
+        assert!(checks("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q") == Check::InCheck);
+        assert!(checks("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n") == Check::NotInCheck);
+    }
+
     #[test]
     fn test_board_make_move_01() {
         let mut b = Board::new();
@@ -1236,4 +1560,51 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_board_parse_fen_en_passant_01() {
+        // file comes first, then rank
+        assert!(Board::parse_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3").en_passant == Coord::new(5, 3));
+        assert!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/6P1/8/PPPPPP1P/RNBQKBNR b KQkq g3 0 1").en_passant == Coord::new(2, 6));
+        assert!(Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").en_passant == Coord::new(8, 8));
+    }
+
+    #[test]
+    fn test_board_castling_rights_01() {
+        let mut b = Board::parse_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        // rook captures rook: both queenside rights are gone, kingside ones stay
+        b.make_move(&move_to_board(&b, "a1a8"));
+        assert_eq!(b.castling, b.gcl(&'K') | b.gcl(&'k'));
+        // rook move takes only its own right
+        b.make_move(&move_to_board(&b, "h8h5"));
+        assert_eq!(b.castling, b.gcl(&'K'));
+        b.revert_move();
+        b.revert_move();
+        assert_eq!(b.castling, 240);
+    }
+
+    #[test]
+    fn test_board_is_under_attack_n_01() {
+        // a knight on every square it can attack e5 from (y, x are field indexes)
+        for (dy, dx) in [(-2, -1), (-2, 1), (-1, -2), (-1, 2), (1, -2), (1, 2), (2, -1), (2, 1)] {
+            let mut b = Board::parse_fen("7k/8/8/4r3/8/8/8/K7 w - - 0 1");
+            assert!(!b.is_under_attack(3, 4, true, [true; 5]));
+            b.field[(3 + dy) as usize][(4 + dx) as usize] = b.gpl(&'N');
+            assert!(b.is_under_attack(3, 4, true, [true; 5]));
+            // a bishop there does not attack it
+            b.field[(3 + dy) as usize][(4 + dx) as usize] = b.gpl(&'B');
+            assert!(!b.is_under_attack(3, 4, true, [true; 5]));
+        }
+    }
+
+    #[test]
+    fn test_board_en_passant_both_sides_01() {
+        // pawns on both sides of the pawn that has just moved can take it
+        let b = Board::parse_fen("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1");
+        let moves: Vec<Mov> = b.get_legal_moves(None, None).into_iter().filter(|mov| mov.to == b.en_passant).collect();
+        assert_eq!(moves.len(), 2);
+        let b = Board::parse_fen("4k3/8/8/8/5pPp/8/8/4K3 b - g3 0 1");
+        let moves: Vec<Mov> = b.get_legal_moves(None, None).into_iter().filter(|mov| mov.to == b.en_passant).collect();
+        assert_eq!(moves.len(), 2);
+    }
+}
@@ -0,0 +1,228 @@
+use super::board::Board;
+use super::coord::Coord;
+use super::mov::{BoardMov, Mov};
+use super::zobrist::ZOBRIST;
+
+// Position editing (setup tools, tests), symmetric positions (evaluation checks) and null move (search).
+// Edits keep king locations, hash and counters consistent with the field: castling rights
+// and en passant that don't fit the new position are dropped. An edited position has no history to revert to.
+impl Board {
+    // Put the piece (as in FEN: 'P', 'n', ...) on the square and return the one that was there.
+    // There is only one king of each color, so a king is moved from its old square; a king can't be replaced, will panic!
+    pub fn put_piece(&mut self, y: u8, x: u8, piece: char) -> Option<char> {
+        let value = self.gpl(&piece);
+        if self.field[y as usize][x as usize] & 254 == self.gpl(&'k') && self.field[y as usize][x as usize] != value {
+            panic!("Attempt to replace a king, move it first");
+        }
+        if piece.eq_ignore_ascii_case(&'k') {
+            let king = if value & 1 == 1 { self.white_king_location } else { self.black_king_location };
+            if self.field[king.y() as usize][king.x() as usize] == value {
+                self.field[king.y() as usize][king.x() as usize] = 0;
+            }
+            if value & 1 == 1 {
+                self.white_king_location.set(y, x);
+            } else {
+                self.black_king_location.set(y, x);
+            }
+        }
+        let old = self.take_piece(y, x);
+        self.field[y as usize][x as usize] = value;
+        self.after_edit();
+        old
+    }
+
+    // Remove the piece from the square and return it. Kings can't be removed (only moved by put_piece),
+    // the board always has both of them, will panic!
+    pub fn remove_piece(&mut self, y: u8, x: u8) -> Option<char> {
+        if self.field[y as usize][x as usize] & 254 == self.gpl(&'k') {
+            panic!("Attempt to remove a king");
+        }
+        let old = self.take_piece(y, x);
+        self.after_edit();
+        old
+    }
+
+    // move counter keeps its parity with the side to move (see parse_fen)
+    pub fn set_white_to_move(&mut self, white_to_move: bool) {
+        if self.white_to_move != white_to_move {
+            self.white_to_move = white_to_move;
+            self.hno = if white_to_move { self.hno.saturating_sub(1) } else { self.hno + 1 };
+            self.after_edit();
+        }
+    }
+
+    // set castling rights (K = 128, Q = 64, k = 32, q = 16), return the ones that are possible and set
+    pub fn set_castling(&mut self, castling: u8) -> u8 {
+        self.castling = castling & 240;
+        self.after_edit();
+        self.castling
+    }
+
+    // set en passant square (None to drop it), return false if a pawn of the opponent couldn't have just passed it
+    pub fn set_en_passant(&mut self, en_passant: Option<Coord>) -> bool {
+        self.en_passant = en_passant.unwrap_or(Coord::new(8, 8));
+        self.after_edit();
+        en_passant.is_none_or(|coord| coord == self.en_passant)
+    }
+
+    // The same position with colors swapped: the field is mirrored vertically, white pieces become black ones
+    // and vice versa, so do castling rights, en passant and the side to move. Evaluations of the two must be opposite.
+    pub fn mirror(&self) -> Board {
+        let mut board = self.clone();
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = self.field[7 - y][x];
+                board.field[y][x] = if piece > 1 { piece ^ 1 } else { piece };
+            }
+        }
+        board.white_king_location.set(7 - self.black_king_location.y(), self.black_king_location.x());
+        board.black_king_location.set(7 - self.white_king_location.y(), self.white_king_location.x());
+        board.castling = ((self.castling >> 2) & 48) | ((self.castling << 2) & 192);
+        if self.en_passant.y() < 8 {
+            board.en_passant = Coord::new(7 - self.en_passant.y(), self.en_passant.x());
+        }
+        board.white_to_move = !self.white_to_move;
+        board.hno = if board.white_to_move { self.hno.saturating_sub(1) } else { self.hno + 1 };
+        board.after_edit();
+        board
+    }
+
+    // The same position mirrored horizontally (a-file becomes h-file), None if castling is still possible:
+    // kings and rooks wouldn't stand on their castling squares
+    pub fn flip(&self) -> Option<Board> {
+        if self.castling != 0 {
+            return None;
+        }
+        let mut board = self.clone();
+        for y in 0..8 {
+            for x in 0..8 {
+                board.field[y][x] = self.field[y][7 - x];
+            }
+        }
+        board.white_king_location.set(self.white_king_location.y(), 7 - self.white_king_location.x());
+        board.black_king_location.set(self.black_king_location.y(), 7 - self.black_king_location.x());
+        if self.en_passant.y() < 8 {
+            board.en_passant = Coord::new(self.en_passant.y(), 7 - self.en_passant.x());
+        }
+        board.after_edit();
+        Some(board)
+    }
+
+    // Pass the move to the opponent (the side to move must not be in check), revert_move takes it back
+    pub fn make_null_move(&mut self) {
+        debug_assert!({
+            let king = *self.get_current_king_coord(true);
+            !self.is_under_attack(king.y(), king.x(), !self.white_to_move, [true; 5])
+        }, "null move in check");
+        self.history.push(BoardMov{mov: Mov::null(), castling: self.castling, en_passant: self.en_passant, hmw: self.hmw, hash: self.hash});
+        self.hash ^= ZOBRIST.color() ^ ZOBRIST.en_passant(self);
+        self.en_passant = Coord::new(8, 8);
+        self.white_to_move = !self.white_to_move;
+        self.hmw += 1;
+        self.hno += 1;
+    }
+
+    // empty the square, keys are recalculated by after_edit()
+    fn take_piece(&mut self, y: u8, x: u8) -> Option<char> {
+        let old = self.field[y as usize][x as usize];
+        self.field[y as usize][x as usize] = 0;
+        if old > 1 {
+            Some(self.gpr(&old))
+        } else {
+            None
+        }
+    }
+
+    fn after_edit(&mut self) {
+        // castling needs the king and the rook on their initial squares
+        for (right, y, rook_x) in [('K', 0, 7), ('Q', 0, 0), ('k', 7, 7), ('q', 7, 0)] {
+            let color_bit = right.is_ascii_uppercase() as u8;
+            if self.field[y][4] != self.gpl(&'k') + color_bit || self.field[y][rook_x] != self.gpl(&'r') + color_bit {
+                self.castling &= !self.gcl(&right);
+            }
+        }
+        if !self.is_en_passant_consistent() {
+            self.en_passant = Coord::new(8, 8);
+        }
+        self.history.clear();
+        self.hash = ZOBRIST.hash(self);
+    }
+
+    // pawn of the opponent stands right behind the en passant square, the squares it has passed are empty
+    fn is_en_passant_consistent(&self) -> bool {
+        let (y, x) = (self.en_passant.y() as usize, self.en_passant.x() as usize);
+        match (y, self.white_to_move) {
+            (5, true) => self.field[4][x] == self.gpl(&'p') && self.field[5][x] < 2 && self.field[6][x] < 2,
+            (2, false) => self.field[3][x] == self.gpl(&'P') && self.field[2][x] < 2 && self.field[1][x] < 2,
+            _ => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::{board::Board, coord::Coord}, utils::utils::move_to_board};
+    use super::ZOBRIST;
+
+    // everything kept by the board matches the one of the same position made from FEN
+    fn assert_same(b: &Board, fen: &str) {
+        let expected = Board::parse_fen(fen);
+        assert!(b.field.iter().flatten().zip(expected.field.iter().flatten()).all(|(a, e)| a.max(&1) == e.max(&1)));
+        assert!(b.white_king_location == expected.white_king_location && b.black_king_location == expected.black_king_location);
+        assert!(b.white_to_move == expected.white_to_move && b.castling == expected.castling && b.en_passant == expected.en_passant);
+        assert!(b.hno == expected.hno && b.hmw == expected.hmw);
+        assert_eq!(b.hash, expected.hash);
+        assert_eq!(b.hash, ZOBRIST.hash(b));
+    }
+
+    #[test]
+    fn test_edit_put_remove_01() {
+        let mut b = Board::new();
+        assert_eq!(b.remove_piece(0, 7), Some('R'));
+        assert_eq!(b.put_piece(3, 4, 'P'), None);
+        assert_eq!(b.put_piece(6, 0, 'Q'), Some('p'));
+        // the king is moved, castling rights of the moved king are gone
+        assert_eq!(b.put_piece(4, 4, 'k'), None);
+        assert_same(&b, "rnbq1bnr/Qppppppp/8/4k3/4P3/8/PPPPPPPP/RNBQKBN1 w Q - 0 1");
+        assert!(b.history.is_empty());
+        assert!(!b.get_legal_moves(None, None).is_empty());
+    }
+
+    #[test]
+    fn test_edit_state_01() {
+        let mut b = Board::parse_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        assert!(!b.set_en_passant(Some(Coord::new(5, 4))));
+        assert!(b.set_en_passant(Some(Coord::new(5, 3))));
+        assert_same(&b, "r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+        // en passant belongs to the side to move
+        b.set_white_to_move(false);
+        assert_same(&b, "r3k2r/8/8/3pP3/8/8/8/R3K2R b KQkq - 0 1");
+        b.remove_piece(7, 0);
+        assert_eq!(b.set_castling(240), 224);
+        assert_same(&b, "4k2r/8/8/3pP3/8/8/8/R3K2R b KQk - 0 1");
+    }
+
+    #[test]
+    fn test_edit_mirror_flip_01() {
+        let b = Board::parse_fen("r3k2r/pp3ppp/8/3pP3/8/2N5/PPP2PPP/R3K1R1 w Qkq d6 0 12");
+        assert_same(&b.mirror(), "r3k1r1/ppp2ppp/2n5/8/3Pp3/8/PP3PPP/R3K2R b KQq d3 0 12");
+        assert_same(&b.mirror().mirror(), "r3k2r/pp3ppp/8/3pP3/8/2N5/PPP2PPP/R3K1R1 w Qkq d6 0 12");
+        assert!(b.flip().is_none());
+        let b = Board::parse_fen("8/2k5/8/3pP3/8/8/1K6/8 w - d6 3 40");
+        assert_same(&b.flip().unwrap(), "8/5k2/8/3Pp3/8/8/6K1/8 w - e6 3 40");
+    }
+
+    #[test]
+    fn test_edit_null_move_01() {
+        let mut b = Board::parse_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+        b.make_move(&move_to_board(&b, "g1f3"));
+        b.make_null_move();
+        assert_same(&b, "rnbqkbnr/ppp1pppp/8/3pP3/8/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 4");
+        b.make_null_move();
+        assert_same(&b, "rnbqkbnr/ppp1pppp/8/3pP3/8/5N2/PPPP1PPP/RNBQKB1R b KQkq - 3 4");
+        b.revert_move();
+        b.revert_move();
+        b.revert_move();
+        assert_same(&b, "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+    }
+}
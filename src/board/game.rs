@@ -0,0 +1,208 @@
+use super::board::Board;
+use super::mov::Mov;
+
+// Game record as a tree of moves: the main line and the variations branching off any position of it (or of them).
+// The board is always in the position of the current node, so the game can be browsed back and forth,
+// and a move played in the middle of it starts a variation instead of dropping the rest of the game.
+pub struct Game {
+    pub board: Board,
+    nodes: Vec<Node>,
+    current: usize
+}
+
+struct Node {
+    // move that leads to the node (null for the starting position)
+    mov: Mov,
+    parent: usize,
+    // the first one is the main continuation, the rest are variations
+    children: Vec<usize>,
+    // continuation followed by forward(), the last one played or visited
+    selected: usize
+}
+
+impl Game {
+    // mask of move data without the check bits, the same move may come with or without them
+    const MOVE_MASK: u8 = 63;
+
+    pub fn new(board: Board) -> Self {
+        Self {
+            board,
+            nodes: vec![Node { mov: Mov::null(), parent: 0, children: Vec::default(), selected: 0 }],
+            current: 0
+        }
+    }
+
+    // Make the (legal) move: follow it, if it's already in the tree, or add it as a new continuation
+    // (a variation, if there are others)
+    pub fn play(&mut self, mov: &Mov) {
+        let existing = self.nodes[self.current].children.iter().position(|&child| {
+            let other = &self.nodes[child].mov;
+            other.from == mov.from && other.to == mov.to && other.data & Self::MOVE_MASK == mov.data & Self::MOVE_MASK
+        });
+        let index = existing.unwrap_or_else(|| {
+            self.nodes.push(Node { mov: *mov, parent: self.current, children: Vec::default(), selected: 0 });
+            let node = self.nodes.len() - 1;
+            self.nodes[self.current].children.push(node);
+            self.nodes[self.current].children.len() - 1
+        });
+        self.enter(index);
+    }
+
+    // go one move back, false at the start
+    pub fn back(&mut self) -> bool {
+        if self.current == 0 {
+            return false;
+        }
+        self.board.revert_move();
+        self.current = self.nodes[self.current].parent;
+        true
+    }
+
+    // go one move forward along the selected continuation, false at the end
+    pub fn forward(&mut self) -> bool {
+        if self.nodes[self.current].children.is_empty() {
+            return false;
+        }
+        self.enter(self.nodes[self.current].selected);
+        true
+    }
+
+    pub fn go_to_start(&mut self) {
+        while self.back() {}
+    }
+
+    // go to the half-move of the current line (0 is the starting position), false if the line is shorter
+    // and it stops at the end
+    pub fn go_to_ply(&mut self, ply: usize) -> bool {
+        while self.ply() > ply {
+            self.back();
+        }
+        while self.ply() < ply && self.forward() {}
+        self.ply() == ply
+    }
+
+    // half-moves played from the starting position
+    pub fn ply(&self) -> usize {
+        let mut ply = 0;
+        let mut node = self.current;
+        while node != 0 {
+            node = self.nodes[node].parent;
+            ply += 1;
+        }
+        ply
+    }
+
+    // continuations of the current position: the main one first, then the variations
+    pub fn get_variations(&self) -> Vec<Mov> {
+        self.nodes[self.current].children.iter().map(|&child| self.nodes[child].mov).collect()
+    }
+
+    // go forward into the continuation by its index in get_variations(), false if there is no such one
+    pub fn enter_variation(&mut self, index: usize) -> bool {
+        if index >= self.nodes[self.current].children.len() {
+            return false;
+        }
+        self.enter(index);
+        true
+    }
+
+    // make the variation leading to the current position the main one (at every branching on the way)
+    pub fn promote_variation(&mut self) {
+        let mut node = self.current;
+        while node != 0 {
+            let parent = self.nodes[node].parent;
+            let children = &mut self.nodes[parent].children;
+            let index = children.iter().position(|&child| child == node).unwrap();
+            children[..=index].rotate_right(1);
+            self.nodes[parent].selected = 0;
+            node = parent;
+        }
+    }
+
+    // moves of the current line from the start to its end (following the selected continuations)
+    pub fn get_line(&self) -> Vec<Mov> {
+        let mut line: Vec<Mov> = Vec::default();
+        let mut node = self.current;
+        while node != 0 {
+            line.push(self.nodes[node].mov);
+            node = self.nodes[node].parent;
+        }
+        line.reverse();
+        node = self.current;
+        while let Some(&child) = self.nodes[node].children.get(self.nodes[node].selected) {
+            line.push(self.nodes[child].mov);
+            node = child;
+        }
+        line
+    }
+
+    fn enter(&mut self, index: usize) {
+        self.nodes[self.current].selected = index;
+        self.current = self.nodes[self.current].children[index];
+        self.board.make_move(&self.nodes[self.current].mov);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, utils::utils::{move_to_board, move_to_user}};
+    use super::Game;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for mov in moves {
+            let mov = move_to_board(&game.board, mov);
+            game.play(&mov);
+        }
+    }
+
+    fn line(game: &mut Game) -> Vec<String> {
+        let ply = game.ply();
+        game.go_to_start();
+        let line = game.get_line().iter().map(|mov| {
+            let user = move_to_user(&game.board, mov);
+            game.forward();
+            user
+        }).collect();
+        game.go_to_ply(ply);
+        line
+    }
+
+    #[test]
+    fn test_game_navigation_01() {
+        let mut game = Game::new(Board::new());
+        play(&mut game, &["e2e4", "e7e5", "g1f3", "b8c6"]);
+        let end = game.board.hash;
+        assert!(game.back() && game.back());
+        assert_eq!(game.ply(), 2);
+        assert!(game.forward());
+        assert!(!game.go_to_ply(usize::MAX));
+        assert!(game.board.hash == end && game.ply() == 4 && !game.forward());
+        assert!(game.go_to_ply(1));
+        assert_eq!(game.board.history.len(), 1);
+        assert!(!game.go_to_ply(9));
+        game.go_to_start();
+        assert!(game.board.hash == Board::new().hash && !game.back());
+    }
+
+    #[test]
+    fn test_game_variations_01() {
+        let mut game = Game::new(Board::new());
+        play(&mut game, &["e2e4", "e7e5", "g1f3"]);
+        game.go_to_ply(1);
+        // a new move starts a variation, the main line stays
+        play(&mut game, &["c7c5", "g1f3"]);
+        assert_eq!(line(&mut game), vec!["e2e4", "c7c5", "g1f3"]);
+        game.go_to_ply(1);
+        assert_eq!(game.get_variations().len(), 2);
+        assert!(game.enter_variation(0));
+        assert_eq!(line(&mut game), vec!["e2e4", "e7e5", "g1f3"]);
+        // the same move is followed, not added again
+        game.back();
+        play(&mut game, &["c7c5"]);
+        assert_eq!(game.get_variations().len(), 1);
+        game.promote_variation();
+        game.go_to_ply(1);
+        assert_eq!(move_to_user(&game.board, &game.get_variations()[0]), "c7c5");
+        assert!(!game.enter_variation(2));
+    }
+}
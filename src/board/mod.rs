@@ -1,4 +1,9 @@
+pub(crate) mod attacks;
 pub(crate) mod bimaps;
+#[allow(clippy::module_inception)]
 pub(crate) mod board;
 pub(crate) mod mov;
-pub(crate) mod coord;
\ No newline at end of file
+pub(crate) mod coord;
+pub(crate) mod edit;
+pub(crate) mod game;
+pub(crate) mod zobrist;
@@ -10,6 +10,15 @@ pub struct Mov {
 }
 
 impl Mov {
+    // passing the move (see Board::make_null_move), real moves never have the same from and to
+    pub fn null() -> Self {
+        Mov{data: 0, from: Coord::new(0, 0), to: Coord::new(0, 0)}
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.from == self.to
+    }
+
     // return true if this move is a check or a capture
     // !! look bimaps for the reference - if it changes, this needs to be changed too
     // (unsafe method)
@@ -40,5 +49,6 @@ pub struct BoardMov {
     pub mov: Mov,
     pub castling: u8,
     pub en_passant: Coord,
-    pub hmw: u8
+    pub hmw: u8,
+    pub hash: u64
 }
\ No newline at end of file
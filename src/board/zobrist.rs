@@ -0,0 +1,193 @@
+use super::board::Board;
+
+// Zobrist keys. Board keeps the XOR of the keys of everything on it up to date on make_move/revert_move
+// (see Board::hash): pieces on squares, side to move, every castling right and the en passant file.
+pub struct Zobrist {
+    pieces: [[[u64; 12]; 8]; 8],
+    color: u64,
+    // K, Q, k, q
+    castlings: [u64; 4],
+    // by file, counted only if en passant capture is possible, otherwise the position is the same
+    en_passant: [u64; 8]
+}
+
+// keys are generated at compile time, so all boards share them
+pub static ZOBRIST: Zobrist = Zobrist::new(Zobrist::SEED);
+
+impl Zobrist {
+    pub const SEED: u64 = 1024;
+
+    pub const fn new(seed: u64) -> Self {
+        let mut state = seed;
+        let mut pieces = [[[0; 12]; 8]; 8];
+        let mut y = 0;
+        while y < 8 {
+            let mut x = 0;
+            while x < 8 {
+                let mut piece = 0;
+                while piece < 12 {
+                    state = state.wrapping_add(1);
+                    pieces[y][x][piece] = Self::mix(state);
+                    piece += 1;
+                }
+                x += 1;
+            }
+            y += 1;
+        }
+        state = state.wrapping_add(1);
+        let color = Self::mix(state);
+        let mut castlings = [0; 4];
+        let mut i = 0;
+        while i < 4 {
+            state = state.wrapping_add(1);
+            castlings[i] = Self::mix(state);
+            i += 1;
+        }
+        let mut en_passant = [0; 8];
+        let mut x = 0;
+        while x < 8 {
+            state = state.wrapping_add(1);
+            en_passant[x] = Self::mix(state);
+            x += 1;
+        }
+        Self { pieces, color, castlings, en_passant }
+    }
+
+    // splitmix64 finalizer: spreads close values (e.g. seeds 1, 2, 3) all over u64
+    pub const fn mix(value: u64) -> u64 {
+        let mut x = value.wrapping_add(0x9e3779b97f4a7c15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+        x ^ (x >> 31)
+    }
+
+    // empty squares (including the ones with a color bit left) have no key
+    pub fn piece(&self, piece: u8, y: usize, x: usize) -> u64 {
+        if piece < 2 {
+            return 0;
+        }
+        self.pieces[y][x][(piece - 2) as usize]
+    }
+
+    pub fn color(&self) -> u64 {
+        self.color
+    }
+
+    // castling rights bits: K = 128, Q = 64, k = 32, q = 16
+    pub fn castling(&self, castling: u8) -> u64 {
+        let mut value = 0;
+        for (i, key) in self.castlings.iter().enumerate() {
+            if castling & (128 >> i) > 0 {
+                value ^= key;
+            }
+        }
+        value
+    }
+
+    pub fn en_passant(&self, board: &Board) -> u64 {
+        if board.is_en_passant_possible() {
+            self.en_passant[board.en_passant.x() as usize]
+        } else {
+            0
+        }
+    }
+
+    // full recalculation, board keeps it incrementally instead; won't include move counters
+    pub fn hash(&self, board: &Board) -> u64 {
+        let mut value = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                value ^= self.piece(board.field[y][x], y, x);
+            }
+        }
+        if board.white_to_move {
+            value ^= self.color;
+        }
+        value ^ self.castling(board.castling) ^ self.en_passant(board)
+    }
+
+    // pawns only, for the pawn structure cache
+    pub fn hash_pawns(&self, board: &Board) -> u64 {
+        let mut value = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece & 254 == board.gpl(&'p') {
+                    value ^= self.piece(piece, y, x);
+                }
+            }
+        }
+        value
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::{mov::Mov, coord::Coord}, utils::utils::move_to_board};
+
+    #[test]
+    fn test_zobrist_create_01() {
+        let a = Zobrist::new(0);
+        let b = Zobrist::new(1);
+        let c = Zobrist::new(123456789);
+        // private fields
+        assert!(a.pieces[0][0][0] != b.pieces[0][0][0]);
+        assert!(c.pieces[7][7][11] != b.pieces[7][7][11]);
+        assert!(a.pieces[2][3][4] == a.pieces[2][3][4]);
+    }
+
+    #[test]
+    fn test_zobrist_hashing_01() {
+        let b = Board::new();
+        assert!(ZOBRIST.hash(&b) != 0);
+        assert_eq!(b.hash, ZOBRIST.hash(&b));
+    }
+
+    #[test]
+    fn test_zobrist_hashing_02() {
+        let b = Board::parse_fen("rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3");
+        let b2 = Board::parse_fen("rnbq1bnr/pppppk1p/8/5p2/4P1pP/5PP1/PPPPN3/RNBQKBR1 b Q h3 0 6");
+        assert!(b.hash != b2.hash);
+    }
+
+    #[test]
+    fn test_zobrist_hashing_03() {
+        let mut b = Board::parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p1N1/2B1P3/8/PPPP1PPP/RNBQK2R b KQkq - 5 4");
+        let h = b.hash;
+        b.make_move(&Mov{ data: 0, from: Coord::new(6, 3), to: Coord::new(4, 3)});
+        let h2 = b.hash;
+        b.revert_move();
+        assert!(h != h2);
+        assert!(h == b.hash);
+    }
+
+    #[test]
+    fn test_zobrist_castling_01() {
+        // every castling right has its own key
+        let hashes: Vec<u64> = ["K", "Q", "k", "q", "KQ", "kq", "-"].iter()
+            .map(|rights| Board::parse_fen(&format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", rights)).hash)
+            .collect();
+        for (i, hash) in hashes.iter().enumerate() {
+            assert!(hashes[..i].iter().all(|other| other != hash));
+        }
+    }
+
+    #[test]
+    fn test_zobrist_en_passant_01() {
+        // en passant square without a pawn to capture is the same position
+        let with = Board::parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1");
+        let without = Board::parse_fen("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1");
+        assert_eq!(with.hash, without.hash);
+        let with = Board::parse_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1");
+        let without = Board::parse_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1");
+        assert!(with.hash != without.hash);
+        // the same position after different moves, incremental hash is still right (checked by debug assertion)
+        let mut a = Board::parse_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        a.make_move(&move_to_board(&a, "e2e4"));
+        let mut b = Board::parse_fen("4k3/8/8/8/8/4P3/8/4K3 w - - 0 1");
+        b.make_move(&move_to_board(&b, "e3e4"));
+        assert_eq!(a.hash, b.hash);
+    }
+}
@@ -1,459 +1,1359 @@
-use std::{collections::HashMap, cmp::{min, max}};
+use std::{collections::{HashMap, BTreeMap}, cmp::min, fs, io, ops::{Add, AddAssign, SubAssign, Mul}, cell::RefCell};
+use serde::{Serialize, Deserialize};
 
-use crate::{engine::{character::Character, eval::{EvalMov, Eval, EvalHashed}, minimax::eval, hashtable::Hashtable}, board::{board::Board}};
+use crate::{engine::{character::{Character, Evaluator}, eval::{EvalExplanation, EvalTerm}, endgame::{self, EndgameEval}, searcher::Searcher}, board::{board::Board, coord::Coord, mov::Mov, zobrist::{Zobrist, ZOBRIST}}};
+use super::pst::Pst;
 
-pub struct Generic {
-    weights: GenericWeights,
+// Generic character: its evaluation with the weights, searched by the common driver
+pub type Generic = Searcher<GenericEval>;
 
-    // maximum static half-depth for minimax eval() call
-    static_half_depth: i8,
-    // maximum half-static half-depth for minimax eval() call
-    mixed_half_depth: i8,
-    // maximum dynamic half-depth for minimax eval() call
-    dynamic_half_depth: i8,
-    // simple Zobrist hashtable
-    hashtable: Hashtable,
-    // stored evaluated board positions (temporary cache)
-    hashes_temp: HashMap<u64, EvalHashed>,
-    // already played positions on board (permanent cache)
-    hashes_perm: HashMap<u64, u8>,
-    hashes_perm_history: Vec<u64>,
-    // history of alpha/beta values used for minimax search (including next one to use)
-    alpha_stack: Vec<Eval>,
-    beta_stack:  Vec<Eval>,
-
-    // temporary storage of evaluated moves
-    // will clear itself on every accept_move() call
-    evals: Vec<EvalMov>
+pub struct GenericEval {
+    weights: GenericWeights,
+    // piece-square tables out of the weights
+    pst: Pst,
+    // piece-square table sums of the searched line by position hash, updated move by move (the last one is the current)
+    pst_line: Vec<(u64, [[f32; 2]; 2])>,
+    // pawn structure scores by pawn-only hash, filled during the (immutable) static evaluation
+    pawn_cache: RefCell<HashMap<u64, PawnTerms>>,
+    // mixed into the store keys: evaluations of other weights are not the same
+    weights_key: u64
 }
 
-impl Character for Generic {
-    fn get_eval_move(&mut self, board: &mut Board) -> EvalMov {
-        self.get_eval_moves(board)[0]
+impl Evaluator for GenericEval {
+    fn get_static_eval(&self, board: &Board) -> f32 {
+        match self.get_terms(board) {
+            Ok((terms, scale)) => Self::sum_terms(&terms, Self::get_phase(board), scale),
+            Err((_, score)) => score
+        }
     }
 
-    fn get_eval_moves(&mut self, board: &mut Board) -> &Vec<EvalMov> {
-        let depths = [self.static_half_depth, self.mixed_half_depth, self.dynamic_half_depth];
-
-        if self.hashes_perm_history.is_empty() {
-            let hash = self.make_hash(board);
-            self.hashes_perm_history.push(hash);
-            self.hashes_perm.insert(hash, 1);
+    fn explain_eval(&self, board: &Board) -> EvalExplanation {
+        let phase = Self::get_phase(board);
+        let (terms, scale, rule) = match self.get_terms(board) {
+            Ok((terms, scale)) => (terms, scale, None),
+            Err((rule, _)) => ([[Tapered::default(); 2]; Term::COUNT], 1.0, Some(rule))
+        };
+        let total = match rule {
+            Some(_) => self.get_static_eval(board),
+            None => Self::sum_terms(&terms, phase, scale)
+        };
+        EvalExplanation {
+            terms: terms.iter().zip(Term::NAMES).map(|([black, white], name)| EvalTerm {
+                name,
+                white: (*white).into(),
+                black: (*black).into(),
+                value: white.taper(phase) + black.taper(phase)
+            }).collect(),
+            phase,
+            scale,
+            rule,
+            noise: 0.0,
+            total
         }
+    }
 
-        // TODO: write a good code?
-        // This is wrong because it mess with the depth and doesn't tell user about it.
-        // However this will yield instant results instead of pondering already evaluated positions, also mate enclosure.
-        // May provoke something unprecedented.
-        let mut mate_in = 0;
-        if self.alpha_stack.last().unwrap().mate_in != -Eval::BIG_MATE {
-            mate_in = -self.alpha_stack.last().unwrap().mate_in;
-            // println!("debug, alpha mate_in (abs): {}", mate_in);
-        } else if self.beta_stack.last().unwrap().mate_in != Eval::BIG_MATE {
-            mate_in = self.beta_stack.last().unwrap().mate_in;
-            // println!("debug,  beta mate_in (   ): {}", mate_in);
-        }
-        if mate_in != 0 {
-            self.static_half_depth = mate_in;
-            self.mixed_half_depth = mate_in;
-            self.dynamic_half_depth = mate_in;
-        }
+    fn make_move(&mut self, board: &mut Board, mov: &Mov) {
+        let (squares, len) = board.get_touched_squares(mov);
+        let mut score = self.get_pst_score(board);
+        board.make_move(mov);
+        self.pst.update(&mut score, board, &squares[..len]);
+        debug_assert!({
+            let full = self.pst.score(board);
+            (0..4).all(|i| (full[i / 2][i % 2] - score[i / 2][i % 2]).abs() < 0.001)
+        }, "incremental piece-square sums differ from the full ones");
+        self.pst_line.push((board.hash, score));
+    }
 
-        self.evals = eval(board, self, *self.alpha_stack.last().unwrap(), *self.beta_stack.last().unwrap());
-        self.static_half_depth = depths[0];
-        self.mixed_half_depth = depths[1];
-        self.dynamic_half_depth = depths[2];
-        &self.evals
+    fn revert_move(&mut self, board: &mut Board) {
+        board.revert_move();
+        self.pst_line.pop();
     }
 
-    fn set_static_half_depth(&mut self, half_depth: i8) {
-        self.static_half_depth = half_depth;
+    fn get_eval_key(&self) -> u64 {
+        self.weights_key
     }
+}
 
-    fn set_mixed_half_depth(&mut self, half_depth: i8) {
-        self.mixed_half_depth = half_depth;
+impl Generic {
+    // seed is taken from the settings, random if it's not there
+    pub fn with_settings(weights: GenericWeights, settings: GenericSettings, depths: &[i8; 3]) -> Self {
+        let mut char = Searcher::from_evaluator(GenericEval::new(weights), depths);
+        if let Some(seed) = settings.seed {
+            char.set_seed(seed);
+        }
+        char.set_contempt(settings.contempt);
+        char
     }
 
-    fn set_dynamic_half_depth(&mut self, half_depth: i8) {
-        self.dynamic_half_depth = half_depth;
+    // same as with_settings(), weights and settings are read from TOML or JSON config (see GenericConfig::load)
+    pub fn from_config(path: &str, depths: &[i8; 3]) -> io::Result<Self> {
+        let config = GenericConfig::load(path)?;
+        Ok(Self::with_settings(config.weights, config.settings, depths))
     }
+}
 
-    fn accept_move(&mut self, board: &Board) {
-        let mut found = false;
-        let mov = board.history.last().unwrap().mov;
-        // set next a/b values to push into search
-        for eval_mov in &self.evals {
-            if eval_mov.mov == mov {
-                if eval_mov.eval.mate_in > 0 {
-                    self.alpha_stack.push(Eval::low());
-                    self.beta_stack.push(Eval::new(-Eval::BIG_SCORE, max(1, eval_mov.eval.mate_in - 1)));
-                } else if eval_mov.eval.mate_in < 0 {
-                    self.alpha_stack.push(Eval::new(Eval::BIG_SCORE, min(-1, eval_mov.eval.mate_in + 1)));
-                    self.beta_stack.push(Eval::high());
-                } else if !self.alpha_stack.is_empty() {
-                    self.alpha_stack.push(Eval::low());
-                    self.beta_stack.push(Eval::high());
+impl GenericEval {
+    pub const PHASE_MAX: i32 = 24;
+    // pawn structures to keep, cache is dropped entirely when it's full
+    pub const PAWN_CACHE_SIZE: usize = 1 << 16;
+
+    pub fn new(weights: GenericWeights) -> Self {
+        let weights_key = weights.get_params().iter().fold(0, |key, (name, value)| {
+            Zobrist::mix(key ^ name.bytes().fold(value.to_bits() as u64, |k, b| Zobrist::mix(k ^ b as u64)))
+        });
+        Self {
+            pst: weights.get_pst(),
+            pst_line: Vec::default(),
+            weights,
+            pawn_cache: RefCell::new(HashMap::new()),
+            weights_key
+        }
+    }
+
+
+    // piece-square table sums: kept for the positions of the searched line, calculated for any other one
+    fn get_pst_score(&self, board: &Board) -> [[f32; 2]; 2] {
+        match self.pst_line.last() {
+            Some((hash, score)) if *hash == board.hash => *score,
+            _ => self.pst.score(board)
+        }
+    }
+
+    // Evaluation terms by colors (white perspective: black contributions are negated) and the drawish scale,
+    // or the final score with the rule that decided it (known endgames). Draws by rules are the search's business (see minimax).
+    // Squares are walked by coordinates, the same ones index per-square tables
+    #[allow(clippy::needless_range_loop)]
+    fn get_terms(&self, board: &Board) -> Result<(Terms, f32), (&'static str, f32)> {
+        // basic endgames are evaluated by their own rules, drawish material scales the evaluation down
+        let mut scale = 1.0;
+        match endgame::probe(board) {
+            Some(EndgameEval::Score(score)) => return Err(("known endgame", score)),
+            Some(EndgameEval::Scale(k)) => scale = k,
+            None => ()
+        }
+
+        let mut terms: Terms = [[Tapered::default(); 2]; Term::COUNT];
+        // score is always from white perspective, color bit 1 stands for white
+        let signs = [-1.0, 1.0];
+
+        // pawns by verticals, cheapest attacker of every square and kings zones
+        let mut pawns_by_verticals: [[i8; 2]; 8] = [[0; 2]; 8];
+        let mut cheapest_attacker = [[[f32::MAX; 2]; 8]; 8];
+        let mut knights_bishops = [[0i8; 2]; 2];
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece < 2 {
+                    continue;
+                }
+                let c = board.gpr(&piece).to_ascii_lowercase();
+                let color = (piece & 1) as usize;
+                let cost = self.weights.material_cost[&c].mg;
+                for (ay, ax) in Self::get_attacks(board, y, x) {
+                    if cost < cheapest_attacker[ay][ax][color] {
+                        cheapest_attacker[ay][ax][color] = cost;
+                    }
+                }
+                match c {
+                    'p' => pawns_by_verticals[x][color] += 1,
+                    'n' => knights_bishops[color][0] += 1,
+                    'b' => knights_bishops[color][1] += 1,
+                    _ => ()
                 }
-                found = true;
-                break;
             }
         }
-        if !found {
-            let a = self.alpha_stack.last().unwrap().mate_in;
-            let b = self.beta_stack.last().unwrap().mate_in;
-            if a == 0 || a == -Eval::BIG_MATE {
-                self.alpha_stack.push(Eval::low());
-                if b == 0 || b == Eval::BIG_MATE {
-                    self.beta_stack.push(Eval::high());
+        let kings = [board.black_king_location, board.white_king_location];
+        // pieces of the color attacking the opponent king zone and their attack units
+        let mut king_attackers = [0; 2];
+        let mut king_attack_units = [0.0; 2];
+
+        for (term, pawn_terms) in Term::PAWNS.iter().zip(self.get_pawn_structure(board)) {
+            terms[*term as usize] = pawn_terms;
+        }
+
+        // piece by piece: material, mobility, outposts, defenders and king threats
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece < 2 {
+                    continue;
+                }
+                let c = board.gpr(&piece).to_ascii_lowercase();
+                let color = (piece & 1) as usize;
+                let sign = signs[color];
+                let cost = self.weights.material_cost[&c];
+                // how far the piece went from its own side (0 - first rank, 7 - last rank)
+                let advance = if color > 0 { y } else { 7 - y };
+
+                if c != 'k' {
+                    terms[Term::Material as usize][color] += sign * cost;
+                    // defended by a cheaper piece means it can be recaptured without a loss
+                    if c != 'p' && cheapest_attacker[y][x][color] < cost.mg {
+                        terms[Term::DefendedPieces as usize][color] += sign * self.weights.defend_cheaper_piece_multiplier;
+                    }
+                }
+
+                // the rest of pawn terms are in the pawn structure
+                if c == 'p' {
+                    continue;
+                }
+
+                // outposts: minor piece on the opponent half, protected by pawn and can't be chased away by pawns
+                if (c == 'n' || c == 'b') && advance > 3 && cheapest_attacker[y][x][color] == self.weights.material_cost[&'p'].mg
+                    && Self::is_passed(board, y, x, color) {
+                    terms[Term::Outposts as usize][color] += sign * self.weights.outpost_multiplier * if c == 'n' { 1.0 } else { 0.5 };
+                }
+
+                let attacks = Self::get_attacks(board, y, x);
+                let cells = attacks.iter().filter(|(ay, ax)| board.field[*ay][*ax] < 2 || board.field[*ay][*ax] & 1 != piece & 1).count() as f32;
+                let max_cells = self.weights.mobility_max_threshold[&c];
+                terms[Term::Mobility as usize][color] += sign * self.weights.mobility_k[&c] * (cells.min(max_cells) - self.weights.mobility_min_threshold[&c]);
+
+                // king goes to the center when there is nothing to fear (endgame value is the one that matters)
+                if c == 'k' {
+                    let center_distance = (3.5 - y as f32).abs().max((3.5 - x as f32).abs()) - 0.5;
+                    terms[Term::KingCentralization as usize][color] += sign * self.weights.king_centralization * (3.0 - center_distance);
                 } else {
-                    found = true;
-                    self.beta_stack.push(Eval::new(-Eval::BIG_SCORE, max(1, self.beta_stack.last().unwrap().mate_in - 1)));
+                    let king = kings[1 - color];
+                    let threats = attacks.iter().filter(|(ay, ax)| ay.abs_diff(king.y() as usize) < 2 && ax.abs_diff(king.x() as usize) < 2).count();
+                    terms[Term::KingThreats as usize][color] += sign * self.weights.king_threat_multiplier * threats as f32;
+                    if threats > 0 {
+                        king_attackers[color] += 1;
+                        king_attack_units[color] += self.weights.king_attack_units[&c] * threats as f32;
+                    }
                 }
-            } else {
-                found = true;
-                self.alpha_stack.push(Eval::new(Eval::BIG_SCORE, min(-1, self.alpha_stack.last().unwrap().mate_in + 1)));
-                self.beta_stack.push(Eval::high());
             }
         }
-        // deal with permanent cache
-        let hash = self.make_hash(board);
-        self.hashes_perm_history.push(hash);
-        if let Some(f) = self.hashes_perm.get_mut(&hash) {
-            *f += 1;
-        } else {
-            self.hashes_perm.insert(hash, 1);
+
+        // king safety: a single attacker is rarely dangerous, the more units the faster the danger grows (see king_attack_table)
+        for (color, sign) in signs.iter().enumerate() {
+            if king_attackers[color] > 1 {
+                let table = &self.weights.king_attack_table;
+                terms[Term::KingAttack as usize][color] += *sign * self.weights.king_safety_k * table[min(king_attack_units[color].round() as usize, table.len() - 1)];
+            }
+            terms[Term::KingShelter as usize][color] += *sign * self.get_king_shelter(board, kings[color], color, &pawns_by_verticals);
         }
-        // deal with temporary cache
-        // TODO: mate_in will go crazy, find a way around to implement hashing in between moves
-        // if !found {
-        if mov.is_repeatable(board) {
-            self.hashes_temp.remove(&hash);
-        } else {
-            self.hashes_temp.clear();
+
+        // knights are better than bishops in closed positions
+        let pawns: i8 = pawns_by_verticals.iter().map(|verticals| verticals[0] + verticals[1]).sum();
+        for (color, sign) in signs.iter().enumerate() {
+            let knights_over_bishops = knights_bishops[color][0] - knights_bishops[color][1];
+            terms[Term::KnightsBishops as usize][color] += *sign * self.weights.k_kvb * (pawns - 8) as f32 * knights_over_bishops as f32;
         }
-        // }
-        self.evals.clear(); 
-    }
 
-    fn takeback(&mut self) {
-        // < 2 is because starting position is also stored in history
-        if self.hashes_perm_history.len() < 2 {
-            panic!("Attempt to make takeback from starting position");
+        for (color, [mg, eg]) in self.get_pst_score(board).iter().enumerate() {
+            terms[Term::Pst as usize][color] += Tapered::new(*mg, *eg);
         }
-        let mut mark_for_delete = false;
-        if let Some(f) = self.hashes_perm.get_mut(self.hashes_perm_history.last().unwrap()) {
-            if *f == 1 {
-                mark_for_delete = true;
-            } else {
-                *f -= 1;
+
+        for batteries in [self.battery_search_vertical(board), self.battery_search_horizontal(board), self.battery_search_diagonal(board)] {
+            for (color, battery) in batteries.iter().enumerate() {
+                terms[Term::Batteries as usize][color] += *battery;
             }
         }
-        if mark_for_delete {
-            self.hashes_perm.remove(self.hashes_perm_history.last().unwrap());
+
+        Ok((terms, scale))
+    }
+
+    // the only way to turn terms into score, so explanation always matches the eval
+    fn sum_terms(terms: &Terms, phase: f32, scale: f32) -> f32 {
+        terms.iter().map(|[black, white]| white.taper(phase) + black.taper(phase)).sum::<f32>() * scale
+    }
+
+    // pawn structure depends on pawns only, so it is cached by pawn-only hash
+    fn get_pawn_structure(&self, board: &Board) -> PawnTerms {
+        let hash = ZOBRIST.hash_pawns(board);
+        if let Some(score) = self.pawn_cache.borrow().get(&hash) {
+            return *score;
+        }
+        let score = self.eval_pawn_structure(board);
+        let mut pawn_cache = self.pawn_cache.borrow_mut();
+        if pawn_cache.len() >= Self::PAWN_CACHE_SIZE {
+            pawn_cache.clear();
         }
-        self.hashes_perm_history.pop();
-        self.alpha_stack.pop();
-        self.beta_stack.pop();
-        self.hashes_temp.clear();
+        pawn_cache.insert(hash, score);
+        score
     }
 
-    fn get_static_eval(&self, board: &Board) -> f32 {
-        if board.hmw > 99 {
-            return 0.0;
-        }
-        
-        let mut score: f32 = 0.0;
-
-        // for i in 0..8 {
-        //     for j in 0..8 {
-        //         if scanned[i][j] || board.field[i][j] < 2 {
-        //             continue;
-        //         }
-        //         let mut piece = board.field[i][j];
-        //         let color_bit = piece & 1;
-        //         piece &= 254;
-
-        //         // TODO: match by symbols (board.gpl() usage preferred)
-        //         match piece {
-        //             // pawn
-        //             2 => {
-                        
-        //             },
-        //             // king
-        //             4 => (),
-        //             // knight
-        //             6 => (),
-        //             // bishop
-        //             8 => (),
-        //             // rook
-        //             10 => {
-        //                 // score += self.scan_for_rq(board, &scanned, i as u8, j as u8, color_bit, 1.0);
-        //             }
-        //             // queen
-        //             12 => (),
-        //             //
-        //             _ => ()
-        //         }
-        //     }
-        // }
-
-        // advanced squares detection (for passed pawns, outposts)
-        let mut advanced_squares = [[[true; 2]; 8]; 8];
+    // Islands, stacked, isolated, backward, connected, advanced, passed and candidate pawns (see Term::PAWNS) by colors.
+    fn eval_pawn_structure(&self, board: &Board) -> PawnTerms {
+        let mut terms: PawnTerms = Default::default();
+        let [islands_term, stacked_term, isolated_term, backward_term, connected_term, advanced_term, passed_term, candidate_term] = [0, 1, 2, 3, 4, 5, 6, 7];
+        let signs = [-1.0, 1.0];
+        let pawn = board.gpl(&'p');
         let mut pawns_by_verticals: [[i8; 2]; 8] = [[0; 2]; 8];
-        for i in 0..8 {
-            let mut prevented = [false, false];
-            let in_bounds = [i > 0, i < 7];
-            for j in 0..8 {
-                for k in 0..2 {
-                    let index = if k > 0 { 7 - j } else { j };
-                    let piece = board.field[index][i];
-                    if prevented[k] {
-                        advanced_squares[index][i][k] = false;
-                        if in_bounds[0] {
-                            advanced_squares[index][i][k] = false;
-                        }
-                        if in_bounds[1] {
-                            advanced_squares[index][i][k] = false;
-                        }
-                    }
-                    if piece == board.gpl(&'p') + k as u8 {
-                        prevented[k] = true;
-                        pawns_by_verticals[i][k] += 1;
-                    }
+        for y in 0..8 {
+            for x in 0..8 {
+                if board.field[y][x] & 254 == pawn {
+                    pawns_by_verticals[x][(board.field[y][x] & 1) as usize] += 1;
                 }
             }
         }
 
         // pawn islands / stacked pawns penalties
-        for k in 0..2 {
-            let mut failed = true;
+        for (k, sign) in signs.iter().enumerate() {
             let mut islands = 0;
             let mut stacked = 0;
-            for i in 0..8 {
-                if pawns_by_verticals[i][k] > 0 {
-                    failed = false;
-                    stacked += pawns_by_verticals[i][k] - 1;
-                } else if !failed {
-                    failed = true;
-                    islands += 1;
+            let mut previous = false;
+            for verticals in &pawns_by_verticals {
+                if verticals[k] > 0 {
+                    stacked += verticals[k] - 1;
+                    if !previous {
+                        islands += 1;
+                    }
+                }
+                previous = verticals[k] > 0;
+            }
+            terms[islands_term][k] -= *sign * self.weights.pawn_islands_penalty[min(islands, 3)];
+            terms[stacked_term][k] -= *sign * self.weights.pawn_stacked_penalty * stacked as f32;
+        }
+
+        let is_at = |y: i8, x: i8, piece: u8| (0..8).contains(&y) && (0..8).contains(&x) && board.field[y as usize][x as usize] == piece;
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece & 254 != pawn {
+                    continue;
+                }
+                let color = (piece & 1) as usize;
+                let sign = signs[color];
+                let (own, opponent) = (pawn + color as u8, pawn + 1 - color as u8);
+                let forward: i8 = if color > 0 { 1 } else { -1 };
+                let (y8, x8) = (y as i8, x as i8);
+                // advance counts from the starting rank
+                let advance = (if color > 0 { y } else { 7 - y }) as f32 - 1.0;
+                if advance > 2.0 {
+                    terms[advanced_term][color] += sign * self.weights.pawn_advanced_multiplier * (advance - 2.0);
+                }
+
+                // own pawns on adjacent verticals that are level or behind (may support it) and opponent ones ahead (may stop it)
+                let mut supporters = 0;
+                let mut sentries = 0;
+                for nx in x.saturating_sub(1)..min(x + 2, 8) {
+                    if nx == x {
+                        continue;
+                    }
+                    for ny in 0..8 {
+                        let ahead = (ny as i8 - y8) * forward > 0;
+                        if board.field[ny][nx] == own && !ahead {
+                            supporters += 1;
+                        } else if board.field[ny][nx] == opponent && ahead {
+                            sentries += 1;
+                        }
+                    }
+                }
+                let isolated = (x == 0 || pawns_by_verticals[x - 1][color] == 0) && (x == 7 || pawns_by_verticals[x + 1][color] == 0);
+                let connected = [x8 - 1, x8 + 1].iter().any(|nx| is_at(y8, *nx, own) || is_at(y8 - forward, *nx, own));
+                if isolated {
+                    terms[isolated_term][color] -= sign * self.weights.pawn_isolated_penalty;
+                } else if connected {
+                    terms[connected_term][color] += sign * self.weights.pawn_connected_multiplier * (advance + 1.0);
+                } else if supporters == 0 && [x8 - 1, x8 + 1].iter().any(|nx| is_at(y8 + 2 * forward, *nx, opponent)) {
+                    // backward: can't be supported by own pawns and can't go further without being captured
+                    terms[backward_term][color] -= sign * self.weights.pawn_backward_penalty;
+                }
+
+                if Self::is_passed(board, y, x, color) {
+                    terms[passed_term][color] += sign * self.weights.pawn_passed_multiplier * advance * advance;
+                } else if supporters >= sentries && !(1..8).any(|d| is_at(y8 + d * forward, x8, opponent)) {
+                    // candidate: nothing in front on its own vertical and enough support to break through the sentries
+                    terms[candidate_term][color] += sign * self.weights.pawn_candidate_multiplier * advance * advance;
                 }
             }
-            if !failed || islands < 1 {
-                islands += 1;
+        }
+        terms
+    }
+
+    // Pawns in front of the king (own shield and opponent storm) and files without pawns next to it.
+    // Only verticals of the king and adjacent ones matter, positive is good for the king's side.
+    #[allow(clippy::needless_range_loop)]
+    fn get_king_shelter(&self, board: &Board, king: Coord, color: usize, pawns_by_verticals: &[[i8; 2]; 8]) -> Tapered {
+        let (ky, kx) = (king.y() as i8, king.x() as usize);
+        let forward: i8 = if color > 0 { 1 } else { -1 };
+        let own_pawn = board.gpl(&'p') + color as u8;
+        let opponent_pawn = board.gpl(&'p') + 1 - color as u8;
+        let mut shelter = Tapered::default();
+        for x in kx.saturating_sub(1)..min(kx + 2, 8) {
+            // nearest pawns of both colors in front of the king
+            let mut shield = None;
+            let mut storm = None;
+            for distance in 1..8 {
+                let y = ky + forward * distance;
+                if !(0..8).contains(&y) {
+                    break;
+                }
+                let piece = board.field[y as usize][x];
+                if piece == own_pawn && shield.is_none() {
+                    shield = Some(distance as usize);
+                } else if piece == opponent_pawn && storm.is_none() {
+                    storm = Some(distance as usize);
+                }
             }
-            if k > 0 {
-                score -= self.weights.pawn_islands_penalty[islands] + self.weights.pawn_stacked_penalty * stacked as f32;
-            } else {
-                score += self.weights.pawn_islands_penalty[islands] + self.weights.pawn_stacked_penalty * stacked as f32;
+            if let Some(bonus) = shield.and_then(|distance| self.weights.king_pawn_shield.get(distance - 1)) {
+                shelter += *bonus;
+            }
+            if let Some(penalty) = storm.and_then(|distance| self.weights.king_pawn_storm.get(distance - 1)) {
+                shelter -= *penalty;
+            }
+            if pawns_by_verticals[x][color] == 0 {
+                shelter -= if pawns_by_verticals[x][1 - color] == 0 { self.weights.king_open_file } else { self.weights.king_half_open_file };
             }
         }
+        shelter
+    }
 
-        // battery & king threat search
-        // TODO
-        for i in 0..8 {
-            // let counters_battery = [0, 0];
-            // let counters_self_pieces = [0, 0];
-            // // first half are not pawn protected, second half are
-            // let counters_enemy_pieces = [0, 0, 0, 0];
-            // // first half mobile, second half - immobile
-            // let counters_self_pawns = [0, 0, 0, 0];
-            // let counters_enemy_pawns = [0, 0, 0, 0];
-
-            let mut start: [i8; 2] = [-1, -1];
-            let mut inb: [bool; 2] = [false, false];
-            let mut total: [i8; 2] = [0, 0];
-            let mut current: [i8; 2] = [0, 0];
-            let mut points: [f32; 2] = [0., 0.];
-            let mut king_threat: [bool; 2] = [false, false];
-            let mut drop: [bool; 2] = [false, false];
-            
-            for j in 0..8 {
-                // there is a material count (this cycle only)
-                // vertical check
-                let piece = board.field[j][i];
-                let color_bit = piece & 1;
-                if piece < 2 {
-                    // nothing
-                } else {
-                    if piece == board.gpl(&'r') + color_bit || piece == board.gpl(&'q') + color_bit {
-                        // add to material score
-                        if start[color_bit as usize] < 0 {
-                            start[color_bit as usize] = j as i8;
-                        } else {
-                            
-                        }
-                        current[color_bit as usize] += 1;
+    // Game phase by non-pawn material left: 1.0 - all pieces are on board (or more, after promotions), 0.0 - pawn endgame.
+    // Knight and bishop count as 1, rook as 2, queen as 4, so the starting position has 24.
+    pub fn get_phase(board: &Board) -> f32 {
+        let mut phase = 0;
+        for row in &board.field {
+            for piece in row {
+                if *piece > 1 {
+                    phase += match board.gpr(piece).to_ascii_lowercase() {
+                        'n' | 'b' => 1,
+                        'r' => 2,
+                        'q' => 4,
+                        _ => 0
+                    };
+                }
+            }
+        }
+        min(phase, Self::PHASE_MAX) as f32 / Self::PHASE_MAX as f32
+    }
 
+    // heavy pieces on the same vertical without anything in between
+    fn battery_search_vertical(&self, board: &Board) -> [Tapered; 2] {
+        Self::battery_search(board, &[(1, 0)], &['r', 'q']).map(|count| self.weights.battery_vertical_cost * count)
+    }
 
-                    } else {
-                    // non-battery piece
-                        // pawn (lane is not open | enemy weakness)
-                        if piece == board.gpl(&'p') + color_bit {
-                            drop[color_bit as usize] = true;
-                        }
-                        
-                        
-                        // pawn (lane is not open) or just a second piece
-
-                        if inb[color_bit as usize] || piece < 4 {
-                            total[color_bit as usize] += current[color_bit as usize];
-                            current[color_bit as usize] = 0;
-                            inb[color_bit as usize] = false;
-                            
-                        } else {
-                        // movable (probably) piece
-                            inb[color_bit as usize] = true;
+    // heavy pieces on the same horizontal without anything in between
+    fn battery_search_horizontal(&self, board: &Board) -> [Tapered; 2] {
+        Self::battery_search(board, &[(0, 1)], &['r', 'q']).map(|count| self.weights.battery_horizontal_cost * count)
+    }
+
+    // bishop and queen on the same diagonal without anything in between
+    fn battery_search_diagonal(&self, board: &Board) -> [Tapered; 2] {
+        Self::battery_search(board, &[(1, 1), (1, -1)], &['b', 'q']).map(|count| self.weights.battery_diagonal_cost * count)
+    }
+
+    // amount of batteries by colors (black ones negative): pairs of same colored pieces of given kinds
+    // that are next to each other in one of the directions
+    fn battery_search(board: &Board, directions: &[(i8, i8)], kinds: &[char]) -> [f32; 2] {
+        let is_member = |piece: u8| piece > 1 && kinds.contains(&board.gpr(&piece).to_ascii_lowercase());
+        let mut count = [0.0; 2];
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if !is_member(piece) {
+                    continue;
+                }
+                for (dy, dx) in directions {
+                    let (mut ny, mut nx) = (y as i8 + dy, x as i8 + dx);
+                    while (0..8).contains(&ny) && (0..8).contains(&nx) && board.field[ny as usize][nx as usize] < 2 {
+                        ny += dy;
+                        nx += dx;
+                    }
+                    if (0..8).contains(&ny) && (0..8).contains(&nx) {
+                        let other = board.field[ny as usize][nx as usize];
+                        if is_member(other) && other & 1 == piece & 1 {
+                            count[(piece & 1) as usize] += if piece & 1 > 0 { 1.0 } else { -1.0 };
                         }
                     }
                 }
             }
         }
-        score
+        count
     }
 
-    fn get_static_eval_mate(&self, board: &Board) -> f32 {
-        self.get_static_eval(board)
+    // all cells attacked by the piece on the given cell (including the ones occupied by own pieces)
+    fn get_attacks(board: &Board, y: usize, x: usize) -> Vec<(usize, usize)> {
+        let piece = board.field[y][x];
+        let (directions, slide): (&[(i8, i8)], bool) = match board.gpr(&piece).to_ascii_lowercase() {
+            'p' => (if piece & 1 > 0 { &[(1, -1), (1, 1)] } else { &[(-1, -1), (-1, 1)] }, false),
+            'n' => (&[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)], false),
+            'b' => (&[(1, 1), (1, -1), (-1, 1), (-1, -1)], true),
+            'r' => (&[(1, 0), (-1, 0), (0, 1), (0, -1)], true),
+            'q' => (&[(1, 1), (1, -1), (-1, 1), (-1, -1), (1, 0), (-1, 0), (0, 1), (0, -1)], true),
+            _ => (&[(1, 1), (1, -1), (-1, 1), (-1, -1), (1, 0), (-1, 0), (0, 1), (0, -1)], false)
+        };
+        let mut attacks = Vec::default();
+        for (dy, dx) in directions {
+            let (mut ny, mut nx) = (y as i8 + dy, x as i8 + dx);
+            while (0..8).contains(&ny) && (0..8).contains(&nx) {
+                attacks.push((ny as usize, nx as usize));
+                if !slide || board.field[ny as usize][nx as usize] > 1 {
+                    break;
+                }
+                ny += dy;
+                nx += dx;
+            }
+        }
+        attacks
     }
 
-    fn get_static_eval_stalemate(&self, board: &Board) -> f32 {
-        0.0
+    // no opponent pawns in front of the cell on the same or adjacent verticals
+    fn is_passed(board: &Board, y: usize, x: usize, color: usize) -> bool {
+        let opponent_pawn = board.gpl(&'p') + 1 - color as u8;
+        let ahead = if color > 0 { y + 1..8 } else { 0..y };
+        for ny in ahead {
+            for nx in x.saturating_sub(1)..min(x + 2, 8) {
+                if board.field[ny][nx] == opponent_pawn {
+                    return false;
+                }
+            }
+        }
+        true
     }
+}
+
+// terms of the evaluation, reported separately by GenericEval::explain_eval()
+#[derive(Clone, Copy)]
+enum Term {
+    Material,
+    DefendedPieces,
+    Outposts,
+    Mobility,
+    KingCentralization,
+    KingThreats,
+    KingAttack,
+    KingShelter,
+    PawnIslands,
+    PawnStacked,
+    PawnIsolated,
+    PawnBackward,
+    PawnConnected,
+    PawnAdvanced,
+    PawnPassed,
+    PawnCandidate,
+    KnightsBishops,
+    Pst,
+    Batteries
+}
+
+impl Term {
+    const COUNT: usize = 19;
+    const NAMES: [&'static str; Self::COUNT] = [
+        "material", "defended pieces", "outposts", "mobility", "king centralization", "king threats", "king attack", "king shelter",
+        "pawn islands", "stacked pawns", "isolated pawns", "backward pawns", "connected pawns", "advanced pawns", "passed pawns", "candidate pawns",
+        "knights vs bishops", "piece-square tables", "batteries"
+    ];
+    // pawn structure terms, in order of PawnTerms
+    const PAWNS: [Self; 8] = [
+        Self::PawnIslands, Self::PawnStacked, Self::PawnIsolated, Self::PawnBackward,
+        Self::PawnConnected, Self::PawnAdvanced, Self::PawnPassed, Self::PawnCandidate
+    ];
+}
+
+// all evaluation terms by colors
+type Terms = [[Tapered; 2]; Term::COUNT];
+// pawn structure terms (see Term::PAWNS) by colors
+type PawnTerms = [[Tapered; 2]; 8];
+
+// middlegame and endgame values of a weight, interpolated by the game phase (see GenericEval::get_phase)
+// in configs it is either [mg, eg] pair or a single number for both
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "TaperedConfig", into = "[f32; 2]")]
+pub struct Tapered {
+    pub mg: f32,
+    pub eg: f32
+}
 
-    fn get_static_half_depth(&self) -> i8 {
-        self.static_half_depth
+impl Tapered {
+    pub fn new(mg: f32, eg: f32) -> Self {
+        Self { mg, eg }
     }
 
-    fn get_mixed_half_depth(&self) -> i8 {
-        self.mixed_half_depth
+    pub fn taper(&self, phase: f32) -> f32 {
+        self.mg * phase + self.eg * (1.0 - phase)
     }
+}
+
+impl Add for Tapered {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
 
-    fn get_dynamic_half_depth(&self) -> i8 {
-        self.dynamic_half_depth
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
     }
+}
 
-    fn make_hash(&self, board: &Board) -> u64 {
-        self.hashtable.hash(board)
+impl SubAssign for Tapered {
+    fn sub_assign(&mut self, other: Self) {
+        *self += other * -1.0;
     }
+}
 
-    fn cache_evaluated(&mut self, hash: u64, eval: Eval, depth: i8) {
-        if let Some(f) = self.hashes_temp.get_mut(&hash) {
-            // if is marked as played and thus already exists
-            f.eval = eval;
-            f.depth = depth;
-        } else {
-            // if is not exists and thus not marked as played
-            self.hashes_temp.insert(hash, EvalHashed::evaluated(eval, depth, 0));
+impl Mul<f32> for Tapered {
+    type Output = Self;
+
+    fn mul(self, k: f32) -> Self {
+        Self::new(self.mg * k, self.eg * k)
+    }
+}
+
+impl Mul<Tapered> for f32 {
+    type Output = Tapered;
+
+    fn mul(self, tapered: Tapered) -> Tapered {
+        tapered * self
+    }
+}
+
+impl From<Tapered> for [f32; 2] {
+    fn from(tapered: Tapered) -> Self {
+        [tapered.mg, tapered.eg]
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TaperedConfig {
+    Single(f32),
+    Pair([f32; 2])
+}
+
+impl From<TaperedConfig> for Tapered {
+    fn from(config: TaperedConfig) -> Self {
+        match config {
+            TaperedConfig::Single(value) => Self::new(value, value),
+            TaperedConfig::Pair([mg, eg]) => Self::new(mg, eg)
         }
     }
+}
 
-    fn cache_play(&mut self, hash: u64) {
-        if let Some(f) = self.hashes_temp.get_mut(&hash) {
-            f.playcount += 1;
-        } else {
-            self.hashes_temp.insert(hash, EvalHashed::new());
+// all weights can be loaded from and dumped to TOML or JSON configs, see GenericConfig::load()
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GenericWeights {
+    // pknbrq
+    pub material_cost: BTreeMap<char, Tapered>,
+    // knbrq
+    pub mobility_k: BTreeMap<char, Tapered>,
+    // amount of cells
+    pub mobility_min_threshold: BTreeMap<char, f32>,
+    pub mobility_max_threshold: BTreeMap<char, f32>,
+    //
+    pub pawn_islands_penalty: [Tapered; 4],
+    pub pawn_stacked_penalty: Tapered,
+    pub pawn_advanced_multiplier: Tapered,
+    pub pawn_passed_multiplier: Tapered,
+    // candidate pawns (could become passed) get the same bonus as passed ones, but with own multiplier
+    pub pawn_candidate_multiplier: Tapered,
+    pub pawn_isolated_penalty: Tapered,
+    pub pawn_backward_penalty: Tapered,
+    // pawns side by side or defended by pawn, multiplied by advance
+    pub pawn_connected_multiplier: Tapered,
+    pub battery_vertical_cost: Tapered,
+    pub battery_horizontal_cost: Tapered,
+    pub battery_diagonal_cost: Tapered,
+    // battery_xray_self_multiplier: f32,
+    // battery_xray_opponent_multiplier: f32,
+    pub king_threat_multiplier: Tapered,
+    // own pawns one or two squares in front of the king (and adjacent verticals)
+    pub king_pawn_shield: [Tapered; 2],
+    // opponent pawns one, two or three squares in front of the king (and adjacent verticals)
+    pub king_pawn_storm: [Tapered; 3],
+    // no pawns at all / no own pawns on the king's or adjacent vertical
+    pub king_open_file: Tapered,
+    pub king_half_open_file: Tapered,
+    // nbrq, units of every attacked square of the king zone, summed up and looked up in king_attack_table
+    pub king_attack_units: BTreeMap<char, f32>,
+    pub king_attack_table: Vec<f32>,
+    pub king_safety_k: Tapered,
+    pub king_centralization: Tapered,
+    pub defend_cheaper_piece_multiplier: Tapered,
+    pub outpost_multiplier: Tapered,
+    pub k_kvb: Tapered,
+    // pknbrq, white side as it is seen on the diagram (a8 goes first, h1 goes last), mirrored for black
+    pub pst: BTreeMap<char, PieceSquareTable>
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PieceSquareTable {
+    pub mg: Vec<f32>,
+    pub eg: Vec<f32>
+}
+
+impl GenericWeights {
+    pub fn new() -> Self {
+        let t = Tapered::new;
+        Self {
+            material_cost: BTreeMap::from([('p', t(1., 1.)), ('k', t(255., 255.)), ('n', t(3., 2.9)), ('b', t(3., 3.1)), ('r', t(4.5, 5.)), ('q', t(9., 9.5))]),
+            mobility_k: BTreeMap::from([('k', t(0., 0.)), ('n', t(0.05, 0.04)), ('b', t(0.05, 0.05)), ('r', t(0.03, 0.05)), ('q', t(0.02, 0.03))]),
+            mobility_min_threshold: BTreeMap::from([('k', 0.), ('n', 2.), ('b', 3.), ('r', 3.), ('q', 5.)]),
+            mobility_max_threshold: BTreeMap::from([('k', 8.), ('n', 8.), ('b', 12.), ('r', 12.), ('q', 24.)]),
+            pawn_islands_penalty: [t(0., 0.), t(0., 0.), t(0.1, 0.1), t(0.25, 0.25)],
+            pawn_stacked_penalty: t(0.15, 0.25),
+            pawn_advanced_multiplier: t(0.05, 0.15),
+            pawn_passed_multiplier: t(0.02, 0.05),
+            pawn_candidate_multiplier: t(0.005, 0.015),
+            pawn_isolated_penalty: t(0.1, 0.15),
+            pawn_backward_penalty: t(0.08, 0.1),
+            pawn_connected_multiplier: t(0.02, 0.03),
+            battery_vertical_cost: t(0.25, 0.15),
+            battery_horizontal_cost: t(0.1, 0.1),
+            battery_diagonal_cost: t(0.15, 0.05),
+            king_threat_multiplier: t(0.05, 0.01),
+            king_pawn_shield: [t(0.1, 0.), t(0.05, 0.)],
+            king_pawn_storm: [t(0.05, 0.), t(0.15, 0.), t(0.1, 0.)],
+            king_open_file: t(0.25, 0.),
+            king_half_open_file: t(0.1, 0.),
+            king_attack_units: BTreeMap::from([('n', 2.), ('b', 2.), ('r', 3.), ('q', 5.)]),
+            king_attack_table: (0..48).map(|units| (0.003 * (units * units) as f32).min(5.0)).collect(),
+            king_safety_k: t(1., 0.1),
+            king_centralization: t(0., 0.1),
+            defend_cheaper_piece_multiplier: t(0.05, 0.05),
+            outpost_multiplier: t(0.3, 0.2),
+            k_kvb: t(0.02, 0.02),
+            pst: BTreeMap::from([
+                ('p', PieceSquareTable { mg: Self::PST_P.to_vec(), eg: Self::PST_P.to_vec() }),
+                ('n', PieceSquareTable { mg: Self::PST_N.to_vec(), eg: Self::PST_N.to_vec() }),
+                ('b', PieceSquareTable { mg: Self::PST_B.to_vec(), eg: Self::PST_B.to_vec() }),
+                ('r', PieceSquareTable { mg: Self::PST_R.to_vec(), eg: Self::PST_R.to_vec() }),
+                ('q', PieceSquareTable { mg: Self::PST_Q.to_vec(), eg: Self::PST_Q.to_vec() }),
+                // endgame king goes to the center by king_centralization
+                ('k', PieceSquareTable { mg: Self::PST_K.to_vec(), eg: vec![0.0; 64] })
+            ])
         }
     }
 
-    fn cache_unplay(&mut self, hash: u64) {
-        if let Some(f) = self.hashes_temp.get_mut(&hash) {
-            f.playcount -= 1;
-        } else {
-            // possibly bad hash_clear() call throught the minimax search iteration
-            panic!("Attempt to 'unplay' non-existent position");
+    pub fn get_pst(&self) -> Pst {
+        let tables: Vec<(char, &[f32], &[f32])> = self.pst.iter().map(|(c, table)| (*c, &table.mg[..], &table.eg[..])).collect();
+        Pst::new(&tables)
+    }
+
+    // default tables (in pawns)
+    const PST_P: [f32; 64] = [
+        0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0,
+        0.5,  0.5,  0.5,  0.5,  0.5,  0.5,  0.5,  0.5,
+        0.1,  0.1,  0.2,  0.3,  0.3,  0.2,  0.1,  0.1,
+        0.05, 0.05, 0.1,  0.25, 0.25, 0.1,  0.05, 0.05,
+        0.0,  0.0,  0.0,  0.2,  0.2,  0.0,  0.0,  0.0,
+        0.05, -0.05,-0.1, 0.0,  0.0,  -0.1, -0.05,0.05,
+        0.05, 0.1,  0.1,  -0.2, -0.2, 0.1,  0.1,  0.05,
+        0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0
+    ];
+    const PST_N: [f32; 64] = [
+        -0.5, -0.4, -0.3, -0.3, -0.3, -0.3, -0.4, -0.5,
+        -0.4, -0.2, 0.0,  0.0,  0.0,  0.0,  -0.2, -0.4,
+        -0.3, 0.0,  0.1,  0.15, 0.15, 0.1,  0.0,  -0.3,
+        -0.3, 0.05, 0.15, 0.2,  0.2,  0.15, 0.05, -0.3,
+        -0.3, 0.0,  0.15, 0.2,  0.2,  0.15, 0.0,  -0.3,
+        -0.3, 0.05, 0.1,  0.15, 0.15, 0.1,  0.05, -0.3,
+        -0.4, -0.2, 0.0,  0.05, 0.05, 0.0,  -0.2, -0.4,
+        -0.5, -0.4, -0.3, -0.3, -0.3, -0.3, -0.4, -0.5
+    ];
+    const PST_B: [f32; 64] = [
+        -0.2, -0.1, -0.1, -0.1, -0.1, -0.1, -0.1, -0.2,
+        -0.1, 0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.1,
+        -0.1, 0.0,  0.05, 0.1,  0.1,  0.05, 0.0,  -0.1,
+        -0.1, 0.05, 0.05, 0.1,  0.1,  0.05, 0.05, -0.1,
+        -0.1, 0.0,  0.1,  0.1,  0.1,  0.1,  0.0,  -0.1,
+        -0.1, 0.1,  0.1,  0.1,  0.1,  0.1,  0.1,  -0.1,
+        -0.1, 0.05, 0.0,  0.0,  0.0,  0.0,  0.05, -0.1,
+        -0.2, -0.1, -0.1, -0.1, -0.1, -0.1, -0.1, -0.2
+    ];
+    const PST_R: [f32; 64] = [
+        0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  0.0,
+        0.05, 0.1,  0.1,  0.1,  0.1,  0.1,  0.1,  0.05,
+        -0.05,0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.05,
+        -0.05,0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.05,
+        -0.05,0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.05,
+        -0.05,0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.05,
+        -0.05,0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.05,
+        0.0,  0.0,  0.0,  0.05, 0.05, 0.0,  0.0,  0.0
+    ];
+    const PST_Q: [f32; 64] = [
+        -0.2, -0.1, -0.1, -0.05,-0.05,-0.1, -0.1, -0.2,
+        -0.1, 0.0,  0.0,  0.0,  0.0,  0.0,  0.0,  -0.1,
+        -0.1, 0.0,  0.05, 0.05, 0.05, 0.05, 0.0,  -0.1,
+        -0.05,0.0,  0.05, 0.05, 0.05, 0.05, 0.0,  -0.05,
+        0.0,  0.0,  0.05, 0.05, 0.05, 0.05, 0.0,  -0.05,
+        -0.1, 0.05, 0.05, 0.05, 0.05, 0.05, 0.0,  -0.1,
+        -0.1, 0.0,  0.05, 0.0,  0.0,  0.0,  0.0,  -0.1,
+        -0.2, -0.1, -0.1, -0.05,-0.05,-0.1, -0.1, -0.2
+    ];
+    const PST_K: [f32; 64] = [
+        -0.3, -0.4, -0.4, -0.5, -0.5, -0.4, -0.4, -0.3,
+        -0.3, -0.4, -0.4, -0.5, -0.5, -0.4, -0.4, -0.3,
+        -0.3, -0.4, -0.4, -0.5, -0.5, -0.4, -0.4, -0.3,
+        -0.3, -0.4, -0.4, -0.5, -0.5, -0.4, -0.4, -0.3,
+        -0.2, -0.3, -0.3, -0.4, -0.4, -0.3, -0.3, -0.2,
+        -0.1, -0.2, -0.2, -0.2, -0.2, -0.2, -0.2, -0.1,
+        0.2,  0.2,  0.0,  0.0,  0.0,  0.0,  0.2,  0.2,
+        0.2,  0.3,  0.1,  0.0,  0.0,  0.1,  0.3,  0.2
+    ];
+
+    // Fields and pieces missing in the config keep their default values, so a personality only lists what it changes.
+    // Every field is applied separately, so a wrong type or unknown key is reported by its name.
+    fn from_value(value: serde_json::Value) -> io::Result<Self> {
+        let serde_json::Value::Object(fields) = value else {
+            return Err(Self::invalid("config must be a table of weights".to_string()));
+        };
+        let mut merged = serde_json::to_value(Self::new()).unwrap();
+        for (name, field) in fields {
+            let Some(slot) = merged.get_mut(&name) else {
+                return Err(Self::invalid(format!("{}: unknown field", name)));
+            };
+            // piece tables are merged as well, so a single piece value can be changed
+            match (slot, field) {
+                (serde_json::Value::Object(slot), serde_json::Value::Object(field)) => slot.extend(field),
+                (slot, field) => *slot = field
+            }
+            if let Err(e) = serde_json::from_value::<Self>(merged.clone()) {
+                return Err(Self::invalid(format!("{}: {}", name, e)));
+            }
+        }
+        let weights: Self = serde_json::from_value(merged).unwrap();
+        weights.validate()?;
+        Ok(weights)
+    }
+
+    // all weights by their names, e.g. "outpost_multiplier.mg", "material_cost.q.eg" or "mobility_max_threshold.r"
+    pub fn get_params(&self) -> BTreeMap<String, f32> {
+        let mut params = BTreeMap::new();
+        Self::flatten("", &serde_json::to_value(self).unwrap(), &mut params);
+        params
+    }
+
+    fn flatten(name: &str, value: &serde_json::Value, params: &mut BTreeMap<String, f32>) {
+        let join = |key: &str| if name.is_empty() { key.to_string() } else { format!("{}.{}", name, key) };
+        match value {
+            serde_json::Value::Object(map) => map.iter().for_each(|(key, value)| Self::flatten(&join(key), value, params)),
+            // tapered weights are [mg, eg] pairs
+            serde_json::Value::Array(array) if array.len() == 2 && array.iter().all(|v| v.is_number()) => {
+                params.insert(join("mg"), array[0].as_f64().unwrap() as f32);
+                params.insert(join("eg"), array[1].as_f64().unwrap() as f32);
+            },
+            serde_json::Value::Array(array) => array.iter().enumerate().for_each(|(i, value)| Self::flatten(&join(&i.to_string()), value, params)),
+            _ => { params.insert(name.to_string(), value.as_f64().unwrap() as f32); }
+        }
+    }
+
+    // copy of the weights with the given params (see get_params) replaced
+    pub fn with_params(&self, params: &BTreeMap<String, f32>) -> io::Result<Self> {
+        let mut value = serde_json::to_value(self).unwrap();
+        for (name, param) in params {
+            let slot = name.split('.').try_fold(&mut value, |slot, key| match slot {
+                serde_json::Value::Array(array) => match key {
+                    "mg" => array.get_mut(0),
+                    "eg" => array.get_mut(1),
+                    _ => key.parse::<usize>().ok().and_then(|i| array.get_mut(i))
+                },
+                _ => slot.get_mut(key)
+            });
+            match slot {
+                Some(slot) => *slot = serde_json::json!(param),
+                None => return Err(Self::invalid(format!("{}: unknown field", name)))
+            }
         }
+        let weights: Self = serde_json::from_value(value).map_err(|e| Self::invalid(e.to_string()))?;
+        weights.validate()?;
+        Ok(weights)
     }
-    
-    fn is_played(&self, hash: u64) -> bool {
-        if let Some(f) = self.hashes_temp.get(&hash) {
-            if f.playcount > 0 {
-                return true;
+
+    // checks that evaluation won't panic on missing pieces or go crazy on NaN/negative values
+    pub fn validate(&self) -> io::Result<()> {
+        let maps: [(&str, Vec<&char>, &str); 5] = [
+            ("material_cost", self.material_cost.keys().collect(), "pknbrq"),
+            ("mobility_k", self.mobility_k.keys().collect(), "knbrq"),
+            ("mobility_min_threshold", self.mobility_min_threshold.keys().collect(), "knbrq"),
+            ("mobility_max_threshold", self.mobility_max_threshold.keys().collect(), "knbrq"),
+            ("king_attack_units", self.king_attack_units.keys().collect(), "nbrq")
+        ];
+        for (name, keys, pieces) in maps {
+            for c in pieces.chars() {
+                if !keys.contains(&&c) {
+                    return Err(Self::invalid(format!("{}.{}: missing value", name, c)));
+                }
             }
+            for c in keys {
+                if !"pknbrq".contains(*c) {
+                    return Err(Self::invalid(format!("{}.{}: unknown piece, expected one of pknbrq", name, c)));
+                }
+            }
+        }
+        if self.king_attack_table.is_empty() {
+            return Err(Self::invalid("king_attack_table: expected at least one value".to_string()));
         }
-        if let Some(f) = self.hashes_perm.get(&hash) {
-            return *f > 1;
+        for c in "pknbrq".chars() {
+            let table = self.pst.get(&c).ok_or_else(|| Self::invalid(format!("pst.{}: missing value", c)))?;
+            for (stage, values) in [("mg", &table.mg), ("eg", &table.eg)] {
+                if values.len() != 64 {
+                    return Err(Self::invalid(format!("pst.{}.{}: expected 64 values, got {}", c, stage, values.len())));
+                }
+            }
+        }
+        if let Some(c) = self.pst.keys().find(|c| !"pknbrq".contains(**c)) {
+            return Err(Self::invalid(format!("pst.{}: unknown piece, expected one of pknbrq", c)));
+        }
+        for (name, value) in self.get_params() {
+            // piece-square tables are the only weights that go below zero
+            if name.starts_with("pst.") && value.is_finite() {
+                continue;
+            }
+            Self::check_value(&name, value)?;
+        }
+        for (c, value) in &self.material_cost {
+            if value.mg == 0.0 || value.eg == 0.0 {
+                return Err(Self::invalid(format!("material_cost.{}: must be positive", c)));
+            }
+        }
+        for (c, min_cells) in &self.mobility_min_threshold {
+            if let Some(max_cells) = self.mobility_max_threshold.get(c) {
+                if min_cells > max_cells {
+                    return Err(Self::invalid(format!("mobility_min_threshold.{}: is greater than mobility_max_threshold.{}", c, c)));
+                }
+            }
         }
-        false
+        Ok(())
     }
 
-    fn is_evaluated(&self, hash: u64) -> bool {
-        if let Some(f) = self.hashes_temp.get(&hash) {
-            return f.eval != Eval::unevaluated();
-        } else {
-            return false;
+    fn check_value(name: &str, value: f32) -> io::Result<()> {
+        if !value.is_finite() || value < 0.0 {
+            return Err(Self::invalid(format!("{}: {} is not a finite non-negative number", name, value)));
         }
+        Ok(())
     }
 
-    fn get_hashed_eval(&self, hash: u64) -> Eval {
-        self.hashes_temp.get(&hash).unwrap().eval
+    fn invalid(message: String) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, message)
     }
+}
+
+// Character settings that are not evaluation weights (they are never tuned)
+#[derive(Clone, PartialEq, Debug, Default, Serialize)]
+pub struct GenericSettings {
+    // seed of the character random choices (see Character::set_seed), random if missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    // how much worse than equal a draw is for the character (in pawns), negative ones seek draws
+    pub contempt: f32
+}
 
-    fn get_hashed_depth(&self, hash: u64) -> i8 {
-        self.hashes_temp.get(&hash).unwrap().depth
+impl GenericSettings {
+    // configs are saved to TOML as well, its integers are signed
+    pub const MAX_SEED: u64 = i64::MAX as u64;
+
+    // seed given by the user (command line, console), in the range of the config seeds
+    pub fn parse_seed(text: &str) -> Option<u64> {
+        text.parse().ok().filter(|seed| *seed <= Self::MAX_SEED)
     }
 }
 
-impl Generic {
-    pub fn new(weights: GenericWeights, depths: &[i8; 3]) -> Self {
-        // TODO: this should be passed as params
-        let piece_costs = [1., 255., 3., 3., 4.5, 9.];
-        Self { 
-            weights,
-            static_half_depth: depths[0],
-            mixed_half_depth: depths[1],
-            dynamic_half_depth: depths[2],
-            hashtable: Hashtable::new(1024),
-            hashes_temp: HashMap::new(),
-            hashes_perm: HashMap::new(),
-            hashes_perm_history: Vec::default(),
-            alpha_stack: [Eval::low()].to_vec(),
-            beta_stack: [Eval::high()].to_vec(),
-            evals: Vec::default()
+// Config file of the character: settings and weights are top-level keys of the same TOML or JSON file,
+// settings go first
+#[derive(Clone, PartialEq, Debug, Default, Serialize)]
+pub struct GenericConfig {
+    #[serde(flatten)]
+    pub settings: GenericSettings,
+    #[serde(flatten)]
+    pub weights: GenericWeights
+}
+
+impl GenericConfig {
+    // read config from .json file, any other extension is read as TOML
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        if path.to_ascii_lowercase().ends_with(".json") {
+            Self::from_json(&text)
+        } else {
+            Self::from_toml(&text)
         }
     }
 
-    fn battery_search_vertical() -> f32 {
+    // write config to .json file, any other extension is written as TOML
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let text = if path.to_ascii_lowercase().ends_with(".json") { self.to_json() } else { self.to_toml() };
+        fs::write(path, text)
+    }
 
-        0.0
+    pub fn from_toml(text: &str) -> io::Result<Self> {
+        let table: toml::Table = toml::from_str(text).map_err(|e| GenericWeights::invalid(format!("bad TOML: {}", e.message())))?;
+        Self::from_value(serde_json::to_value(table).map_err(|e| GenericWeights::invalid(e.to_string()))?)
     }
 
-    fn battery_search_horizontal() -> f32 {
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        Self::from_value(serde_json::from_str(text).map_err(|e| GenericWeights::invalid(format!("bad JSON: {}", e)))?)
+    }
 
-        0.0
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).unwrap()
     }
 
-    fn battery_search_diagonal() -> f32 {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
 
-        0.0
+    // settings are taken out of the config, the rest of it is weights (see GenericWeights::from_value)
+    fn from_value(value: serde_json::Value) -> io::Result<Self> {
+        let serde_json::Value::Object(mut fields) = value else {
+            return Err(GenericWeights::invalid("config must be a table of weights".to_string()));
+        };
+        let mut settings = GenericSettings::default();
+        if let Some(seed) = fields.remove("seed") {
+            settings.seed = Some(seed.as_u64().filter(|seed| *seed <= GenericSettings::MAX_SEED)
+                .ok_or_else(|| GenericWeights::invalid(format!("seed: expected an integer from 0 to {}", GenericSettings::MAX_SEED)))?);
+        }
+        if let Some(contempt) = fields.remove("contempt") {
+            settings.contempt = contempt.as_f64().filter(|contempt| contempt.is_finite())
+                .ok_or_else(|| GenericWeights::invalid("contempt: must be a number".to_string()))? as f32;
+        }
+        Ok(Self { settings, weights: GenericWeights::from_value(serde_json::Value::Object(fields))? })
     }
 }
 
-pub struct GenericWeights {
-    // pknbrq
-    material_cost: HashMap<char, f32>,
-    // knbrq
-    mobility_k: HashMap<char, f32>,
-    // amount of cells
-    mobility_min_threshold: HashMap<char, f32>,
-    mobility_max_threshold: HashMap<char, f32>,
-    //
-    pawn_islands_penalty: [f32; 4],
-    pawn_stacked_penalty: f32,
-    pawn_advanced_multiplier: f32,
-    pawn_passed_multiplier: f32,
-    battery_vertical_cost: f32,
-    battery_horizontal_cost: f32,
-    battery_diagonal_cost: f32,
-    // battery_xray_self_multiplier: f32,
-    // battery_xray_opponent_multiplier: f32,
-    king_threat_multiplier: f32,
-    defend_cheaper_piece_multiplier: f32,
-    outpost_multiplier: f32,
-    // king_safety_k: f32,
-    k_kvb: f32    
+impl Default for GenericWeights {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl GenericWeights {
-    pub fn new() -> Self {
-        todo!();
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, engine::{character::{Character, Evaluator}, eval::Eval, strength::Strength, store::AnalysisStore}, utils::utils::{move_to_board, move_to_user}};
+    use super::{Generic, GenericEval, GenericConfig, GenericSettings, GenericWeights, Tapered};
+
+    fn eval(fen: &str) -> f32 {
+        GenericEval::new(GenericWeights::new()).get_static_eval(&Board::parse_fen(fen))
+    }
+
+    #[test]
+    fn test_generic_eval_01() {
+        // symmetric positions are equal
+        assert!(eval("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").abs() < 0.001);
+        assert!(eval("r1bqkb1r/pppp1ppp/2n2n2/4p3/4P3/2N2N2/PPPP1PPP/R1BQKB1R w KQkq - 4 4").abs() < 0.001);
+    }
+
+    #[test]
+    fn test_generic_eval_02() {
+        assert!(eval("rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1") > 8.0);
+        assert!(eval("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1") < -8.0);
+    }
+
+    #[test]
+    fn test_generic_eval_pawns_01() {
+        // passed pawn is worth more the further it goes
+        assert!(eval("4k3/p7/4P3/8/8/8/P7/4K3 w - - 0 1") > eval("4k3/p7/8/8/8/4P3/P7/4K3 w - - 0 1"));
+        // stacked pawns
+        assert!(eval("4k3/pp6/8/8/8/P7/P7/4K3 w - - 0 1") < 0.0);
+    }
+
+    #[test]
+    fn test_generic_eval_batteries_01() {
+        let board = Board::parse_fen("3rk3/3q4/8/8/8/8/R7/R3K3 w - - 0 1");
+        assert_eq!(GenericEval::battery_search(&board, &[(1, 0)], &['r', 'q']), [-1.0, 1.0]);
+        let board = Board::parse_fen("3rk3/8/3q4/8/8/8/R7/R3K3 w - - 0 1");
+        assert_eq!(GenericEval::battery_search(&board, &[(1, 0)], &['r', 'q']), [-1.0, 1.0]);
+        let board = Board::parse_fen("4k3/8/8/8/8/8/R7/R3K3 w - - 0 1");
+        assert_eq!(GenericEval::battery_search(&board, &[(1, 0)], &['r', 'q']), [0.0, 1.0]);
+        let board = Board::parse_fen("4k3/8/8/8/8/2Q5/1B6/4K3 w - - 0 1");
+        assert_eq!(GenericEval::battery_search(&board, &[(1, 1), (1, -1)], &['b', 'q']), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_generic_weights_config_01() {
+        let config = GenericConfig::default();
+        assert!(GenericConfig::from_toml(&config.to_toml()).unwrap() == config);
+        assert!(GenericConfig::from_json(&config.to_json()).unwrap() == config);
+    }
+
+    #[test]
+    fn test_generic_weights_config_02() {
+        // missing fields keep default values
+        let weights = GenericConfig::from_toml("k_kvb = 0.5\n[material_cost]\nq = [9.0, 10.0]\n").unwrap().weights;
+        assert_eq!(weights.k_kvb, Tapered::new(0.5, 0.5));
+        assert_eq!(weights.material_cost[&'q'], Tapered::new(9.0, 10.0));
+        assert_eq!(weights.material_cost[&'r'], GenericWeights::new().material_cost[&'r']);
+        assert_eq!(weights.outpost_multiplier, GenericWeights::new().outpost_multiplier);
+        let weights = GenericConfig::from_json("{\"pawn_islands_penalty\": [0, 0.1, [0.2, 0.3], 0.3]}").unwrap().weights;
+        assert_eq!(weights.pawn_islands_penalty[2], Tapered::new(0.2, 0.3));
+        assert_eq!(weights.pawn_islands_penalty[3], Tapered::new(0.3, 0.3));
+    }
+
+    #[test]
+    fn test_generic_weights_config_03() {
+        // errors name the bad field
+        let error = |result: std::io::Result<GenericConfig>| result.err().unwrap().to_string();
+        assert!(error(GenericConfig::from_toml("k_kvb = \"high\"")).starts_with("k_kvb:"));
+        assert!(error(GenericConfig::from_toml("kvb = 0.1")).starts_with("kvb: unknown field"));
+        assert!(error(GenericConfig::from_json("{\"pawn_islands_penalty\": [0, 1]}")).starts_with("pawn_islands_penalty:"));
+        assert!(error(GenericConfig::from_json("{\"outpost_multiplier\": [0.2, -1]}")).starts_with("outpost_multiplier.eg:"));
+        assert!(error(GenericConfig::from_toml("[mobility_k]\nx = 1.0")).starts_with("mobility_k.x:"));
+        assert!(error(GenericConfig::from_toml("[mobility_min_threshold]\nn = 10.0")).starts_with("mobility_min_threshold.n:"));
+        assert!(error(GenericConfig::from_toml("[material_cost]\np = 0.0")).starts_with("material_cost.p:"));
+    }
+
+    #[test]
+    fn test_generic_weights_config_04() {
+        // settings go along with the weights, but they are not weights to tune
+        let config = GenericConfig::from_toml("seed = 42\ncontempt = 0.25\nk_kvb = 0.5").unwrap();
+        assert_eq!(config.settings, GenericSettings { seed: Some(42), contempt: 0.25 });
+        assert_eq!(config.weights.k_kvb, Tapered::new(0.5, 0.5));
+        assert!(GenericConfig::from_toml(&config.to_toml()).unwrap() == config);
+        assert!(GenericConfig::from_json(&config.to_json()).unwrap() == config);
+        assert!(!config.weights.get_params().keys().any(|name| name == "seed" || name == "contempt"));
+        assert!(!GenericConfig::default().to_toml().contains("seed"));
+        assert!(GenericConfig::from_toml("seed = -1").err().unwrap().to_string().starts_with("seed:"));
+        // TOML can't keep a bigger seed, so JSON doesn't take it either
+        assert!(GenericConfig::from_json(&format!("{{\"seed\": {}}}", u64::MAX)).err().unwrap().to_string().starts_with("seed:"));
+        assert_eq!(GenericSettings::parse_seed(&i64::MAX.to_string()), Some(GenericSettings::MAX_SEED));
+        assert_eq!(GenericSettings::parse_seed(&u64::MAX.to_string()), None);
+        assert!(GenericConfig::from_toml("contempt = \"high\"").err().unwrap().to_string().starts_with("contempt:"));
+        assert_eq!(Generic::with_settings(config.weights, config.settings, &[1, 1, 1]).get_seed(), 42);
+    }
+
+    #[test]
+    fn test_generic_phase_01() {
+        assert_eq!(GenericEval::get_phase(&Board::parse_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")), 1.0);
+        assert_eq!(GenericEval::get_phase(&Board::parse_fen("4k3/pppp4/8/8/8/8/PPPP4/4K3 w - - 0 1")), 0.0);
+        assert_eq!(GenericEval::get_phase(&Board::parse_fen("r3k3/pppp4/8/8/8/8/PPPP4/R3K3 w - - 0 1")), 4.0 / 24.0);
+        assert_eq!(Tapered::new(1.0, 3.0).taper(0.5), 2.0);
+    }
+
+    #[test]
+    fn test_generic_eval_tapered_01() {
+        // central king is good in pawn endgame
+        assert!(eval("4k3/pppp4/8/8/3K4/8/PPPP4/8 w - - 0 1") > 0.2);
+        assert!(eval("8/pppp4/8/3k4/8/8/PPPP4/K7 w - - 0 1") < -0.2);
+    }
+
+    #[test]
+    fn test_generic_eval_pst_01() {
+        // incrementally updated tables give the same eval as the full recalculation
+        let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings::default(), &[2, 2, 2]);
+        let plain = GenericEval::new(GenericWeights::new());
+        let mut board = Board::parse_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+        char.get_eval_moves(&mut board);
+        for mov in ["f1c4", "g8f6", "e1g1"] {
+            let mov = move_to_board(&board, mov);
+            char.make_move(&mut board, &mov);
+            assert!((char.get_static_eval(&board) - plain.get_static_eval(&board)).abs() < 0.001);
+        }
+        char.revert_move(&mut board);
+        assert!((char.get_static_eval(&board) - plain.get_static_eval(&board)).abs() < 0.001);
+        // knight on the rim is dim
+        assert!(eval("4k3/8/8/8/8/8/8/N3K2n w - - 0 1") < eval("4k3/8/8/8/8/5N2/8/4K2n w - - 0 1"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generic_king_safety_01() {
+        let char = GenericEval::new(GenericWeights::new());
+        let shelter = |fen: &str| {
+            let board = Board::parse_fen(fen);
+            let mut pawns_by_verticals = [[0; 2]; 8];
+            for y in 0..8 {
+                for x in 0..8 {
+                    if board.field[y][x] & 254 == board.gpl(&'p') {
+                        pawns_by_verticals[x][(board.field[y][x] & 1) as usize] += 1;
+                    }
+                }
+            }
+            char.get_king_shelter(&board, board.white_king_location, 1, &pawns_by_verticals).mg
+        };
+        let castled = shelter("4k3/pppppppp/8/8/8/8/PPPPPPPP/6K1 w - - 0 1");
+        // pushed shield pawn, storming pawn and open vertical are all worse
+        assert!(shelter("4k3/pppppppp/8/8/8/6P1/PPPPPP1P/6K1 w - - 0 1") < castled);
+        assert!(shelter("4k3/pppppp1p/8/8/8/6p1/PPPPPPPP/6K1 w - - 0 1") < castled);
+        assert!(shelter("4k3/pppppp1p/8/8/8/8/PPPPPP1P/6K1 w - - 0 1") < shelter("4k3/pppppppp/8/8/8/8/PPPPPP1P/6K1 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_generic_king_safety_02() {
+        // queen and knight attacking the castled king together, but a single queen is not enough
+        let mut weights = GenericWeights::new();
+        weights.king_safety_k = Tapered::default();
+        let unsafe_eval = |fen: &str| eval(fen) - GenericEval::new(weights.clone()).get_static_eval(&Board::parse_fen(fen));
+        assert!(unsafe_eval("r5k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1") > 0.1);
+        assert_eq!(unsafe_eval("r5k1/5ppp/8/7Q/8/8/5PPP/6K1 w - - 0 1"), 0.0);
+    }
+
+    #[test]
+    fn test_generic_pawn_structure_01() {
+        let char = GenericEval::new(GenericWeights::new());
+        let pawns = |fen: &str| char.eval_pawn_structure(&Board::parse_fen(fen)).iter().flatten().map(|term| term.eg).sum::<f32>();
+        // isolated
+        assert!(pawns("4k3/pp6/8/8/8/8/P1P5/4K3 w - - 0 1") < pawns("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1"));
+        // backward: c3 can't be supported and c4 is controlled by d5
+        assert!(pawns("4k3/8/8/3p4/1P6/2P5/8/4K3 w - - 0 1") < pawns("4k3/8/8/3p4/1PP5/8/8/4K3 w - - 0 1"));
+        // connected pawns are better the further they go
+        assert!(pawns("4k3/8/8/1PP5/8/8/8/4K3 w - - 0 1") > pawns("4k3/8/8/8/8/8/1PP5/4K3 w - - 0 1"));
+        // candidate: b5 is supported by c4 against a single sentry on c6, but not when it's blocked by b6
+        let mut weights = GenericWeights::new();
+        weights.pawn_candidate_multiplier = Tapered::default();
+        let no_candidates = GenericEval::new(weights);
+        let candidates = |fen: &str| pawns(fen) - no_candidates.eval_pawn_structure(&Board::parse_fen(fen)).iter().flatten().map(|term| term.eg).sum::<f32>();
+        assert!(candidates("4k3/8/2p5/1P6/2P5/8/8/4K3 w - - 0 1") > 0.0);
+        assert_eq!(candidates("4k3/8/1pp5/1P6/2P5/8/8/4K3 w - - 0 1"), 0.0);
+        // mirrored structures are equal
+        assert!(pawns("4k3/p1p5/8/3p4/8/6P1/1P3P1P/4K3 w - - 0 1") + pawns("4k3/1p3p1p/6p1/8/3P4/8/P1P5/4K3 w - - 0 1") == 0.0);
+    }
+
+    #[test]
+    fn test_generic_pawn_structure_02() {
+        // cached score is the same as a fresh one, and pieces don't change the key
+        let char = GenericEval::new(GenericWeights::new());
+        let board = Board::parse_fen("r3k3/pp3ppp/2p5/3p4/3P4/2P5/PP3PPP/R3K3 w - - 0 1");
+        let score = char.get_pawn_structure(&board);
+        assert!(score == char.eval_pawn_structure(&board));
+        char.get_pawn_structure(&Board::parse_fen("4k3/pp3ppp/2p5/3p4/3P4/2P5/PP3PPP/4K3 w - - 0 1"));
+        assert_eq!(char.pawn_cache.borrow().len(), 1);
+        assert!(char.get_pawn_structure(&board) == score);
+    }
+
+    #[test]
+    fn test_generic_explain_01() {
+        let char = GenericEval::new(GenericWeights::new());
+        for fen in [
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "r5k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1",
+            "4k3/8/1pp5/1P6/2P5/8/8/4K3 w - - 0 1",
+            "8/8/8/8/8/2b5/P7/K1k5 w - - 0 1"
+        ] {
+            let board = Board::parse_fen(fen);
+            let explanation = char.explain_eval(&board);
+            assert_eq!(explanation.total, char.get_static_eval(&board));
+            let sum: f32 = explanation.terms.iter().map(|term| term.value).sum();
+            assert_eq!(sum * explanation.scale, explanation.total);
+            assert!(explanation.rule.is_none());
+        }
+        // decided by rule
+        let board = Board::parse_fen("4k3/8/8/8/8/8/8/4K2Q w - - 0 1");
+        let explanation = char.explain_eval(&board);
+        assert!(explanation.rule.is_some() && explanation.total == char.get_static_eval(&board));
+        assert!(explanation.to_json().contains("\"total\""));
+    }
+
+    #[test]
+    fn test_generic_explain_02() {
+        // per side: the pawn up side has more material, the other one has nothing to show
+        let explanation = GenericEval::new(GenericWeights::new()).explain_eval(&Board::parse_fen("r3k3/pp6/8/8/8/8/PPP5/R3K3 w - - 0 1"));
+        let material = explanation.terms.iter().find(|term| term.name == "material").unwrap();
+        assert!(material.white[0] > -material.black[0]);
+        assert_eq!(material.value, 1.0);
+    }
+
+    #[test]
+    fn test_generic_strength_01() {
+        // noise is the same for the same position, explanation still adds up
+        let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings::default(), &[4, 6, 64]);
+        let board = Board::parse_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let full = char.get_static_eval(&board);
+        char.set_strength(Some(Strength::from_elo(800)));
+        let noisy = char.get_static_eval(&board);
+        assert!(noisy != full && noisy == char.get_static_eval(&board));
+        let explanation = char.explain_eval(&board);
+        assert_eq!(explanation.total, noisy);
+        assert!((explanation.noise - (noisy - full)).abs() < 1e-5);
+        char.set_strength(None);
+        assert_eq!(char.get_static_eval(&board), full);
+    }
+
+    #[test]
+    fn test_generic_strength_02() {
+        // node limit doesn't lose any moves, the weakest level still takes a hanging queen
+        let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings::default(), &[4, 6, 64]);
+        char.set_strength(Some(Strength { max_nodes: 10, ..Strength::from_elo(800) }));
+        let mut board = Board::parse_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        let legals = board.get_legal_moves(None, None).len();
+        assert_eq!(char.get_eval_moves(&mut board).len(), legals);
+        char.set_strength(Some(Strength::from_elo(800)));
+        for _ in 0..20 {
+            let eval_mov = char.get_eval_move(&mut board);
+            assert_eq!(move_to_user(&board, &eval_mov.mov), "d2d5");
+        }
+    }
+
+    #[test]
+    fn test_generic_seed_01() {
+        // the same seed plays the same, other seeds don't (at least some of them)
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let play = |seed: u64| {
+            let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings { seed: Some(seed), contempt: 0.0 }, &[2, 2, 4]);
+            char.set_strength(Some(Strength::from_elo(800)));
+            let mut board = Board::parse_fen(fen);
+            let eval_mov = char.get_eval_move(&mut board);
+            move_to_user(&board, &eval_mov.mov)
+        };
+        let moves: Vec<String> = (0..5).map(play).collect();
+        assert_eq!(moves, (0..5).map(play).collect::<Vec<String>>());
+        assert!(moves.iter().any(|mov| *mov != moves[0]));
+    }
+
+    #[test]
+    fn test_generic_seed_02() {
+        // all king moves are a draw: full strength breaks the tie by the seed
+        let play = |seed: u64| {
+            let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings::default(), &[2, 2, 4]);
+            char.set_seed(seed);
+            let mut board = Board::parse_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+            let eval_mov = char.get_eval_move(&mut board);
+            move_to_user(&board, &eval_mov.mov)
+        };
+        let moves: Vec<String> = (0..8).map(play).collect();
+        assert_eq!(moves, (0..8).map(play).collect::<Vec<String>>());
+        assert!(moves.iter().any(|mov| *mov != moves[0]));
+    }
+
+    #[test]
+    fn test_generic_draws_01() {
+        // black is a queen up, but the king going back to h8 repeats the position for the third time
+        let play = |contempt: f32| {
+            let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings { seed: None, contempt }, &[2, 2, 4]);
+            let mut board = Board::parse_fen("7k/8/8/8/8/8/q7/6NK w - - 0 1");
+            char.get_eval_moves(&mut board);
+            for mov in ["g1f3", "h8g8", "f3g1", "g8h8", "g1f3", "h8g8", "f3g1"] {
+                board.make_move(&move_to_board(&board, mov));
+                char.accept_move(&board);
+            }
+            let evals = char.get_eval_moves(&mut board).clone();
+            let repetition = evals.iter().find(|eval_mov| move_to_user(&board, &eval_mov.mov) == "g8h8").unwrap().eval;
+            (repetition, move_to_user(&board, &evals[0].mov))
+        };
+        let (repetition, best) = play(0.0);
+        assert!(repetition == Eval::equal() && best != "g8h8");
+        // a draw is worse than equal for black with contempt (white-based score goes up)
+        assert!(play(0.5).0 == Eval::new(0.5, 0));
+    }
+
+    #[test]
+    fn test_generic_draws_02() {
+        let evals = |fen: &str, contempt: f32| {
+            let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings { seed: None, contempt }, &[2, 2, 4]);
+            let mut board = Board::parse_fen(fen);
+            char.get_eval_moves(&mut board).iter().map(|eval_mov| (move_to_user(&board, &eval_mov.mov), eval_mov.eval)).collect::<Vec<_>>()
+        };
+        // the hundredth half-move is a draw, unless it's a checkmate
+        let moves = evals("7k/8/6K1/8/8/8/8/5Q2 w - - 99 80", 0.0);
+        assert!(moves[0].0 == "f1f8" && moves[0].1.mate_in == 1);
+        assert!(moves.iter().filter(|(mov, _)| mov == "f1f2" || mov == "g6f6").all(|(_, eval)| *eval == Eval::equal()));
+        // nothing to checkmate with: every move is a draw, scored with the contempt of white
+        assert!(evals("8/8/4k3/8/8/8/8/3NK3 w - - 0 1", 0.5).iter().all(|(_, eval)| *eval == Eval::new(-0.5, 0)));
+    }
+
+    #[test]
+    fn test_generic_store_01() {
+        // the next session takes the stored analysis instead of the search, other settings don't
+        let path = std::env::temp_dir().join("cce_test_generic_store_01.bin");
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let session = |depths: &[i8; 3]| {
+            let mut char = Generic::with_settings(GenericWeights::new(), GenericSettings::default(), depths);
+            let mut store = AnalysisStore::open(path, 16).unwrap();
+            store.min_depth = 2;
+            char.set_store(Some(store));
+            let mut board = Board::parse_fen(fen);
+            let evals = char.get_eval_moves(&mut board).clone();
+            (evals.len(), move_to_user(&board, &evals[0].mov))
+        };
+        let (searched, best) = session(&[2, 2, 4]);
+        assert!(searched > 1);
+        assert_eq!(session(&[2, 2, 4]), (1, best));
+        assert!(session(&[2, 2, 6]).0 > 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1 +1,4 @@
-pub(crate) mod generic;
\ No newline at end of file
+pub(crate) mod generic;
+pub(crate) mod pst;
+pub(crate) mod tuning;
+pub(crate) mod personalities;
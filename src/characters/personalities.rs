@@ -0,0 +1,205 @@
+use super::generic::{Generic, GenericConfig, GenericSettings, GenericWeights};
+
+// Built-in playing styles: all of them are Generic with its evaluation terms weighted differently
+// and their own search depths. Any of them can be dumped to a config file and tweaked further.
+// TODO: selection by name is only done with --character on the command line, there is no UCI protocol yet
+// to expose it as an option (UCI_Opponent-like "Character" combo), add it together with the UCI loop.
+pub struct Personality {
+    pub name: &'static str,
+    pub description: &'static str,
+    // static, mixed and dynamic half-depths
+    pub depths: [i8; 3],
+    // see GenericSettings
+    pub contempt: f32,
+    // changes on top of the default weights
+    tune: fn(&mut GenericWeights)
+}
+
+impl Personality {
+    pub const DEFAULT: &'static str = "generic";
+
+    // Depths only differ where the style is about seeing further: the attacker follows checks deeper,
+    // the materialist doesn't look at checks beyond the full-width search.
+    // Positional play and pawn storms are about what a position is worth, not about forcing lines,
+    // so they keep the default depths (the same static depth for all keeps their strength comparable).
+    pub const ALL: [Personality; 5] = [
+        Personality {
+            name: "generic",
+            description: "balanced default weights",
+            depths: [4, 6, 64],
+            contempt: 0.0,
+            tune: |_| ()
+        },
+        Personality {
+            name: "attacker",
+            description: "goes for the king, gives up material for the initiative",
+            depths: [4, 8, 64],
+            // plays on rather than repeats
+            contempt: 0.3,
+            tune: Self::tune_attacker
+        },
+        Personality {
+            name: "positional",
+            description: "slow play: pawn structure, outposts, safe king",
+            depths: [4, 6, 64],
+            contempt: 0.0,
+            tune: Self::tune_positional
+        },
+        Personality {
+            name: "materialist",
+            description: "grabs everything that isn't nailed down",
+            depths: [4, 4, 64],
+            // takes a draw rather than risks what it has
+            contempt: -0.1,
+            tune: Self::tune_materialist
+        },
+        Personality {
+            name: "pawnstormer",
+            description: "throws pawns at the opponent king",
+            depths: [4, 6, 64],
+            contempt: 0.0,
+            tune: Self::tune_pawnstormer
+        }
+    ];
+
+    pub fn find(name: &str) -> Option<&'static Personality> {
+        Self::ALL.iter().find(|personality| personality.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get_weights(&self) -> GenericWeights {
+        let mut weights = GenericWeights::new();
+        (self.tune)(&mut weights);
+        weights
+    }
+
+    // settings and weights as they would be read from a config file
+    pub fn get_config(&self) -> GenericConfig {
+        GenericConfig { settings: GenericSettings { seed: None, contempt: self.contempt }, weights: self.get_weights() }
+    }
+
+    pub fn create(&self) -> Generic {
+        let config = self.get_config();
+        Generic::with_settings(config.weights, config.settings, &self.depths)
+    }
+
+    fn tune_attacker(weights: &mut GenericWeights) {
+        weights.king_threat_multiplier = weights.king_threat_multiplier * 2.0;
+        weights.king_safety_k = weights.king_safety_k * 2.0;
+        weights.king_attack_units.values_mut().for_each(|units| *units *= 1.5);
+        weights.mobility_k.values_mut().for_each(|k| *k = *k * 1.5);
+        // minor piece for two pawns near the king is fine
+        for c in ['n', 'b'] {
+            weights.material_cost.get_mut(&c).unwrap().mg -= 0.3;
+        }
+        weights.battery_vertical_cost = weights.battery_vertical_cost * 1.5;
+        weights.battery_diagonal_cost = weights.battery_diagonal_cost * 1.5;
+    }
+
+    fn tune_positional(weights: &mut GenericWeights) {
+        for penalty in &mut weights.pawn_islands_penalty {
+            *penalty = *penalty * 1.5;
+        }
+        weights.pawn_stacked_penalty = weights.pawn_stacked_penalty * 1.5;
+        weights.pawn_isolated_penalty = weights.pawn_isolated_penalty * 1.5;
+        weights.pawn_backward_penalty = weights.pawn_backward_penalty * 1.5;
+        weights.pawn_connected_multiplier = weights.pawn_connected_multiplier * 1.5;
+        weights.outpost_multiplier = weights.outpost_multiplier * 1.5;
+        weights.defend_cheaper_piece_multiplier = weights.defend_cheaper_piece_multiplier * 2.0;
+        for bonus in &mut weights.king_pawn_shield {
+            *bonus = *bonus * 1.5;
+        }
+        weights.king_open_file = weights.king_open_file * 1.5;
+        weights.king_threat_multiplier = weights.king_threat_multiplier * 0.5;
+    }
+
+    fn tune_materialist(weights: &mut GenericWeights) {
+        // the same material is worth more compared to everything else
+        for (c, cost) in weights.material_cost.iter_mut() {
+            if *c != 'k' {
+                *cost = *cost * 1.25;
+            }
+        }
+        weights.mobility_k.values_mut().for_each(|k| *k = *k * 0.5);
+        weights.king_threat_multiplier = weights.king_threat_multiplier * 0.5;
+        weights.outpost_multiplier = weights.outpost_multiplier * 0.5;
+    }
+
+    fn tune_pawnstormer(weights: &mut GenericWeights) {
+        // opponent storm is own attack, so it goes up both ways
+        for penalty in &mut weights.king_pawn_storm {
+            *penalty = *penalty * 2.5;
+        }
+        weights.pawn_advanced_multiplier = weights.pawn_advanced_multiplier * 2.0;
+        weights.pawn_connected_multiplier = weights.pawn_connected_multiplier * 1.5;
+        weights.pawn_candidate_multiplier = weights.pawn_candidate_multiplier * 2.0;
+        weights.king_open_file = weights.king_open_file * 1.5;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, engine::character::Evaluator, characters::generic::GenericConfig};
+    use super::Personality;
+
+    #[test]
+    fn test_personalities_find_01() {
+        assert_eq!(Personality::find("Attacker").unwrap().name, "attacker");
+        assert!(Personality::find(Personality::DEFAULT).is_some());
+        assert!(Personality::find("coward").is_none());
+    }
+
+    #[test]
+    fn test_personalities_weights_01() {
+        // weights are valid and survive config round-trip, every personality is distinct
+        for (i, personality) in Personality::ALL.iter().enumerate() {
+            let weights = personality.get_weights();
+            assert!(weights.validate().is_ok());
+            let config = personality.get_config();
+            assert!(GenericConfig::from_toml(&config.to_toml()).unwrap() == config);
+            for other in &Personality::ALL[..i] {
+                assert!(other.get_weights() != weights);
+            }
+        }
+    }
+
+    #[test]
+    fn test_personalities_eval_01() {
+        let eval = |name: &str, fen: &str| Personality::find(name).unwrap().create().get_static_eval(&Board::parse_fen(fen));
+        // extra pawn means more for the materialist
+        let fen = "r3k3/pp6/8/8/8/8/PPP5/R3K3 w - - 0 1";
+        assert!(eval("materialist", fen) > eval("generic", fen));
+        // attack on the king means more for the attacker
+        let fen = "r5k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1";
+        assert!(eval("attacker", fen) > eval("generic", fen));
+    }
+
+    #[test]
+    fn test_personalities_symmetry_01() {
+        // evaluations are white-based: the same position with colors swapped is evaluated opposite, mirrored left to right it is the same
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 1 5",
+            "r4rk1/pp3ppp/2n1b3/q2pP3/3P4/P1PB1N2/5PPP/R2QK2R w KQ - 2 14",
+            "2kr3r/ppp2pp1/2n4p/4p3/1PB1P1b1/P1N2N2/5PPP/R4RK1 b - - 0 15",
+            "r5k1/5ppp/8/6NQ/8/8/5PPP/6K1 w - - 0 1",
+            "8/5pk1/6p1/p1p4p/P1P4P/1P4P1/5PK1/8 w - - 0 40",
+            "8/3k4/2p5/1pP5/1P1K4/8/8/8 b - - 0 50",
+            "6k1/8/4K3/4P3/8/8/8/8 w - - 0 60",
+            "4k3/8/8/3r4/8/8/2B1N3/4K3 w - - 0 1",
+            "1k6/1pp4p/p7/3b4/8/5NP1/PP3PKP/8 w - - 0 30"
+        ];
+        for personality in &Personality::ALL {
+            let char = personality.create();
+            for fen in fens {
+                let b = Board::parse_fen(fen);
+                let eval = char.get_static_eval(&b);
+                let mirrored = char.get_static_eval(&b.mirror());
+                assert!((eval + mirrored).abs() < 0.001, "{} {}: {} vs {}", personality.name, fen, eval, mirrored);
+                if let Some(flipped) = b.flip() {
+                    let flipped = char.get_static_eval(&flipped);
+                    assert!((eval - flipped).abs() < 0.001, "{} {} flipped: {} vs {}", personality.name, fen, eval, flipped);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use crate::board::{bimaps::Bimaps, board::Board};
+
+// Piece-square tables: [middlegame, endgame] value of every piece on every square from white's perspective,
+// so black values are the white ones mirrored vertically and negated.
+// The evaluation keeps the sums of them up to date move by move, see update().
+pub struct Pst {
+    // [piece][y * 8 + x]
+    values: [[[f32; 2]; 64]; 14]
+}
+
+impl Pst {
+    // tables of white pieces (pknbrq) as they are seen on the diagram: a8 goes first, h1 goes last
+    pub fn new(tables: &[(char, &[f32], &[f32])]) -> Self {
+        let bimaps = Bimaps::init();
+        let mut values = [[[0.0; 2]; 64]; 14];
+        for (c, mg, eg) in tables {
+            let white = *bimaps.pieces.get_by_left(&c.to_ascii_uppercase()).unwrap() as usize;
+            let black = *bimaps.pieces.get_by_left(&c.to_ascii_lowercase()).unwrap() as usize;
+            for i in 0..64 {
+                let (y, x) = (7 - i / 8, i % 8);
+                values[white][y * 8 + x] = [mg[i], eg[i]];
+                values[black][(7 - y) * 8 + x] = [-mg[i], -eg[i]];
+            }
+        }
+        Self { values }
+    }
+
+    // empty squares (including the ones with a color bit left) are worth nothing
+    pub fn get(&self, piece: u8, y: usize, x: usize) -> [f32; 2] {
+        if piece < 2 {
+            return [0.0; 2];
+        }
+        self.values[piece as usize][y * 8 + x]
+    }
+
+    // full recalculation by colors (0 - black, 1 - white)
+    pub fn score(&self, board: &Board) -> [[f32; 2]; 2] {
+        let mut score = [[0.0; 2]; 2];
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                let value = self.get(piece, y, x);
+                score[(piece & 1) as usize][0] += value[0];
+                score[(piece & 1) as usize][1] += value[1];
+            }
+        }
+        score
+    }
+
+    // update the sums after the move, squares are the touched ones with the pieces before the move
+    // (see Board::get_touched_squares)
+    pub fn update(&self, score: &mut [[f32; 2]; 2], board: &Board, squares: &[(usize, usize, u8)]) {
+        for &(y, x, old) in squares {
+            let new = board.field[y][x];
+            if new != old {
+                for (piece, k) in [(old, -1.0), (new, 1.0)] {
+                    let value = self.get(piece, y, x);
+                    score[(piece & 1) as usize][0] += k * value[0];
+                    score[(piece & 1) as usize][1] += k * value[1];
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, utils::utils::move_to_board};
+    use super::Pst;
+
+    #[test]
+    fn test_pst_update_01() {
+        // every square has its own value, so any missed update would show up
+        let values: Vec<Vec<f32>> = (0..6).map(|k| (0..64).map(|i| (k * 64 + i) as f32).collect()).collect();
+        let tables: Vec<(char, &[f32], &[f32])> = "pknbrq".chars().zip(&values).map(|(c, table)| (c, &table[..], &table[..])).collect();
+        let pst = Pst::new(&tables);
+        let mut b = Board::parse_fen("r3k2r/pPp1pppp/8/3pP3/8/8/PPPP1PPP/R3K2R w KQkq d6 0 1");
+        let mut score = pst.score(&b);
+        // en passant, promotion with capture, both castlings
+        for mov in ["e5d6", "e8c8", "b7a8q", "h7h6", "e1g1"] {
+            let mov = move_to_board(&b, mov);
+            let (squares, len) = b.get_touched_squares(&mov);
+            b.make_move(&mov);
+            pst.update(&mut score, &b, &squares[..len]);
+            assert!(score == pst.score(&b));
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::{fs, io};
+
+use crate::{engine::character::Evaluator, board::board::Board};
+use super::generic::{GenericEval, GenericWeights};
+
+// Texel tuning: weights are fitted so that sigmoid(static eval) predicts game results of the dataset positions.
+// Positions are expected to be quiet (no captures or checks pending), since static eval is used without search.
+pub struct Tuner {
+    // positions with results from white's perspective: 1.0 - win, 0.5 - draw, 0.0 - loss
+    positions: Vec<(Board, f32)>,
+    // sigmoid scaling, fitted to the dataset with initial weights
+    pub k: f32,
+    // prefixes of weight names that are not tuned (see GenericWeights::get_params), FIXED by default
+    pub fixed: Vec<String>
+}
+
+// progress of the tuning after a pass over all weights
+pub struct TuningPass {
+    pub pass: usize,
+    pub error: f32,
+    pub step: f32
+}
+
+impl Tuner {
+    // weights that are never tuned: king cost is not a part of the eval, pawn cost is the unit of the scale,
+    // mobility thresholds are cell counts
+    pub const FIXED: [&'static str; 4] = ["material_cost.k", "material_cost.p", "mobility_min_threshold", "mobility_max_threshold"];
+    // mate-like static evals (e.g. known endgame wins) would only add noise
+    const MAX_SCORE: f32 = 20.0;
+
+    pub fn new(positions: Vec<(Board, f32)>) -> Self {
+        Self { positions, k: 1.0, fixed: Self::FIXED.iter().map(|name| name.to_string()).collect() }
+    }
+
+    // Read dataset: one position per line, FEN followed by the game result in any of the common forms:
+    // 1-0 / 0-1 / 1/2-1/2 (optionally quoted, EPD "c9" opcode is ok) or [1.0] / [0.5] / [0.0].
+    // Empty lines and lines starting with # are skipped.
+    pub fn load(path: &str) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut positions = Vec::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match Self::parse_line(line) {
+                Some(position) => positions.push(position),
+                None => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: cannot find game result", path, i + 1)))
+            }
+        }
+        Ok(Self::new(positions))
+    }
+
+    pub fn parse_line(line: &str) -> Option<(Board, f32)> {
+        let (index, result) = [("1/2-1/2", 0.5), ("1-0", 1.0), ("0-1", 0.0), ("[1.0]", 1.0), ("[0.5]", 0.5), ("[0.0]", 0.0)]
+            .iter()
+            .find_map(|(token, result)| line.rfind(token).map(|index| (index, *result)))?;
+        let fen = line[..index].trim_end_matches(|c: char| c.is_whitespace() || c == '"' || c == ';' || c == ',');
+        let fen = fen.strip_suffix(" c9").unwrap_or(fen).trim();
+        if fen.split_whitespace().count() < 2 {
+            return None;
+        }
+        Some((Board::parse_fen(fen), result))
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    // expected score of white for given eval (in pawns)
+    pub fn sigmoid(k: f32, score: f32) -> f32 {
+        1.0 / (1.0 + 10f32.powf(-k * score / 4.0))
+    }
+
+    // mean squared difference between expected and real results
+    pub fn error(&self, weights: &GenericWeights) -> f32 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let char = GenericEval::new(weights.clone());
+        let sum: f64 = self.positions.iter().map(|(board, result)| {
+            let diff = result - Self::sigmoid(self.k, char.get_static_eval(board).clamp(-Self::MAX_SCORE, Self::MAX_SCORE));
+            (diff * diff) as f64
+        }).sum();
+        (sum / self.positions.len() as f64) as f32
+    }
+
+    // ternary search of k with the lowest error
+    pub fn fit_k(&mut self, weights: &GenericWeights) -> f32 {
+        let (mut low, mut high) = (0.01, 10.0);
+        while high - low > 0.001 {
+            let (a, b) = (low + (high - low) / 3.0, high - (high - low) / 3.0);
+            self.k = a;
+            let error_a = self.error(weights);
+            self.k = b;
+            if error_a < self.error(weights) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        self.k = (low + high) / 2.0;
+        self.k
+    }
+
+    // Local search: every tunable weight is moved by +-step while the error goes down,
+    // step is halved when no weight can be improved. Stops after max_passes or when step is too small.
+    // progress is called after every pass.
+    pub fn tune(&self, weights: &GenericWeights, max_passes: usize, mut progress: impl FnMut(&TuningPass)) -> GenericWeights {
+        let names = self.get_tunable(weights);
+        let mut params = weights.get_params();
+        let mut best = self.error(weights);
+        let mut rate = 0.1;
+        for pass in 0..max_passes {
+            let mut improved = false;
+            for name in &names {
+                let value = params[name];
+                let step = (value.abs() * rate).max(rate * 0.1);
+                for candidate in [value + step, value - step] {
+                    params.insert(name.clone(), candidate);
+                    if let Ok(tuned) = weights.with_params(&params) {
+                        let error = self.error(&tuned);
+                        if error < best {
+                            best = error;
+                            improved = true;
+                            break;
+                        }
+                    }
+                    params.insert(name.clone(), value);
+                }
+            }
+            progress(&TuningPass { pass: pass + 1, error: best, step: rate });
+            if !improved {
+                rate /= 2.0;
+                if rate < 0.001 {
+                    break;
+                }
+            }
+        }
+        weights.with_params(&params).unwrap()
+    }
+
+    fn get_tunable(&self, weights: &GenericWeights) -> Vec<String> {
+        weights.get_params().into_keys().filter(|name| !self.fixed.iter().any(|fixed| name.starts_with(fixed))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::characters::generic::GenericWeights;
+    use super::Tuner;
+
+    #[test]
+    fn test_tuning_parse_01() {
+        let (board, result) = Tuner::parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1.0]").unwrap();
+        assert_eq!(result, 1.0);
+        assert!(board.white_to_move);
+        let (board, result) = Tuner::parse_line("4k3/8/8/8/8/8/4P3/4K3 b - - c9 \"1/2-1/2\";").unwrap();
+        assert_eq!(result, 0.5);
+        assert!(!board.white_to_move);
+        assert_eq!(Tuner::parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1; 0-1").unwrap().1, 0.0);
+        assert!(Tuner::parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").is_none());
+        assert!(Tuner::parse_line("1-0").is_none());
+    }
+
+    #[test]
+    fn test_tuning_sigmoid_01() {
+        assert_eq!(Tuner::sigmoid(1.0, 0.0), 0.5);
+        assert!(Tuner::sigmoid(1.0, 4.0) > 0.9);
+        assert!(Tuner::sigmoid(1.0, -4.0) < 0.1);
+    }
+
+    #[test]
+    fn test_tuning_tune_01() {
+        // far advanced passed pawn always wins here, so the tuner should value it more than default weights do
+        let lines = [
+            "r7/8/k3P3/8/8/8/8/R3K3 w - - 0 1 [1.0]",
+            "r3k3/8/8/8/8/4p3/8/K6R w - - 0 1 [0.0]",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 [0.5]",
+            "4k3/p7/8/8/8/8/P7/4K3 w - - 0 1 [0.5]"
+        ];
+        let mut tuner = Tuner::new(lines.iter().map(|line| Tuner::parse_line(line).unwrap()).collect());
+        assert_eq!(tuner.len(), 4);
+        // piece-square tables are way too many for a quick test
+        tuner.fixed.push("pst".to_string());
+        let weights = GenericWeights::new();
+        let mut errors = Vec::default();
+        let tuned = tuner.tune(&weights, 5, |pass| errors.push(pass.error));
+        assert_eq!(errors.len(), 5);
+        assert!(errors.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(tuner.error(&tuned) < tuner.error(&weights));
+        assert!(tuned.validate().is_ok());
+        assert_eq!(tuned.material_cost[&'p'], weights.material_cost[&'p']);
+        assert!(tuned.pawn_passed_multiplier.eg > weights.pawn_passed_multiplier.eg);
+    }
+}
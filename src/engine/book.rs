@@ -0,0 +1,354 @@
+use std::{cmp::max, collections::HashMap, fs, io};
+use rand::{rngs::StdRng, SeedableRng, Rng};
+use crate::{board::{board::Board, mov::Mov, coord::Coord}, utils::utils::san_to_board};
+use super::polyglot::polyglot_hash;
+
+// how to choose a move if there are several book moves for the position
+#[derive(Clone, Copy, PartialEq)]
+pub enum BookSelection {
+    // always the move with the highest weight
+    Best,
+    // random move, probability is proportional to its weight
+    Weighted,
+    // random move, weights are ignored
+    Random
+}
+
+// Polyglot book entry, stored as 16 big-endian bytes on disk:
+// key (8 bytes), move (2 bytes), weight (2 bytes), learn (4 bytes)
+#[derive(Clone, Copy, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    // bits 0-2: to file, 3-5: to row, 6-8: from file, 9-11: from row, 12-14: promotion (none, n, b, r, q)
+    // castling is stored as "king captures own rook", e.g. e1h1
+    pub mov: u16,
+    pub weight: u16,
+    pub learn: u32
+}
+
+pub struct Book {
+    // sorted by key, so entries of the same position are adjacent
+    entries: Vec<BookEntry>,
+    pub selection: BookSelection,
+    // book won't be consulted since this half-move (Board::hno)
+    pub max_ply: u16,
+    rng: StdRng
+}
+
+impl Book {
+    pub const ENTRY_SIZE: usize = 16;
+    pub const DEFAULT_MAX_PLY: u16 = 24;
+
+    pub fn new(mut entries: Vec<BookEntry>, selection: BookSelection, max_ply: u16) -> Self {
+        // stable sort keeps the order of moves inside the position
+        entries.sort_by_key(|entry| entry.key);
+        Self { entries, selection, max_ply, rng: StdRng::from_entropy() }
+    }
+
+    // random choice of the book moves is reproducible from here on
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // read polyglot .bin file
+    pub fn open(path: &str, selection: BookSelection, max_ply: u16) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() % Self::ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not a polyglot book: size is not divisible by {}", path, Self::ENTRY_SIZE)));
+        }
+        let entries = bytes.chunks_exact(Self::ENTRY_SIZE).map(|chunk| BookEntry {
+            key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+            mov: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(chunk[12..16].try_into().unwrap())
+        }).collect();
+        Ok(Self::new(entries, selection, max_ply))
+    }
+
+    // write polyglot .bin file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(self.entries.len() * Self::ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.mov.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    // Build a book out of PGN games text: every move of the first max_ply half-moves is stored,
+    // weight is 2 per won game and 1 per drawn game for the side that made the move (lost games add nothing).
+    // Variations, comments and NAGs are ignored; games with an illegal or unparseable move are cut at that move.
+    pub fn from_pgn(pgn: &str, selection: BookSelection, max_ply: u16) -> Self {
+        let mut weights: HashMap<(u64, u16), u32> = HashMap::new();
+        for (sans, result) in Self::parse_pgn(pgn) {
+            let mut board = Board::new();
+            for san in sans.iter().take(max_ply as usize) {
+                let mov = match san_to_board(&mut board, san) {
+                    Some(mov) => mov,
+                    None => break
+                };
+                // result is 2 for white win, 1 for a draw, 0 for black win
+                let score = if board.white_to_move { result } else { result.map(|r| 2 - r) };
+                let weight = weights.entry((polyglot_hash(&board), Self::encode_move(&board, &mov))).or_insert(0);
+                *weight += score.unwrap_or(0) as u32;
+                board.make_move(&mov);
+            }
+        }
+        // polyglot weights are 16-bit, so scale them down if necessary
+        let max_weight = weights.values().copied().max().unwrap_or(0);
+        let scale = if max_weight > u16::MAX as u32 { u16::MAX as f64 / max_weight as f64 } else { 1.0 };
+        let mut entries: Vec<BookEntry> = weights.iter()
+            .filter(|(_, weight)| **weight > 0)
+            .map(|((key, mov), weight)| BookEntry { key: *key, mov: *mov, weight: (*weight as f64 * scale).max(1.0) as u16, learn: 0 })
+            .collect();
+        // best moves first inside the same position, then sort by key (stable)
+        entries.sort_by(|a, b| b.weight.cmp(&a.weight).then(a.mov.cmp(&b.mov)));
+        Self::new(entries, selection, max_ply)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // all stored entries of the given position
+    pub fn get_entries(&self, board: &Board) -> &[BookEntry] {
+        let key = polyglot_hash(board);
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = self.entries.partition_point(|entry| entry.key <= key);
+        &self.entries[start..end]
+    }
+
+    // all legal book moves of the given position with their weights
+    pub fn get_moves(&self, board: &mut Board) -> Vec<(Mov, u16)> {
+        let entries = self.get_entries(board).to_vec();
+        if entries.is_empty() {
+            return Vec::default();
+        }
+        let legals = board.get_legal_moves(None, Some(true));
+        entries.iter().filter_map(|entry| {
+            Self::decode_move(board, &legals, entry.mov).map(|mov| (mov, entry.weight))
+        }).collect()
+    }
+
+    // choose a move to play in according to book settings, if any
+    pub fn get_move(&mut self, board: &mut Board) -> Option<Mov> {
+        if board.hno >= self.max_ply {
+            return None;
+        }
+        let moves = self.get_moves(board);
+        if moves.is_empty() {
+            return None;
+        }
+        let total: u32 = moves.iter().map(|(_, weight)| *weight as u32).sum();
+        let index = match self.selection {
+            BookSelection::Best => {
+                // first of the best ones
+                let best = moves.iter().map(|(_, weight)| *weight).max().unwrap();
+                moves.iter().position(|(_, weight)| *weight == best).unwrap()
+            },
+            BookSelection::Weighted if total > 0 => {
+                let mut target = self.rng.gen_range(0..total);
+                let mut index = 0;
+                while target >= moves[index].1 as u32 {
+                    target -= moves[index].1 as u32;
+                    index += 1;
+                }
+                index
+            },
+            _ => self.rng.gen_range(0..moves.len())
+        };
+        Some(moves[index].0)
+    }
+
+    // convert Board move to polyglot move (call before making this move)
+    pub fn encode_move(board: &Board, mov: &Mov) -> u16 {
+        let mut to_x = mov.to.x() as u16;
+        let piece = board.field[mov.from.y() as usize][mov.from.x() as usize];
+        if piece & 254 == board.gpl(&'k') && (mov.from.x() as i8 - mov.to.x() as i8).abs() > 1 {
+            to_x = if mov.to.x() > mov.from.x() { 7 } else { 0 };
+        }
+        let mut raw = to_x | (mov.to.y() as u16) << 3 | (mov.from.x() as u16) << 6 | (mov.from.y() as u16) << 9;
+        if board.is_promotion(mov) {
+            let promotion = match board.rtpc(mov.data) {
+                'n' => 1,
+                'b' => 2,
+                'r' => 3,
+                _ => 4
+            };
+            raw |= promotion << 12;
+        }
+        raw
+    }
+
+    // find the legal move that is encoded by polyglot move
+    pub fn decode_move(board: &Board, legals: &[Mov], raw: u16) -> Option<Mov> {
+        let from = Coord::new((raw >> 9 & 7) as u8, (raw >> 6 & 7) as u8);
+        let mut to = Coord::new((raw >> 3 & 7) as u8, (raw & 7) as u8);
+        let promotion = match raw >> 12 & 7 {
+            1 => Some('n'),
+            2 => Some('b'),
+            3 => Some('r'),
+            4 => Some('q'),
+            _ => None
+        };
+        // "king captures own rook" castling notation
+        let piece = board.field[from.y() as usize][from.x() as usize];
+        if piece & 254 == board.gpl(&'k') && from.x() == 4 && from.y() == to.y() && (to.x() == 0 || to.x() == 7) {
+            to.set(to.y(), if to.x() == 7 { 6 } else { 2 });
+        }
+        legals.iter().find(|mov| {
+            mov.from == from && mov.to == to && match promotion {
+                Some(c) => board.is_promotion(mov) && board.rtpc(mov.data) == c,
+                None => !board.is_promotion(mov)
+            }
+        }).copied()
+    }
+
+    // split PGN text into games: SAN moves and result (2 - white won, 1 - draw, 0 - black won, None - unknown)
+    fn parse_pgn(pgn: &str) -> Vec<(Vec<String>, Option<u8>)> {
+        let mut games = Vec::default();
+        let mut sans: Vec<String> = Vec::default();
+        let mut text = String::new();
+        // strip tags, comments and variations
+        let mut comment = false;
+        let mut line_comment = false;
+        let mut tag = false;
+        let mut variation = 0;
+        for c in pgn.chars() {
+            if line_comment {
+                line_comment = c != '\n';
+            } else if comment {
+                comment = c != '}';
+            } else if tag {
+                tag = c != ']';
+            } else if c == '(' {
+                variation += 1;
+            } else if c == ')' {
+                variation = max(variation, 1) - 1;
+            } else if variation > 0 {
+                continue;
+            } else if c == '{' {
+                comment = true;
+            } else if c == ';' {
+                line_comment = true;
+            } else if c == '[' {
+                tag = true;
+                text.push(' ');
+            } else {
+                text.push(c);
+            }
+        }
+        for token in text.split_ascii_whitespace() {
+            let result = match token {
+                "1-0" => Some(Some(2)),
+                "1/2-1/2" => Some(Some(1)),
+                "0-1" => Some(Some(0)),
+                "*" => Some(None),
+                _ => None
+            };
+            if let Some(result) = result {
+                games.push((sans, result));
+                sans = Vec::default();
+                continue;
+            }
+            if token.starts_with('$') {
+                continue;
+            }
+            // move numbers may stick to the move itself: "1.e4", "12...Nf6"
+            let san = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            if !san.is_empty() {
+                sans.push(san.to_string());
+            }
+        }
+        if !sans.is_empty() {
+            games.push((sans, None));
+        }
+        games
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::utils::move_to_user;
+
+    const PGN: &str = "[Event \"Test\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 {main line} Nc6 (2... d6 3. d4) 3. Bb5 a6 1-0\n\n\
+                       [Event \"Test\"]\n[Result \"1/2-1/2\"]\n\n1.e4 c5 2.Nf3 $1 d6 3.d4 cxd4 1/2-1/2\n\n\
+                       [Event \"Test\"]\n[Result \"0-1\"]\n\n1. d4 d5 2. c4 e6 0-1\n";
+
+    #[test]
+    fn test_book_from_pgn_01() {
+        let mut book = Book::from_pgn(PGN, BookSelection::Best, Book::DEFAULT_MAX_PLY);
+        let mut b = Board::new();
+        // e4 is 2 + 1, d4 is 0 (lost game)
+        let moves = book.get_moves(&mut b);
+        assert_eq!(moves.len(), 1);
+        assert_eq!(move_to_user(&b, &moves[0].0), "e2e4");
+        assert_eq!(moves[0].1, 3);
+        let mov = book.get_move(&mut b).unwrap();
+        b.make_move(&mov);
+        // e5 lost, c5 drew
+        let mov = book.get_move(&mut b).unwrap();
+        assert_eq!(move_to_user(&b, &mov), "c7c5");
+        // variations are skipped
+        b.make_move(&mov);
+        let mov = book.get_move(&mut b).unwrap();
+        b.make_move(&mov);
+        assert_eq!(book.get_moves(&mut b).len(), 1);
+    }
+
+    #[test]
+    fn test_book_from_pgn_02() {
+        let mut book = Book::from_pgn(PGN, BookSelection::Weighted, 2);
+        let mut b = Board::new();
+        for _ in 0..2 {
+            let mov = book.get_move(&mut b).unwrap();
+            b.make_move(&mov);
+        }
+        // depth limit
+        assert!(book.get_move(&mut b).is_none());
+        assert!(book.get_entries(&b).is_empty());
+    }
+
+    #[test]
+    fn test_book_castling_01() {
+        let b = Board::parse_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1");
+        let legals = b.get_legal_moves(None, Some(true));
+        let mov = Book::decode_move(&b, &legals, 7 | 4 << 6).unwrap();
+        assert_eq!(move_to_user(&b, &mov), "e1g1");
+        assert_eq!(Book::encode_move(&b, &mov), 7 | 4 << 6);
+        let mov = Book::decode_move(&b, &legals, 4 << 6).unwrap();
+        assert_eq!(move_to_user(&b, &mov), "e1c1");
+        assert_eq!(Book::encode_move(&b, &mov), 4 << 6);
+    }
+
+    #[test]
+    fn test_book_promotion_01() {
+        let b = Board::parse_fen("8/1P4k1/8/8/8/8/6K1/8 w - - 0 1");
+        let legals = b.get_legal_moves(None, Some(true));
+        // b7b8n
+        let raw = 1 | 7 << 3 | 1 << 6 | 6 << 9 | 1 << 12;
+        let mov = Book::decode_move(&b, &legals, raw).unwrap();
+        assert_eq!(move_to_user(&b, &mov), "b7b8n");
+        assert_eq!(Book::encode_move(&b, &mov), raw);
+    }
+
+    #[test]
+    fn test_book_save_open_01() {
+        let book = Book::from_pgn(PGN, BookSelection::Best, Book::DEFAULT_MAX_PLY);
+        let path = std::env::temp_dir().join("cce_test_book_save_open_01.bin");
+        let path = path.to_str().unwrap();
+        book.save(path).unwrap();
+        let opened = Book::open(path, BookSelection::Best, Book::DEFAULT_MAX_PLY).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(opened.len(), book.len());
+        assert!(opened.entries == book.entries);
+    }
+}
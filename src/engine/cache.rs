@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use crate::board::board::Board;
+use super::eval::{Eval, EvalHashed};
+
+// Positions known to minimax: the ones on the current search line and the ones played in the game (repetitions),
+// and the evaluated ones (transpositions). Characters only tell it about the moves played in the game.
+pub trait PositionCache {
+    //
+    // Calls from minimax:
+
+    // Hash given position on board
+    fn make_hash(&self, board: &Board) -> u64;
+    // Store evaluated position
+    fn cache_evaluated(&mut self, hash: u64, eval: Eval, depth: i8);
+    // Add 1 playcount to the position; add as unevaluated, if missing
+    fn cache_play(&mut self, hash: u64);
+    // Remove 1 playcount from the position; if non-existant, will panic!
+    fn cache_unplay(&mut self, hash: u64);
+    // Return true if the position was played enough times - 1 to call it a draw (1 or 2)
+    fn is_played(&self, hash: u64) -> bool;
+    // Return true if the position is evaluated
+    fn is_evaluated(&self, hash: u64) -> bool;
+    // Get evaluation from given hashed position (will panic if no such position)
+    fn get_hashed_eval(&self, hash: u64) -> Eval;
+    // Get depth value on which given position was evaluated (will panic if no such position)
+    fn get_hashed_depth(&self, hash: u64) -> i8;
+
+    //
+    // Calls from character:
+
+    // Store the starting position of the game, if nothing is played yet
+    fn start(&mut self, board: &Board);
+    // Store the position played in game; send board with the move that is already made
+    fn accept_move(&mut self, board: &Board);
+    // Remove the last played position; will panic! on the starting one
+    fn takeback(&mut self);
+    // Drop all evaluations, e.g. when the evaluation or search depths have changed
+    fn clear(&mut self);
+}
+
+// Default cache: by Zobrist hashes kept by the board, evaluations are kept until an irreversible move is played
+pub struct HashCache {
+    // stored evaluated board positions (temporary cache)
+    hashes_temp: HashMap<u64, EvalHashed>,
+    // already played positions on board (permanent cache)
+    hashes_perm: HashMap<u64, u8>,
+    hashes_perm_history: Vec<u64>
+}
+
+impl HashCache {
+    pub fn new() -> Self {
+        Self {
+            hashes_temp: HashMap::new(),
+            hashes_perm: HashMap::new(),
+            hashes_perm_history: Vec::default()
+        }
+    }
+}
+
+impl Default for HashCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PositionCache for HashCache {
+    fn make_hash(&self, board: &Board) -> u64 {
+        board.hash
+    }
+
+    fn cache_evaluated(&mut self, hash: u64, eval: Eval, depth: i8) {
+        if let Some(f) = self.hashes_temp.get_mut(&hash) {
+            // if is marked as played and thus already exists
+            f.eval = eval;
+            f.depth = depth;
+        } else {
+            // if is not exists and thus not marked as played
+            self.hashes_temp.insert(hash, EvalHashed::evaluated(eval, depth, 0));
+        }
+    }
+
+    fn cache_play(&mut self, hash: u64) {
+        if let Some(f) = self.hashes_temp.get_mut(&hash) {
+            f.playcount += 1;
+        } else {
+            self.hashes_temp.insert(hash, EvalHashed::new());
+        }
+    }
+
+    fn cache_unplay(&mut self, hash: u64) {
+        if let Some(f) = self.hashes_temp.get_mut(&hash) {
+            f.playcount -= 1;
+        } else {
+            // possibly bad hash_clear() call throught the minimax search iteration
+            panic!("Attempt to 'unplay' non-existent position");
+        }
+    }
+
+    fn is_played(&self, hash: u64) -> bool {
+        if let Some(f) = self.hashes_temp.get(&hash) {
+            if f.playcount > 0 {
+                return true;
+            }
+        }
+        if let Some(f) = self.hashes_perm.get(&hash) {
+            return *f > 1;
+        }
+        false
+    }
+
+    fn is_evaluated(&self, hash: u64) -> bool {
+        if let Some(f) = self.hashes_temp.get(&hash) {
+            f.eval != Eval::unevaluated()
+        } else {
+            false
+        }
+    }
+
+    fn get_hashed_eval(&self, hash: u64) -> Eval {
+        self.hashes_temp.get(&hash).unwrap().eval
+    }
+
+    fn get_hashed_depth(&self, hash: u64) -> i8 {
+        self.hashes_temp.get(&hash).unwrap().depth
+    }
+
+    fn start(&mut self, board: &Board) {
+        if self.hashes_perm_history.is_empty() {
+            let hash = self.make_hash(board);
+            self.hashes_perm_history.push(hash);
+            self.hashes_perm.insert(hash, 1);
+        }
+    }
+
+    fn accept_move(&mut self, board: &Board) {
+        let mov = board.history.last().unwrap().mov;
+        // deal with permanent cache
+        let hash = self.make_hash(board);
+        self.hashes_perm_history.push(hash);
+        if let Some(f) = self.hashes_perm.get_mut(&hash) {
+            *f += 1;
+        } else {
+            self.hashes_perm.insert(hash, 1);
+        }
+        // deal with temporary cache
+        // TODO: mate_in will go crazy, find a way around to implement hashing in between moves
+        if mov.is_repeatable(board) {
+            self.hashes_temp.remove(&hash);
+        } else {
+            self.hashes_temp.clear();
+        }
+    }
+
+    fn takeback(&mut self) {
+        // < 2 is because starting position is also stored in history
+        if self.hashes_perm_history.len() < 2 {
+            panic!("Attempt to make takeback from starting position");
+        }
+        let mut mark_for_delete = false;
+        if let Some(f) = self.hashes_perm.get_mut(self.hashes_perm_history.last().unwrap()) {
+            if *f == 1 {
+                mark_for_delete = true;
+            } else {
+                *f -= 1;
+            }
+        }
+        if mark_for_delete {
+            self.hashes_perm.remove(self.hashes_perm_history.last().unwrap());
+        }
+        self.hashes_perm_history.pop();
+        self.hashes_temp.clear();
+    }
+
+    fn clear(&mut self) {
+        self.hashes_temp.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, engine::eval::Eval, utils::utils::move_to_board};
+    use super::{HashCache, PositionCache};
+
+    #[test]
+    fn test_cache_play_01() {
+        // position on the search line counts as a repetition, evaluation is kept after it's left
+        let mut cache = HashCache::new();
+        let board = Board::parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let hash = cache.make_hash(&board);
+        assert!(!cache.is_played(hash) && !cache.is_evaluated(hash));
+        cache.cache_play(hash);
+        assert!(cache.is_played(hash));
+        cache.cache_unplay(hash);
+        cache.cache_evaluated(hash, Eval::new(5.0, 0), 2);
+        assert!(!cache.is_played(hash) && cache.is_evaluated(hash));
+        assert!(cache.get_hashed_eval(hash) == Eval::new(5.0, 0));
+        assert_eq!(cache.get_hashed_depth(hash), 2);
+        cache.clear();
+        assert!(!cache.is_evaluated(hash));
+    }
+
+    #[test]
+    fn test_cache_game_01() {
+        // position played twice in game is a draw for the search
+        let mut cache = HashCache::new();
+        let mut board = Board::parse_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let hash = cache.make_hash(&board);
+        cache.start(&board);
+        for mov in ["a1a2", "e8d8", "a2a1", "d8e8"] {
+            board.make_move(&move_to_board(&board, mov));
+            cache.accept_move(&board);
+        }
+        assert!(cache.is_played(hash));
+        board.revert_move();
+        cache.takeback();
+        assert!(!cache.is_played(hash));
+    }
+}
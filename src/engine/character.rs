@@ -1,57 +1,92 @@
-use crate::board::board::Board;
-use super::eval::{EvalMov, Eval};
+use crate::board::{board::Board, mov::Mov};
+use super::{eval::{EvalMov, Eval, EvalExplanation}, book::Book, syzygy::Tablebase, strength::Strength, store::AnalysisStore};
 
-pub trait Character {
+// A character is an evaluation (its style) and search limits, plus the engine-facing calls.
+// Searcher (see searcher.rs) makes one out of any Evaluator, so a new style only implements the evaluation.
+// Hashing and caching of positions is not its business: minimax takes a PositionCache (see cache.rs).
+pub trait Character: Evaluator + SearchConfig {
     //
     // Calls from engine:
 
-    // Return the move to make with its evaluation
+    // Return the move to make with its evaluation (not always the best one with limited strength)
     fn get_eval_move(&mut self, board: &mut Board) -> EvalMov;
     // Return the list of all evaluated moves
     fn get_eval_moves(&mut self, board: &mut Board) -> &Vec<EvalMov>;
-    // Set maximum static half-depth for minimax search
-    fn set_static_half_depth(&mut self, half_depth: i8);
-    // Set maximum half-static half-depth for minimax search (meaning: any answer to check is possible and will count as dynamic as well)
-    fn set_mixed_half_depth(&mut self, half_depth: i8);
-    // Set maximum dynamic half-depth for minimax search
-    fn set_dynamic_half_depth(&mut self, half_depth: i8);
     // Set move as played in game; send board with the move that is already made
     fn accept_move(&mut self, board: &Board);
-    // 
-    fn takeback(&mut self);
-
     //
-    // Calls from minimax:
+    fn takeback(&mut self);
+    // Set opening book to consult before any search (None to disable)
+    fn set_book(&mut self, book: Option<Book>);
+    // Set endgame tablebases to probe at the root and in the search tree (None to disable)
+    fn set_tablebase(&mut self, tablebase: Option<Tablebase>);
+    // Set persistent analysis to consult before the search and to save deep searches to (None to disable)
+    fn set_store(&mut self, store: Option<AnalysisStore>);
+    // Set playing strength below the full one (None for the full strength)
+    fn set_strength(&mut self, strength: Option<Strength>);
+    // Set seed of all random choices (book moves, strength noise and move choice, ties):
+    // the same seed, position (with the same game history) and limits give the same move
+    fn set_seed(&mut self, seed: u64);
+    fn get_seed(&self) -> u64;
+}
 
+// Evaluation of positions: the style of the character
+pub trait Evaluator {
     // Return the static evaluation of the position
     fn get_static_eval(&self, board: &Board) -> f32;
+    // Return the static evaluation broken down by terms, its total is exactly get_static_eval()
+    fn explain_eval(&self, board: &Board) -> EvalExplanation;
     // Return the static evaluation of a guaranteed win/lose position
-    fn get_static_eval_mate(&self, board: &Board) -> f32;
+    fn get_static_eval_mate(&self, board: &Board) -> f32 {
+        self.get_static_eval(board)
+    }
     // Return the static evaluation of a guaranteed draw position
-    fn get_static_eval_stalemate(&self, board: &Board) -> f32;
+    fn get_static_eval_stalemate(&self, _board: &Board) -> f32 {
+        0.0
+    }
+    // Make the move of the search line, the evaluation may keep its own state of the line up to date
+    fn make_move(&mut self, board: &mut Board, mov: &Mov) {
+        board.make_move(mov);
+    }
+    // Take back the last move of the search line
+    fn revert_move(&mut self, board: &mut Board) {
+        board.revert_move();
+    }
+    // Return the key of the evaluation settings (weights), analyses of other ones are not reused
+    fn get_eval_key(&self) -> u64 {
+        0
+    }
+}
+
+// Search limits and the rest of calls from minimax
+pub trait SearchConfig {
+    // Set maximum static half-depth for minimax search
+    fn set_static_half_depth(&mut self, half_depth: i8);
+    // Set maximum half-static half-depth for minimax search (meaning: any answer to check is possible and will count as dynamic as well)
+    fn set_mixed_half_depth(&mut self, half_depth: i8);
+    // Set maximum dynamic half-depth for minimax search
+    fn set_dynamic_half_depth(&mut self, half_depth: i8);
     // Return the maximum static half-depth param called on this minimax evaluation
     fn get_static_half_depth(&self) -> i8;
     // Return the maximum half-static half-depth param called on this minimax evaluation
     fn get_mixed_half_depth(&self) -> i8;
     // Return the maximum dynamic half-depth param called on this minimax evaluation
     fn get_dynamic_half_depth(&self) -> i8;
-
-    // Position hashing features
-
-    // Hash given position on board
-    fn make_hash(&self, board: &Board) -> u64;
-    // Store evaluated position
-    fn cache_evaluated(&mut self, hash: u64, eval: Eval, depth: i8);
-    // Add 1 playcount to the position; add as unevaluated, if missing
-    fn cache_play(&mut self, hash: u64);
-    // Remove 1 playcount from the position; if non-existant, will panic!
-    fn cache_unplay(&mut self, hash: u64);
-    // Return true if the position was played enough times - 1 to call it a draw (1 or 2)
-    fn is_played(&self, hash: u64) -> bool;
-    // Return true if the position is evaluated
-    fn is_evaluated(&self, hash: u64) -> bool;
-    // Get evaluation from given hashed position (will panic if no such position)
-    fn get_hashed_eval(&self, hash: u64) -> Eval;
-    // Get depth value on which given position was evaluated (will panic if no such position)
-    fn get_hashed_depth(&self, hash: u64) -> i8;
-}
\ No newline at end of file
+    // Return the exact evaluation of the position from tablebases, if it's there
+    fn get_tablebase_eval(&mut self, _board: &mut Board) -> Option<Eval> {
+        None
+    }
+    // Count the searched position, return false if the node limit is reached (the rest is evaluated statically)
+    fn count_node(&mut self) -> bool {
+        true
+    }
+    // Return the seed of the current search, root moves of equal order are shuffled with it to break ties
+    fn get_search_seed(&self) -> u64 {
+        0
+    }
+    // Return the score of a draw (repetition, fifty moves, dead position, stalemate) from white perspective:
+    // contempt of the side the search is made for, a draw is worse than equal for a character that avoids it
+    fn get_draw_score(&self) -> f32 {
+        0.0
+    }
+}
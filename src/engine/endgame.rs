@@ -0,0 +1,469 @@
+use std::{cmp::{max, min}, sync::OnceLock};
+use crate::board::board::Board;
+
+// Built-in knowledge of basic endgames, used when tablebases are not available.
+// Recognizers are keyed on material signature (like "KBNvK", the stronger side first) and either give the final
+// static score of the position or scale the regular evaluation of drawish material.
+
+pub enum EndgameEval {
+    // exact static score (from white perspective), no regular evaluation is needed
+    Score(f32),
+    // multiplier for the regular evaluation
+    Scale(f32)
+}
+
+// score of a won basic endgame, far above any material advantage
+pub const KNOWN_WIN: f32 = 100.0;
+
+// pieces order in Material arrays
+const KINDS: [char; 6] = ['p', 'n', 'b', 'r', 'q', 'k'];
+const P: usize = 0;
+const N: usize = 1;
+const B: usize = 2;
+const R: usize = 3;
+const Q: usize = 4;
+const K: usize = 5;
+const COSTS: [f32; 5] = [1., 3., 3., 5., 9.];
+
+// the most pieces of one kind a side may have: two knights, bishops or rooks and eight promoted pawns
+const MAX_PIECES: usize = 10;
+
+// squares (y * 8 + x) of all pieces by color (0 - black, 1 - white as in board color bit) and kind
+pub struct Material {
+    squares: [[[usize; MAX_PIECES]; 6]; 2],
+    counts: [[usize; 6]; 2]
+}
+
+impl Material {
+    pub fn new(board: &Board) -> Self {
+        let mut material = Self { squares: [[[0; MAX_PIECES]; 6]; 2], counts: [[0; 6]; 2] };
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece > 1 {
+                    let kind = KINDS.iter().position(|c| *c == board.gpr(&piece).to_ascii_lowercase()).unwrap();
+                    let color = (piece & 1) as usize;
+                    let count = &mut material.counts[color][kind];
+                    // more pieces are only possible in a broken position
+                    if *count < MAX_PIECES {
+                        material.squares[color][kind][*count] = y * 8 + x;
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        material
+    }
+
+    pub fn count(&self, color: usize, kind: usize) -> usize {
+        self.counts[color][kind]
+    }
+
+    pub fn squares(&self, color: usize, kind: usize) -> &[usize] {
+        &self.squares[color][kind][..self.counts[color][kind]]
+    }
+
+    // material without king, in pawns
+    pub fn cost(&self, color: usize) -> f32 {
+        (0..5).map(|kind| COSTS[kind] * self.count(color, kind) as f32).sum()
+    }
+
+    // color with more material (white if equal)
+    pub fn strong_color(&self) -> usize {
+        if self.cost(0) > self.cost(1) { 0 } else { 1 }
+    }
+
+    // material signature with the given color first, as "KRPvKR"
+    pub fn signature(&self, color: usize) -> String {
+        let mut code = String::new();
+        for side in [color, 1 - color] {
+            for kind in [K, Q, R, B, N, P] {
+                for _ in 0..self.count(side, kind) {
+                    code.push(KINDS[kind].to_ascii_uppercase());
+                }
+            }
+            if side == color {
+                code.push('v');
+            }
+        }
+        code
+    }
+}
+
+// Find the recognizer for the position material, if any
+pub fn probe(board: &Board) -> Option<EndgameEval> {
+    let material = Material::new(board);
+    let strong = material.strong_color();
+    let sign = if strong > 0 { 1.0 } else { -1.0 };
+    let signature = material.signature(strong);
+    match signature.as_str() {
+        "KvK" | "KNvK" | "KBvK" | "KNNvK" => Some(EndgameEval::Score(0.0)),
+        "KPvK" => Some(EndgameEval::Score(sign * eval_kpk(board, &material, strong))),
+        "KQvK" | "KRvK" | "KQQvK" | "KQRvK" | "KRRvK" => Some(EndgameEval::Score(sign * eval_mop_up(&material, strong))),
+        // bishops on the same color squares can't mate
+        "KBBvK" => {
+            let bishops = material.squares(strong, B);
+            Some(EndgameEval::Score(if is_light(bishops[0]) != is_light(bishops[1]) { sign * eval_mop_up(&material, strong) } else { 0.0 }))
+        },
+        "KBNvK" => Some(EndgameEval::Score(sign * eval_kbnk(&material, strong))),
+        _ => {
+            let weak = 1 - strong;
+            let only_pawns = |color: usize| (N..K).all(|kind| material.count(color, kind) == 0);
+            if signature.ends_with("vK") && material.count(strong, P) > 0 {
+                // wrong rook pawn: the defending king holds the corner
+                if (signature.starts_with("KBP") || only_pawns(strong)) && is_rook_pawn_draw(&material, strong, weak) {
+                    return Some(EndgameEval::Scale(0.0));
+                }
+            }
+            // opposite-colored bishops with pawns only
+            if material.count(strong, B) == 1 && material.count(weak, B) == 1
+                && (N..K).all(|kind| kind == B || (material.count(strong, kind) == 0 && material.count(weak, kind) == 0))
+                && is_light(material.squares(strong, B)[0]) != is_light(material.squares(weak, B)[0]) {
+                let difference = material.count(strong, P) as i32 - material.count(weak, P) as i32;
+                return Some(EndgameEval::Scale(if difference.abs() < 2 { 0.25 } else { 0.5 }));
+            }
+            None
+        }
+    }
+}
+
+// Neither side can ever checkmate: kings with at most one minor piece, or with bishops on the same color squares only.
+// Cheap enough for every node of the search (unlike probe), the game is a draw whatever the moves are.
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let (bishop, knight) = (board.gpl(&'b'), board.gpl(&'n'));
+    let mut minors = 0;
+    let mut knights = 0;
+    let mut bishop_colors = [false; 2];
+    for y in 0..8 {
+        for x in 0..8 {
+            match board.field[y][x] & 254 {
+                piece if piece < 2 || piece == board.gpl(&'k') => (),
+                piece if piece == bishop => {
+                    minors += 1;
+                    bishop_colors[is_light(y * 8 + x) as usize] = true;
+                },
+                piece if piece == knight => {
+                    minors += 1;
+                    knights += 1;
+                },
+                _ => return false
+            }
+        }
+    }
+    minors < 2 || (knights == 0 && !(bishop_colors[0] && bishop_colors[1]))
+}
+
+// bare king against pawn: exact result from the bitbase
+fn eval_kpk(board: &Board, material: &Material, strong: usize) -> f32 {
+    // normalize to white pawn on files a-d
+    let flip_rank = if strong > 0 { 0 } else { 56 };
+    let mut pawn = material.squares(strong, P)[0] ^ flip_rank;
+    let mut strong_king = material.squares(strong, K)[0] ^ flip_rank;
+    let mut weak_king = material.squares(1 - strong, K)[0] ^ flip_rank;
+    if pawn & 7 > 3 {
+        pawn ^= 7;
+        strong_king ^= 7;
+        weak_king ^= 7;
+    }
+    let strong_to_move = board.white_to_move == (strong > 0);
+    if kpk_probe(strong_king, pawn, weak_king, strong_to_move) {
+        KNOWN_WIN + COSTS[P] + (pawn >> 3) as f32 * 0.1
+    } else {
+        0.0
+    }
+}
+
+// bare king against a decisive material advantage: drive the king to the edge and come closer
+fn eval_mop_up(material: &Material, strong: usize) -> f32 {
+    let strong_king = material.squares(strong, K)[0];
+    let weak_king = material.squares(1 - strong, K)[0];
+    KNOWN_WIN + material.cost(strong) + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+}
+
+// bishop and knight mate is only possible in the corner of the bishop's color
+fn eval_kbnk(material: &Material, strong: usize) -> f32 {
+    let strong_king = material.squares(strong, K)[0];
+    let mut weak_king = material.squares(1 - strong, K)[0];
+    // a1 and h8 are dark, so mirror the board for the light bishop
+    if is_light(material.squares(strong, B)[0]) {
+        weak_king ^= 7;
+    }
+    // distance from a8-h1 diagonal
+    let push_to_corner = ((7 - (weak_king >> 3) as i32) - (weak_king & 7) as i32).abs() as f32;
+    KNOWN_WIN + material.cost(strong) + push_close(strong_king, weak_king) + push_to_corner * 0.5
+}
+
+// all pawns are on one rook file, the defending king is in front of them near the promotion square
+// and the bishop (if any) can't control it
+fn is_rook_pawn_draw(material: &Material, strong: usize, weak: usize) -> bool {
+    let pawns = material.squares(strong, P);
+    let file = pawns[0] & 7;
+    if (file != 0 && file != 7) || pawns.iter().any(|pawn| pawn & 7 != file) {
+        return false;
+    }
+    let promotion = if strong > 0 { 56 + file } else { file };
+    if let Some(bishop) = material.squares(strong, B).first() {
+        if is_light(*bishop) == is_light(promotion) {
+            return false;
+        }
+    }
+    let weak_king = material.squares(weak, K)[0];
+    let in_front = pawns.iter().all(|pawn| if strong > 0 { weak_king >> 3 > pawn >> 3 } else { weak_king >> 3 < pawn >> 3 });
+    // without a bishop the king just has to block the file
+    distance(weak_king, promotion) <= 1 || (in_front && (weak_king & 7).abs_diff(file) <= 1 && material.squares(strong, B).is_empty())
+}
+
+fn is_light(square: usize) -> bool {
+    !((square >> 3) + (square & 7)).is_multiple_of(2)
+}
+
+fn distance(a: usize, b: usize) -> usize {
+    max((a >> 3).abs_diff(b >> 3), (a & 7).abs_diff(b & 7))
+}
+
+fn push_to_edge(square: usize) -> f32 {
+    let rank = min(square >> 3, 7 - (square >> 3)) as f32;
+    let file = min(square & 7, 7 - (square & 7)) as f32;
+    0.9 - 0.035 * (rank * rank + file * file)
+}
+
+fn push_close(a: usize, b: usize) -> f32 {
+    1.4 - 0.2 * distance(a, b) as f32
+}
+
+//
+// KPK bitbase
+
+// Index of the position with the white pawn on files a-d and ranks 2-7:
+// bits 0-5 - white king square, bits 6-11 - black king square, bit 12 - side to move (1 for black),
+// bits 13-14 - pawn file, bits 15-17 - 7th rank minus pawn rank
+const KPK_SIZE: usize = 2 * 24 * 64 * 64;
+
+const KPK_INVALID: u8 = 0;
+const KPK_UNKNOWN: u8 = 1;
+const KPK_DRAW: u8 = 2;
+const KPK_WIN: u8 = 4;
+
+static KPK_BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+// true if white wins (white pawn on files a-d)
+pub fn kpk_probe(white_king: usize, pawn: usize, black_king: usize, white_to_move: bool) -> bool {
+    let bitbase = KPK_BITBASE.get_or_init(kpk_generate);
+    let idx = kpk_index(!white_to_move, black_king, white_king, pawn);
+    bitbase[idx >> 6] & (1 << (idx & 63)) > 0
+}
+
+fn kpk_index(black_to_move: bool, black_king: usize, white_king: usize, pawn: usize) -> usize {
+    white_king | (black_king << 6) | ((black_to_move as usize) << 12) | ((pawn & 7) << 13) | ((6 - (pawn >> 3)) << 15)
+}
+
+fn king_attacks(square: usize) -> Vec<usize> {
+    let mut squares = Vec::default();
+    for y in (square >> 3).saturating_sub(1)..min((square >> 3) + 2, 8) {
+        for x in (square & 7).saturating_sub(1)..min((square & 7) + 2, 8) {
+            if y * 8 + x != square {
+                squares.push(y * 8 + x);
+            }
+        }
+    }
+    squares
+}
+
+fn pawn_attacks(square: usize) -> Vec<usize> {
+    let mut squares = Vec::default();
+    if square < 56 {
+        if square & 7 > 0 {
+            squares.push(square + 7);
+        }
+        if square & 7 < 7 {
+            squares.push(square + 9);
+        }
+    }
+    squares
+}
+
+// retrograde analysis: start with obvious wins and draws, then classify the rest until nothing changes
+fn kpk_generate() -> Vec<u64> {
+    let mut db = vec![KPK_UNKNOWN; KPK_SIZE];
+    for (idx, result) in db.iter_mut().enumerate() {
+        let (white_king, black_king, black_to_move, pawn) = kpk_decode(idx);
+        *result = if distance(white_king, black_king) <= 1 || white_king == pawn || black_king == pawn
+            || (!black_to_move && pawn_attacks(pawn).contains(&black_king)) {
+            KPK_INVALID
+        } else if !black_to_move && pawn >> 3 == 6 && white_king != pawn + 8
+            && (distance(black_king, pawn + 8) > 1 || distance(white_king, pawn + 8) == 1) {
+            // pawn promotes safely
+            KPK_WIN
+        } else if black_to_move && {
+            let white_attacks = king_attacks(white_king);
+            let black_moves = king_attacks(black_king);
+            // stalemate or undefended pawn capture
+            black_moves.iter().all(|s| white_attacks.contains(s) || pawn_attacks(pawn).contains(s))
+                || (black_moves.contains(&pawn) && !white_attacks.contains(&pawn))
+        } {
+            KPK_DRAW
+        } else {
+            KPK_UNKNOWN
+        };
+    }
+
+    let mut repeat = true;
+    while repeat {
+        repeat = false;
+        for idx in 0..KPK_SIZE {
+            if db[idx] == KPK_UNKNOWN {
+                db[idx] = kpk_classify(&db, idx);
+                repeat |= db[idx] != KPK_UNKNOWN;
+            }
+        }
+    }
+
+    let mut bitbase = vec![0u64; KPK_SIZE / 64];
+    for (idx, result) in db.iter().enumerate() {
+        if *result == KPK_WIN {
+            bitbase[idx >> 6] |= 1 << (idx & 63);
+        }
+    }
+    bitbase
+}
+
+fn kpk_decode(idx: usize) -> (usize, usize, bool, usize) {
+    let pawn = (6 - ((idx >> 15) & 7)) * 8 + ((idx >> 13) & 3);
+    (idx & 63, (idx >> 6) & 63, (idx >> 12) & 1 > 0, pawn)
+}
+
+// White to move wins if any move wins and draws if every move draws, black to move is the other way around
+fn kpk_classify(db: &[u8], idx: usize) -> u8 {
+    let (white_king, black_king, black_to_move, pawn) = kpk_decode(idx);
+    let (good, bad) = if black_to_move { (KPK_DRAW, KPK_WIN) } else { (KPK_WIN, KPK_DRAW) };
+    let mut r = KPK_INVALID;
+    if black_to_move {
+        for s in king_attacks(black_king) {
+            r |= db[kpk_index(false, s, white_king, pawn)];
+        }
+    } else {
+        for s in king_attacks(white_king) {
+            r |= db[kpk_index(true, black_king, s, pawn)];
+        }
+        if pawn >> 3 < 6 {
+            r |= db[kpk_index(true, black_king, white_king, pawn + 8)];
+        }
+        if pawn >> 3 == 1 && pawn + 8 != white_king && pawn + 8 != black_king {
+            r |= db[kpk_index(true, black_king, white_king, pawn + 16)];
+        }
+    }
+    if r & good > 0 {
+        good
+    } else if r & KPK_UNKNOWN > 0 {
+        KPK_UNKNOWN
+    } else {
+        bad
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::board::Board;
+    use super::{probe, is_insufficient_material, EndgameEval, Material, KNOWN_WIN};
+
+    fn score(fen: &str) -> Option<f32> {
+        match probe(&Board::parse_fen(fen)) {
+            Some(EndgameEval::Score(score)) => Some(score),
+            _ => None
+        }
+    }
+
+    fn scale(fen: &str) -> Option<f32> {
+        match probe(&Board::parse_fen(fen)) {
+            Some(EndgameEval::Scale(scale)) => Some(scale),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn test_endgame_signature_01() {
+        let material = Material::new(&Board::parse_fen("8/8/4k3/8/2R5/1P6/8/3K3r w - - 0 1"));
+        assert_eq!(material.signature(1), "KRPvKR");
+        assert_eq!(material.signature(0), "KRvKRP");
+        assert_eq!(material.strong_color(), 1);
+    }
+
+    #[test]
+    fn test_endgame_kpk_01() {
+        // king in front of the pawn on the 6th rank wins whoever moves
+        assert!(score("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(score("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(score("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1").unwrap() < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_endgame_kpk_02() {
+        // stalemate
+        assert_eq!(score("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"), Some(0.0));
+        // rook pawn with the king in the corner
+        assert_eq!(score("k7/8/8/8/8/8/P7/K7 w - - 0 1"), Some(0.0));
+        assert_eq!(score("k7/p7/8/8/8/8/8/K7 b - - 0 1"), Some(0.0));
+        // the defending king keeps the opposition
+        assert_eq!(score("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"), Some(0.0));
+        // the pawn is lost
+        assert_eq!(score("8/8/8/8/8/8/3kP3/7K b - - 0 1"), Some(0.0));
+    }
+
+    #[test]
+    fn test_endgame_mop_up_01() {
+        let center = score("8/8/8/3k4/8/3K4/8/Q7 w - - 0 1").unwrap();
+        let edge = score("3k4/8/3K4/8/8/8/8/Q7 w - - 0 1").unwrap();
+        assert!(center > KNOWN_WIN);
+        assert!(edge > center);
+        assert!(score("3K4/8/8/8/8/8/8/3rk3 w - - 0 1").unwrap() < -KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_endgame_kbbk_01() {
+        assert!(score("8/8/4k3/8/8/8/8/2B1KB2 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(score("4k3/3bb3/8/8/8/8/8/4K3 b - - 0 1").unwrap() < -KNOWN_WIN);
+        // both bishops on the same color squares
+        assert_eq!(score("8/8/4k3/8/8/8/8/3BKB2 w - - 0 1"), Some(0.0));
+        assert_eq!(score("4k3/2b1b3/8/8/8/8/8/4K3 w - - 0 1"), Some(0.0));
+    }
+
+    #[test]
+    fn test_endgame_kbnk_01() {
+        // dark-squared bishop mates in a1 or h8
+        let right = score("8/8/8/8/8/2K5/8/k1B1N3 w - - 0 1").unwrap();
+        let wrong = score("8/8/8/8/8/5K2/8/2B1N2k w - - 0 1").unwrap();
+        assert!(right > wrong);
+        assert!(wrong > KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_endgame_draws_01() {
+        assert_eq!(score("8/8/4k3/8/8/8/8/3NK3 w - - 0 1"), Some(0.0));
+        assert_eq!(score("8/8/4k3/8/8/8/8/3BK3 b - - 0 1"), Some(0.0));
+        assert_eq!(score("8/8/4k3/8/8/8/8/4K3 w - - 0 1"), Some(0.0));
+    }
+
+    #[test]
+    fn test_endgame_scale_01() {
+        // light-squared bishop can't help the h-pawn promote on dark h8
+        assert_eq!(scale("7k/8/7P/8/8/8/4B3/4K3 w - - 0 1"), Some(0.0));
+        // dark-squared bishop can
+        assert_eq!(scale("7k/8/7P/8/8/4B3/8/4K3 w - - 0 1"), None);
+        // opposite-colored bishops
+        assert_eq!(scale("8/5k2/4b3/2p5/2P1P3/4B3/5K2/8 w - - 0 1"), Some(0.25));
+        assert_eq!(scale("8/5k2/3b4/2p5/2P1P3/4B3/5K2/8 w - - 0 1"), None);
+    }
+
+    #[test]
+    fn test_endgame_insufficient_material_01() {
+        let dead = |fen: &str| is_insufficient_material(&Board::parse_fen(fen));
+        assert!(dead("8/8/4k3/8/8/8/8/4K3 w - - 0 1"));
+        assert!(dead("8/8/4k3/8/8/8/8/3NK3 b - - 0 1"));
+        // bishops on light squares only, whatever side they are
+        assert!(dead("2b5/8/4k3/8/8/8/8/3BKB2 w - - 0 1"));
+        assert!(!dead("8/8/4k3/8/8/8/8/2B1KB2 w - - 0 1"));
+        assert!(!dead("8/8/4k3/8/8/8/8/3NKN2 w - - 0 1"));
+        assert!(!dead("8/3n4/4k3/8/8/8/8/3BK3 w - - 0 1"));
+        assert!(!dead("8/8/4k3/8/8/8/7P/4K3 w - - 0 1"));
+    }
+}
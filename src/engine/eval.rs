@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use serde::Serialize;
 use crate::board::mov::Mov;
 
 // if there's a winning sequence, we should look onto the fastest possible, not on the score
@@ -106,8 +107,55 @@ impl EvalHashed {
     }
 }
 
+// static evaluation broken down by terms, see Character::explain_eval()
+#[derive(Clone, Serialize)]
+pub struct EvalExplanation {
+    pub terms: Vec<EvalTerm>,
+    // game phase: 1.0 - middlegame, 0.0 - endgame
+    pub phase: f32,
+    // drawish material multiplier of the sum of terms
+    pub scale: f32,
+    // rule that decided the evaluation instead of terms (e.g. known endgame), terms are zero then
+    pub rule: Option<&'static str>,
+    // random blur of the evaluation with limited strength, see Strength::get_noise()
+    pub noise: f32,
+    // sum of term values multiplied by scale plus noise, the same as the static evaluation
+    pub total: f32
+}
+
+#[derive(Clone, Serialize)]
+pub struct EvalTerm {
+    pub name: &'static str,
+    // [middlegame, endgame] contributions of every side, from white's perspective
+    pub white: [f32; 2],
+    pub black: [f32; 2],
+    // both sides tapered by phase
+    pub value: f32
+}
+
+impl EvalExplanation {
+    pub fn print(&self) {
+        println!("{:<20} {:>16} {:>16} {:>8}", "term", "white (mg/eg)", "black (mg/eg)", "value");
+        for term in &self.terms {
+            println!("{:<20} {:>7.3}/{:<8.3} {:>7.3}/{:<8.3} {:>8.3}", term.name, term.white[0], term.white[1], term.black[0], term.black[1], term.value);
+        }
+        if let Some(rule) = self.rule {
+            println!("decided by rule: {}", rule);
+        }
+        if self.noise != 0.0 {
+            println!("noise: {:.3}", self.noise);
+        }
+        println!("phase: {:.3}, scale: {:.3}, total: {:.3}", self.phase, self.scale, self.total);
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
     use std::cmp::{min, max};
@@ -1,27 +1,47 @@
-use std::{cmp::{max, min, Ordering}};
+use std::cmp::{max, min, Ordering};
+use rand::{rngs::StdRng, SeedableRng, seq::SliceRandom};
 use crate::{board::{board::{Board, Check}, mov::{Mov}}};
-use super::{eval::{EvalMov, Eval}, character::Character};
+use super::{eval::{EvalMov, Eval}, character::{Evaluator, SearchConfig}, cache::PositionCache, picker::{MovePicker, PickerStage}, endgame::is_insufficient_material};
 
 // this will copy the first minimax iteration
 // the purpose is to have a vector of evaluated possible moves as an output, not just the best one IF necessary
 // initial alpha and beta are recommended to be just Eval::low(), Eval::high()
-pub fn eval<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval, mut beta: Eval) -> Vec<EvalMov> {
+pub fn eval<Char: Evaluator + SearchConfig, Cache: PositionCache>(board: &mut Board, char: &mut Char, cache: &mut Cache, alpha: Eval, beta: Eval) -> Vec<EvalMov> {
+    eval_root(board, char, cache, alpha, beta, false)
+}
+
+// same as eval(), but every move is searched with the initial alpha and beta, so all the evals are exact
+// (not just the best one, the rest are only bounds after pruning)
+pub fn eval_exact<Char: Evaluator + SearchConfig, Cache: PositionCache>(board: &mut Board, char: &mut Char, cache: &mut Cache, alpha: Eval, beta: Eval) -> Vec<EvalMov> {
+    eval_root(board, char, cache, alpha, beta, true)
+}
+
+#[allow(clippy::unnecessary_sort_by)]
+fn eval_root<Char: Evaluator + SearchConfig, Cache: PositionCache>(board: &mut Board, char: &mut Char, cache: &mut Cache, mut alpha: Eval, mut beta: Eval, exact: bool) -> Vec<EvalMov> {
     if char.get_static_half_depth() < 1 {
         panic!("0-half-depth minimax search attempt!\nStatic half depth must be at least 1, ideally divisible by 2.");
     }
     let mut moves: Vec<Mov> = board.get_legal_moves(Some(Check::Unknown), Some(true));
     let mut evals: Vec<EvalMov> = Vec::default();
-    
+    // root is on the search line as well: getting back to it is a repetition
+    let hash = cache.make_hash(board);
+    cache.cache_play(hash);
+
+    // equally evaluated moves keep the search order, so ties are broken by the shuffle (sorts are stable)
+    moves.shuffle(&mut StdRng::seed_from_u64(char.get_search_seed()));
     // pre-sort in descending order by Mov data (will fasten a/b pruning)
     moves.sort_by(|a, b| b.data.cmp(&a.data));
 
     for mov in &moves {
-        board.make_move(mov);
+        char.make_move(board, mov);
         evals.push(EvalMov{ 
             mov: *mov, 
-            eval: minimax(board, char, alpha, beta, board.white_to_move, board.get_check(&mov.data), 1, false) });
-        board.revert_move();
+            eval: minimax(board, char, cache, alpha, beta, board.white_to_move, board.get_check(&mov.data), 1) });
+        char.revert_move(board);
 
+        if exact {
+            continue;
+        }
         if board.white_to_move {
             alpha = max(alpha, evals.last().unwrap().eval);
         } else {
@@ -32,6 +52,8 @@ pub fn eval<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval
         }
     }
 
+    cache.cache_unplay(hash);
+
     // sort evaluated moves from the best to the worst in according to the current player to move
     if board.white_to_move {
         evals.sort_by(|a, b| b.eval.cmp(&a.eval));
@@ -42,23 +64,29 @@ pub fn eval<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval
 }
 
 // will return score eval and the mate_in moves if there's a forced checkmate sequence
-fn minimax<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval, mut beta: Eval, maximize: bool, check: Check, depth: i8, force_break: bool) -> Eval {
-    let hash = char.make_hash(board);
+// search state goes down the recursion as it is, without bundling it up
+#[allow(clippy::too_many_arguments)]
+fn minimax<Char: Evaluator + SearchConfig, Cache: PositionCache>(board: &mut Board, char: &mut Char, cache: &mut Cache, mut alpha: Eval, mut beta: Eval, maximize: bool, check: Check, depth: i8) -> Eval {
+    let hash = cache.make_hash(board);
     let sd = depth < char.get_static_half_depth();
 
-    if char.is_played(hash) {
-        return Eval::equal();
+    // Draws by rules are known before the move generation:
+    // position repeated on the search line (twofold) or for the third time in the game,
+    // fifty moves without captures and pawn moves (unless it's a checkmate, see below), no material to checkmate with
+    // (dead positions are never cached, they are looked for on a cache miss only)
+    if cache.is_played(hash) || (board.hmw > 99 && check == Check::NotInCheck) {
+        return Eval { score: char.get_draw_score(), mate_in: 0 };
     }
-    if char.is_evaluated(hash) {
-        let stored_eval = char.get_hashed_eval(hash);
-        let stored_depth = char.get_hashed_depth(hash);
+    if cache.is_evaluated(hash) {
+        let stored_eval = cache.get_hashed_eval(hash);
+        let stored_depth = cache.get_hashed_depth(hash);
         match depth.cmp(&stored_depth) {
             // Meaining: we got to some evaluated position faster.
             Ordering::Less => {
                 if !sd {
                     return stored_eval;
                 }
-                char.cache_play(hash);
+                cache.cache_play(hash);
             },
             // Meaining: we got to some evaluated position by different move order.
             Ordering::Equal => {
@@ -82,16 +110,20 @@ fn minimax<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval,
             }
         }
     } else {
-        char.cache_play(hash);
+        if is_insufficient_material(board) {
+            return Eval { score: char.get_draw_score(), mate_in: 0 };
+        }
+        cache.cache_play(hash);
     }
 
     // It might be even faster to check for half_depth before, but it just feels wrong
-    // get_legal_moves is a __relatively__ heavy method
+    // moves are generated stage by stage (see MovePicker), the first one tells if there are any
     // the fact that there are certain moves may be useful to get interesting static results
     let mut eval: Eval;
-    let mut moves: Vec<Mov> = board.get_legal_moves(Some(check), Some(true));
+    let mut picker = MovePicker::new(check);
+    let mut next = picker.pick(board);
 
-    if moves.is_empty() {
+    if next.is_none() {
         let eval = match check {
             Check::InCheck | Check::InDoubleCheck => {
                 let mate_in = if maximize {
@@ -108,67 +140,72 @@ fn minimax<Char: Character>(board: &mut Board, char: &mut Char, mut alpha: Eval,
             },
             // Stalemate
             _ => Eval {
-                score: char.get_static_eval_stalemate(board),
+                score: char.get_static_eval_stalemate(board) + char.get_draw_score(),
                 mate_in: 0
             }
         };
-        char.cache_unplay(hash);
-        char.cache_evaluated(hash, eval, depth);
+        cache.cache_unplay(hash);
+        cache.cache_evaluated(hash, eval, depth);
         return eval;
     }
 
-    // pre-sort in descending order by Mov data (will fasten a/b pruning)
-    moves.sort_by(|a, b| b.data.cmp(&a.data));
+    // fifty-move rule in check: it's not a checkmate, so it's a draw (counter is not hashed, the eval is not cached)
+    if board.hmw > 99 {
+        cache.cache_unplay(hash);
+        return Eval { score: char.get_draw_score(), mate_in: 0 };
+    }
 
-    if depth >= char.get_dynamic_half_depth() {
+    // tablebase score is exact, so there is no need to search any deeper
+    if let Some(eval) = char.get_tablebase_eval(board) {
+        cache.cache_unplay(hash);
+        cache.cache_evaluated(hash, eval, depth);
+        return eval;
+    }
+
+    if depth >= char.get_dynamic_half_depth() || !char.count_node() {
         let eval = Eval { score: char.get_static_eval(board), mate_in: 0 };
-        char.cache_unplay(hash);
-        char.cache_evaluated(hash, eval, depth);
+        cache.cache_unplay(hash);
+        cache.cache_evaluated(hash, eval, depth);
         return eval;
     }
     
     let md = depth < char.get_mixed_half_depth();
-    let mut cur = 0;
-    if maximize {
-        eval = Eval::lowest();
-        while cur < moves.len() && (sd || moves[cur].is_dynamic() || (md && (check == Check::InCheck || check == Check::InDoubleCheck))) {
-            board.make_move(&moves[cur]);
-            let temp = minimax(board, char, alpha, beta, board.white_to_move, board.get_check(&moves[cur].data), depth + 1, false);
-            board.revert_move();
-            eval = max(eval, temp);
-            alpha = max(alpha, temp);
-            if beta <= alpha {
+    let in_check = check == Check::InCheck || check == Check::InDoubleCheck;
+    // quiescence skips quiet moves (promotions among them), but isn't forced to make dynamic ones if there are any
+    let mut quiets_left = false;
+    eval = if maximize { Eval::lowest() } else { Eval::highest() };
+    while let Some(mov) = next {
+        if !(sd || mov.is_dynamic() || (md && in_check)) {
+            quiets_left = true;
+            // quiet moves that are not checks are never dynamic, no need to look through them
+            if picker.stage() == PickerStage::Quiets {
                 break;
             }
-            cur += 1;
+            next = picker.pick(board);
+            continue;
         }
-        // if we are searching for quiescence we must also take into account the fact that we are not forced to make dynamic moves
-        if !sd && cur < moves.len() && beta > alpha {
-            let temp = Eval { score: char.get_static_eval(board), mate_in: 0 };
+        char.make_move(board, &mov);
+        let temp = minimax(board, char, cache, alpha, beta, board.white_to_move, board.get_check(&mov.data), depth + 1);
+        char.revert_move(board);
+        if maximize {
             eval = max(eval, temp);
-        }
-    } else {
-        eval = Eval::highest();
-        while cur < moves.len() && (sd || moves[cur].is_dynamic() || (md && (check == Check::InCheck || check == Check::InDoubleCheck))) {
-            board.make_move(&moves[cur]);
-            let temp = minimax(board, char, alpha, beta, board.white_to_move, board.get_check(&moves[cur].data), depth + 1, false);
-            board.revert_move();
+            alpha = max(alpha, temp);
+        } else {
             eval = min(eval, temp);
             beta = min(beta, temp);
-            if beta <= alpha {
-                break;
-            }
-            cur += 1;
         }
-        //
-        if !sd && cur < moves.len() && beta > alpha {
-            let temp = Eval { score: char.get_static_eval(board), mate_in: 0 };
-            eval = min(eval, temp);
+        if beta <= alpha {
+            break;
         }
+        next = picker.pick(board);
+    }
+    if !sd && quiets_left && beta > alpha {
+        let temp = Eval { score: char.get_static_eval(board), mate_in: 0 };
+        eval = if maximize { max(eval, temp) } else { min(eval, temp) };
     }
 
-    char.cache_unplay(hash);
-    char.cache_evaluated(hash, eval, depth);
+    cache.cache_unplay(hash);
+    cache.cache_evaluated(hash, eval, depth);
     eval
 }
 
@@ -1,4 +1,12 @@
 pub(crate) mod minimax;
 pub(crate) mod eval;
 pub(crate) mod character;
-pub(crate) mod hashtable;
+pub(crate) mod book;
+pub(crate) mod polyglot;
+pub(crate) mod syzygy;
+pub(crate) mod endgame;
+pub(crate) mod strength;
+pub(crate) mod cache;
+pub(crate) mod store;
+pub(crate) mod picker;
+pub(crate) mod searcher;
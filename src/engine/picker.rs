@@ -0,0 +1,99 @@
+use std::cmp::Reverse;
+use crate::board::{board::{Board, Check, Stage}, mov::Mov};
+
+// stage of the picked moves
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum PickerStage {
+    // nothing is picked yet
+    Start,
+    // captures (en passant as well) and promotions
+    Captures,
+    // quiet moves giving check
+    QuietChecks,
+    // the rest of quiet moves
+    Quiets
+}
+
+// Moves of a position on demand, stage by stage: captures and promotions, quiet checks, the rest of quiet moves.
+// A stage is generated only when the previous one is over, so a cutoff on a capture saves generating the quiet moves.
+// Moves of a stage come in descending order by Mov data, all of them with their check bits.
+pub struct MovePicker {
+    check: Check,
+    stage: PickerStage,
+    moves: Vec<Mov>,
+    cur: usize,
+    // quiet moves are generated at once, checks are picked first
+    quiets: Vec<Mov>
+}
+
+impl MovePicker {
+    // check status of the side to move, as in get_legal_moves
+    pub fn new(check: Check) -> Self {
+        Self {
+            check,
+            stage: PickerStage::Start,
+            moves: Vec::default(),
+            cur: 0,
+            quiets: Vec::default()
+        }
+    }
+
+    // next move to search, None when all the stages are over; the board must be in the same position on every call
+    pub fn pick(&mut self, board: &Board) -> Option<Mov> {
+        while self.cur == self.moves.len() {
+            match self.stage {
+                PickerStage::Start => {
+                    self.stage = PickerStage::Captures;
+                    self.moves = board.get_legal_moves_staged(Some(self.check), Some(true), Stage::Captures);
+                },
+                PickerStage::Captures => {
+                    self.stage = PickerStage::QuietChecks;
+                    let quiets = board.get_legal_moves_staged(Some(self.check), Some(true), Stage::Quiets);
+                    (self.moves, self.quiets) = quiets.into_iter().partition(|mov| board.get_check(&mov.data) != Check::NotInCheck);
+                },
+                PickerStage::QuietChecks => {
+                    self.stage = PickerStage::Quiets;
+                    self.moves = std::mem::take(&mut self.quiets);
+                },
+                PickerStage::Quiets => return None
+            }
+            self.moves.sort_by_key(|mov| Reverse(mov.data));
+            self.cur = 0;
+        }
+        self.cur += 1;
+        Some(self.moves[self.cur - 1])
+    }
+
+    // stage of the last picked move
+    pub fn stage(&self) -> PickerStage {
+        self.stage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::{Board, Check}, utils::utils::move_to_user};
+    use super::{MovePicker, PickerStage};
+
+    #[test]
+    fn test_picker_stages_01() {
+        let b = Board::parse_fen("r3k2r/1P3ppp/7N/3pP3/8/8/8/4KB2 w kq d6 0 1");
+        let mut picker = MovePicker::new(Check::Unknown);
+        let mut picked: Vec<(String, PickerStage)> = Vec::default();
+        while let Some(mov) = picker.pick(&b) {
+            picked.push((move_to_user(&b, &mov), picker.stage()));
+        }
+        assert!(picker.pick(&b).is_none());
+        // the same moves as the full generation, captures first: the queen promotion capture is the most valuable one
+        let mut all: Vec<String> = b.get_legal_moves(None, None).iter().map(|m| move_to_user(&b, m)).collect();
+        let mut names: Vec<String> = picked.iter().map(|(mov, _)| mov.clone()).collect();
+        assert_eq!(picked[0], ("b7a8q".to_string(), PickerStage::Captures));
+        assert!(picked.contains(&("f1b5".to_string(), PickerStage::QuietChecks)));
+        assert!(picked.contains(&("e1d1".to_string(), PickerStage::Quiets)));
+        assert!(picked.windows(2).all(|w| w[0].1 == w[1].1 || matches!((w[0].1, w[1].1),
+            (PickerStage::Captures, PickerStage::QuietChecks) | (PickerStage::Captures, PickerStage::Quiets) | (PickerStage::QuietChecks, PickerStage::Quiets))));
+        all.sort();
+        names.sort();
+        assert_eq!(all, names);
+    }
+}
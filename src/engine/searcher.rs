@@ -0,0 +1,454 @@
+use std::{cmp::{min, max}, mem};
+use rand::{rngs::StdRng, SeedableRng, Rng};
+
+use crate::board::{board::Board, mov::Mov, zobrist::Zobrist};
+use super::{character::{Character, Evaluator, SearchConfig}, cache::{HashCache, PositionCache}, eval::{EvalMov, Eval, EvalExplanation}, minimax::{eval, eval_exact}, book::Book, syzygy::Tablebase, strength::Strength, store::AnalysisStore};
+
+// Search driver of a character: minimax with the cache, alpha/beta bounds between the moves of the game,
+// book, tablebases, analysis store, limited strength and seed. Any Evaluator (the style) becomes a Character with it.
+pub struct Searcher<E: Evaluator> {
+    evaluator: E,
+
+    // maximum static half-depth for minimax eval() call
+    static_half_depth: i8,
+    // maximum half-static half-depth for minimax eval() call
+    mixed_half_depth: i8,
+    // maximum dynamic half-depth for minimax eval() call
+    dynamic_half_depth: i8,
+    // played and evaluated positions, lent to minimax on every search: evals depend on the draw score,
+    // so with contempt the searches for white and for black keep their own caches
+    caches: [HashCache; 2],
+    // cache of the current search
+    side: usize,
+    // history of alpha/beta values used for minimax search (including next one to use)
+    alpha_stack: Vec<Eval>,
+    beta_stack:  Vec<Eval>,
+
+    // temporary storage of evaluated moves
+    // will clear itself on every accept_move() call
+    evals: Vec<EvalMov>,
+    // opening book, consulted before the search
+    book: Option<Book>,
+    // endgame tablebases, probed at the root and in the search tree
+    tablebase: Option<Tablebase>,
+    // analysis of the previous sessions, consulted before the search
+    store: Option<AnalysisStore>,
+    // limited playing strength, None is the full one
+    strength: Option<Strength>,
+    // seed of all random choices, see Character::set_seed()
+    seed: u64,
+    // how much worse than equal a draw is for the character (in pawns), negative ones seek draws
+    contempt: f32,
+    // seed of the current search: character seed mixed with the position hash
+    search_seed: u64,
+    // mixed into position hashes for the eval noise, derived from the seed
+    noise_key: u64,
+    // positions searched by the current search
+    nodes: u64,
+    // score of a draw in the current search: contempt of the side to move at its root (see SearchConfig::get_draw_score)
+    draw_score: f32,
+    // move choice and blunders with limited strength, reseeded by every search
+    rng: StdRng
+}
+
+impl<E: Evaluator> Character for Searcher<E> {
+    fn get_eval_move(&mut self, board: &mut Board) -> EvalMov {
+        let evals = self.get_eval_moves(board).clone();
+        let strength = match self.strength {
+            Some(strength) if evals.len() > 1 => strength,
+            _ => return evals[0]
+        };
+        if self.rng.gen::<f32>() < strength.blunder_chance {
+            if let Some(eval_mov) = self.get_blunder(board, &evals, &strength) {
+                return eval_mov;
+            }
+        }
+        evals[strength.choose(&evals, board.white_to_move, &mut self.rng)]
+    }
+
+    fn get_eval_moves(&mut self, board: &mut Board) -> &Vec<EvalMov> {
+        let depths = [self.static_half_depth, self.mixed_half_depth, self.dynamic_half_depth];
+
+        self.search_seed = Zobrist::mix(self.seed ^ board.hash);
+        self.rng = StdRng::seed_from_u64(self.search_seed);
+        for cache in &mut self.caches {
+            cache.start(board);
+        }
+        // draws are scored against the side the search is made for
+        self.draw_score = if board.white_to_move { -self.contempt } else { self.contempt };
+        self.side = if self.contempt == 0.0 { 0 } else { board.white_to_move as usize };
+
+        // book move is played instantly, without any evaluation
+        if let Some(book) = &mut self.book {
+            book.set_seed(self.search_seed);
+            if let Some(mov) = book.get_move(board) {
+                self.evals = [EvalMov{ mov, eval: Eval::equal() }].to_vec();
+                return &self.evals;
+            }
+        }
+
+        // tablebase moves are ranked by distance to zeroing, so won endgames are converted instead of shuffled
+        if let Some(tablebase) = &mut self.tablebase {
+            if let Some(evals) = tablebase.get_eval_moves(board) {
+                if !evals.is_empty() {
+                    self.evals = evals;
+                    return &self.evals;
+                }
+            }
+        }
+
+        // deep enough analysis of the previous sessions (weaker levels need evaluations of all moves)
+        let salt = self.get_store_salt();
+        if self.strength.is_none() {
+            if let Some(eval_mov) = self.store.as_ref().and_then(|store| store.get(board, salt, self.static_half_depth)) {
+                self.evals = [eval_mov].to_vec();
+                return &self.evals;
+            }
+        }
+
+        // weaker levels don't search as deep as the character would
+        self.nodes = 0;
+        if let Some(strength) = &self.strength {
+            self.static_half_depth = min(self.static_half_depth, strength.depths[0]);
+            self.mixed_half_depth = min(self.mixed_half_depth, strength.depths[1]);
+            self.dynamic_half_depth = min(self.dynamic_half_depth, strength.depths[2]);
+        }
+
+        // TODO: write a good code?
+        // This is wrong because it mess with the depth and doesn't tell user about it.
+        // However this will yield instant results instead of pondering already evaluated positions, also mate enclosure.
+        // May provoke something unprecedented.
+        let mut mate_in = 0;
+        if self.alpha_stack.last().unwrap().mate_in != -Eval::BIG_MATE {
+            mate_in = -self.alpha_stack.last().unwrap().mate_in;
+        } else if self.beta_stack.last().unwrap().mate_in != Eval::BIG_MATE {
+            mate_in = self.beta_stack.last().unwrap().mate_in;
+        }
+        if mate_in != 0 {
+            self.static_half_depth = mate_in;
+            self.mixed_half_depth = mate_in;
+            self.dynamic_half_depth = mate_in;
+        }
+
+        let (alpha, beta) = (*self.alpha_stack.last().unwrap(), *self.beta_stack.last().unwrap());
+        // move choice with limited strength needs to know how bad the other moves are
+        let exact = self.strength.is_some_and(|strength| strength.temperature > 0.0);
+        self.evals = self.search(board, alpha, beta, exact);
+        // positions cut by the node limit are evaluated statically, they are not good for the next search
+        if self.strength.is_some_and(|strength| self.nodes > strength.max_nodes) {
+            self.caches[self.side].clear();
+        }
+        // only full strength searches with full window are exact
+        if let (Some(store), None, 0, Some(best)) = (&mut self.store, self.strength, mate_in, self.evals.first()) {
+            if alpha == Eval::low() && beta == Eval::high() {
+                if let Err(e) = store.insert(board, salt, best, self.static_half_depth) {
+                    eprintln!("Cannot write analysis store: {}", e);
+                }
+            }
+        }
+        self.static_half_depth = depths[0];
+        self.mixed_half_depth = depths[1];
+        self.dynamic_half_depth = depths[2];
+        &self.evals
+    }
+
+    fn accept_move(&mut self, board: &Board) {
+        let mut found = false;
+        let mov = board.history.last().unwrap().mov;
+        // set next a/b values to push into search
+        for eval_mov in &self.evals {
+            if eval_mov.mov == mov {
+                if eval_mov.eval.mate_in > 0 {
+                    self.alpha_stack.push(Eval::low());
+                    self.beta_stack.push(Eval::new(-Eval::BIG_SCORE, max(1, eval_mov.eval.mate_in - 1)));
+                } else if eval_mov.eval.mate_in < 0 {
+                    self.alpha_stack.push(Eval::new(Eval::BIG_SCORE, min(-1, eval_mov.eval.mate_in + 1)));
+                    self.beta_stack.push(Eval::high());
+                } else if !self.alpha_stack.is_empty() {
+                    self.alpha_stack.push(Eval::low());
+                    self.beta_stack.push(Eval::high());
+                }
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            let a = self.alpha_stack.last().unwrap().mate_in;
+            let b = self.beta_stack.last().unwrap().mate_in;
+            if a == 0 || a == -Eval::BIG_MATE {
+                self.alpha_stack.push(Eval::low());
+                if b == 0 || b == Eval::BIG_MATE {
+                    self.beta_stack.push(Eval::high());
+                } else {
+                    self.beta_stack.push(Eval::new(-Eval::BIG_SCORE, max(1, self.beta_stack.last().unwrap().mate_in - 1)));
+                }
+            } else {
+                self.alpha_stack.push(Eval::new(Eval::BIG_SCORE, min(-1, self.alpha_stack.last().unwrap().mate_in + 1)));
+                self.beta_stack.push(Eval::high());
+            }
+        }
+        for cache in &mut self.caches {
+            cache.accept_move(board);
+        }
+        self.evals.clear();
+    }
+
+    fn takeback(&mut self) {
+        for cache in &mut self.caches {
+            cache.takeback();
+        }
+        self.alpha_stack.pop();
+        self.beta_stack.pop();
+    }
+
+    fn set_book(&mut self, book: Option<Book>) {
+        self.book = book;
+    }
+
+    fn set_tablebase(&mut self, tablebase: Option<Tablebase>) {
+        self.tablebase = tablebase;
+    }
+
+    fn set_store(&mut self, store: Option<AnalysisStore>) {
+        self.store = store;
+    }
+
+    fn set_strength(&mut self, strength: Option<Strength>) {
+        self.strength = strength;
+        // cached evals were made without (or with another) noise
+        self.clear_caches();
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.noise_key = Zobrist::mix(!seed);
+        self.clear_caches();
+    }
+
+    fn get_seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+// the evaluation of the character with the noise of limited strength on top
+impl<E: Evaluator> Evaluator for Searcher<E> {
+    fn get_static_eval(&self, board: &Board) -> f32 {
+        self.evaluator.get_static_eval(board) + self.get_noise(board)
+    }
+
+    fn explain_eval(&self, board: &Board) -> EvalExplanation {
+        let mut explanation = self.evaluator.explain_eval(board);
+        let noise = self.get_noise(board);
+        explanation.noise += noise;
+        explanation.total += noise;
+        explanation
+    }
+
+    fn get_static_eval_mate(&self, board: &Board) -> f32 {
+        self.evaluator.get_static_eval_mate(board)
+    }
+
+    fn get_static_eval_stalemate(&self, board: &Board) -> f32 {
+        self.evaluator.get_static_eval_stalemate(board)
+    }
+
+    fn make_move(&mut self, board: &mut Board, mov: &Mov) {
+        self.evaluator.make_move(board, mov);
+    }
+
+    fn revert_move(&mut self, board: &mut Board) {
+        self.evaluator.revert_move(board);
+    }
+
+    fn get_eval_key(&self) -> u64 {
+        self.evaluator.get_eval_key()
+    }
+}
+
+impl<E: Evaluator> SearchConfig for Searcher<E> {
+    fn set_static_half_depth(&mut self, half_depth: i8) {
+        self.static_half_depth = half_depth;
+    }
+
+    fn set_mixed_half_depth(&mut self, half_depth: i8) {
+        self.mixed_half_depth = half_depth;
+    }
+
+    fn set_dynamic_half_depth(&mut self, half_depth: i8) {
+        self.dynamic_half_depth = half_depth;
+    }
+
+    fn get_static_half_depth(&self) -> i8 {
+        self.static_half_depth
+    }
+
+    fn get_mixed_half_depth(&self) -> i8 {
+        self.mixed_half_depth
+    }
+
+    fn get_dynamic_half_depth(&self) -> i8 {
+        self.dynamic_half_depth
+    }
+
+    fn get_tablebase_eval(&mut self, board: &mut Board) -> Option<Eval> {
+        self.tablebase.as_mut()?.get_eval(board)
+    }
+
+    fn count_node(&mut self) -> bool {
+        self.nodes += 1;
+        self.strength.is_none_or(|strength| self.nodes <= strength.max_nodes)
+    }
+
+    fn get_search_seed(&self) -> u64 {
+        self.search_seed
+    }
+
+    fn get_draw_score(&self) -> f32 {
+        self.draw_score
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    // static, mixed and dynamic half-depths of the search, random seed and no contempt
+    pub fn from_evaluator(evaluator: E, depths: &[i8; 3]) -> Self {
+        let seed = rand::random();
+        Self {
+            evaluator,
+            static_half_depth: depths[0],
+            mixed_half_depth: depths[1],
+            dynamic_half_depth: depths[2],
+            caches: [HashCache::new(), HashCache::new()],
+            side: 0,
+            alpha_stack: [Eval::low()].to_vec(),
+            beta_stack: [Eval::high()].to_vec(),
+            evals: Vec::default(),
+            book: None,
+            tablebase: None,
+            store: None,
+            strength: None,
+            seed,
+            contempt: 0.0,
+            search_seed: seed,
+            noise_key: Zobrist::mix(!seed),
+            nodes: 0,
+            draw_score: 0.0,
+            rng: StdRng::seed_from_u64(seed)
+        }
+    }
+
+
+    // how much worse than equal a draw is for the character (in pawns), negative ones seek draws
+    pub fn set_contempt(&mut self, contempt: f32) {
+        self.contempt = contempt;
+        self.clear_caches();
+    }
+
+    // Best move of the shallow search, which misses the opponent's reply, if it's not too bad for the full search
+    // (evals) to be played: a human-like blunder.
+    fn get_blunder(&mut self, board: &mut Board, evals: &[EvalMov], strength: &Strength) -> Option<EvalMov> {
+        let depths = [self.static_half_depth, self.mixed_half_depth, self.dynamic_half_depth];
+        // evals cached at one depth would be taken as the ones of another
+        self.caches[self.side].clear();
+        self.nodes = 0;
+        [self.static_half_depth, self.mixed_half_depth, self.dynamic_half_depth] = Strength::BLUNDER_DEPTHS;
+        let shallow = self.search(board, Eval::low(), Eval::high(), false);
+        [self.static_half_depth, self.mixed_half_depth, self.dynamic_half_depth] = depths;
+        self.caches[self.side].clear();
+
+        let mov = shallow.first()?.mov;
+        let eval_mov = evals.iter().find(|eval_mov| eval_mov.mov == mov)?;
+        strength.is_plausible(&evals[0].eval, &eval_mov.eval, board.white_to_move).then_some(*eval_mov)
+    }
+
+    // minimax search with the cache, see eval_exact() for exact
+    fn search(&mut self, board: &mut Board, alpha: Eval, beta: Eval, exact: bool) -> Vec<EvalMov> {
+        let mut cache = mem::take(&mut self.caches[self.side]);
+        let evals = if exact {
+            eval_exact(board, self, &mut cache, alpha, beta)
+        } else {
+            eval(board, self, &mut cache, alpha, beta)
+        };
+        self.caches[self.side] = cache;
+        evals
+    }
+
+    fn clear_caches(&mut self) {
+        for cache in &mut self.caches {
+            cache.clear();
+        }
+    }
+
+    // store key salt: evaluation, draw score and the depths of the search besides the static one (it's stored with the entry)
+    fn get_store_salt(&self) -> u64 {
+        Zobrist::mix(self.evaluator.get_eval_key() ^ ((self.draw_score.to_bits() as u64) << 16 | (self.mixed_half_depth as u8 as u64) << 8 | self.dynamic_half_depth as u8 as u64))
+    }
+
+    fn get_noise(&self, board: &Board) -> f32 {
+        self.strength.map_or(0.0, |strength| strength.get_noise(board.hash ^ self.noise_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{board::board::Board, engine::{cache::PositionCache, character::{Character, Evaluator, SearchConfig}, eval::{EvalExplanation, EvalTerm}}, utils::utils::{move_to_board, move_to_user}};
+    use super::Searcher;
+
+    // the simplest character: material only
+    struct Material;
+
+    impl Evaluator for Material {
+        fn get_static_eval(&self, board: &Board) -> f32 {
+            let mut score = 0.0;
+            for piece in board.field.iter().flatten().filter(|piece| **piece > 1) {
+                let cost = match board.gpr(piece).to_ascii_lowercase() {
+                    'p' => 1.0,
+                    'n' | 'b' => 3.0,
+                    'r' => 5.0,
+                    'q' => 9.0,
+                    _ => 0.0
+                };
+                score += if piece & 1 == 1 { cost } else { -cost };
+            }
+            score
+        }
+
+        fn explain_eval(&self, board: &Board) -> EvalExplanation {
+            let total = self.get_static_eval(board);
+            EvalExplanation {
+                terms: [EvalTerm { name: "material", white: [total; 2], black: [0.0; 2], value: total }].to_vec(),
+                phase: 1.0,
+                scale: 1.0,
+                rule: None,
+                noise: 0.0,
+                total
+            }
+        }
+    }
+
+    #[test]
+    fn test_searcher_material_01() {
+        // the hanging queen is taken, the defended knight is not (for a rook)
+        let mut char = Searcher::from_evaluator(Material, &[2, 2, 4]);
+        let mut board = Board::parse_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        let eval_mov = char.get_eval_move(&mut board);
+        assert_eq!(move_to_user(&board, &eval_mov.mov), "d2d5");
+        let mut board = Board::parse_fen("4k3/8/4p3/3n4/8/8/3R4/4K3 w - - 0 1");
+        let eval_mov = char.get_eval_move(&mut board);
+        assert_ne!(move_to_user(&board, &eval_mov.mov), "d2d5");
+        assert_eq!(char.explain_eval(&board).total, char.get_static_eval(&board));
+    }
+
+    #[test]
+    fn test_searcher_contempt_caches_01() {
+        // searches for both sides with contempt keep each other's evals
+        let mut char = Searcher::from_evaluator(Material, &[2, 2, 2]);
+        char.set_contempt(0.5);
+        let mut board = Board::parse_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        char.get_eval_moves(&mut board);
+        board.make_move(&move_to_board(&board, "d2d5"));
+        let hash = board.hash;
+        assert!(char.caches[1].is_evaluated(hash));
+        char.get_eval_moves(&mut board);
+        assert!(char.caches[1].is_evaluated(hash) && char.get_draw_score() == 0.5);
+    }
+}
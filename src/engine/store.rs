@@ -0,0 +1,312 @@
+use std::{cmp::{min, Reverse}, collections::{BinaryHeap, HashMap}, fs::{self, OpenOptions}, io::{self, Write}, iter};
+use crate::board::{board::Board, zobrist::Zobrist};
+use super::{book::Book, eval::{Eval, EvalMov}};
+
+// another set of keys: its hash tells the position apart from the one with the colliding Zobrist hash
+static VERIFICATION: Zobrist = Zobrist::new(!Zobrist::SEED);
+
+// Stored analysis of a position, 24 little-endian bytes on disk:
+// key (8 bytes), check (4), move (2), depth (1), mate_in (1), score (4), generation (4)
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StoreEntry {
+    // Zobrist hash of the position mixed with the character salt (see AnalysisStore::get)
+    pub key: u64,
+    // lower bits of the verification hash
+    pub check: u32,
+    // best move, polyglot encoded (see Book::encode_move)
+    pub mov: u16,
+    // static half-depth of the search
+    pub depth: i8,
+    pub mate_in: i8,
+    pub score: f32,
+    // session that stored the entry, newer sessions win over older ones
+    pub generation: u32
+}
+
+// Persistent analysis: best moves and evaluations of deep root searches, kept between sessions.
+// New entries are appended to the file as they come, the file is rewritten only by compact().
+// Evaluations depend on weights and search settings, so every character mixes its own salt into the keys
+// and several characters may share the same file.
+// Keys are position hashes only, so positions where the game history matters (repetitions, fifty moves) are left out.
+pub struct AnalysisStore {
+    path: String,
+    entries: HashMap<u64, StoreEntry>,
+    // keys by depth and generation, the worst entry on top; items of replaced entries are skipped when they come up
+    worst: BinaryHeap<Reverse<(i8, u32, u64)>>,
+    // entries written to the file, including the outdated ones
+    file_entries: usize,
+    // entries kept by compact(), the rest are dropped shallowest and oldest first
+    pub max_entries: usize,
+    // shallower searches are not worth storing
+    pub min_depth: i8,
+    generation: u32
+}
+
+impl AnalysisStore {
+    pub const ENTRY_SIZE: usize = 24;
+    pub const DEFAULT_MAX_ENTRIES: usize = 1 << 20;
+    pub const DEFAULT_MIN_DEPTH: i8 = 4;
+    // half-moves without captures and pawn moves, the search of a position with more of them may run into the fifty-move rule
+    pub const MAX_HMW: u8 = 80;
+
+    // read the store file (missing file is an empty store); file with too many outdated entries is compacted
+    pub fn open(path: &str, max_entries: usize) -> io::Result<Self> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::default(),
+            Err(e) => return Err(e)
+        };
+        if bytes.len() % Self::ENTRY_SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} is not an analysis store: size is not divisible by {}", path, Self::ENTRY_SIZE)));
+        }
+        let mut store = Self {
+            path: path.to_string(),
+            entries: HashMap::new(),
+            worst: BinaryHeap::new(),
+            file_entries: bytes.len() / Self::ENTRY_SIZE,
+            max_entries,
+            min_depth: Self::DEFAULT_MIN_DEPTH,
+            generation: 0
+        };
+        for chunk in bytes.chunks_exact(Self::ENTRY_SIZE) {
+            let entry = Self::from_bytes(chunk);
+            store.generation = store.generation.max(entry.generation + 1);
+            // later entries of the same depth are newer
+            if store.entries.get(&entry.key).is_none_or(|old| entry.depth >= old.depth) {
+                store.entries.insert(entry.key, entry);
+            }
+        }
+        if store.file_entries > 2 * store.entries.len() || store.entries.len() > max_entries {
+            store.compact()?;
+        } else {
+            store.index();
+        }
+        Ok(store)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn file_len(&self) -> usize {
+        self.file_entries
+    }
+
+    // Stored best move of the position, if it was searched at least as deep as asked
+    // and neither the position nor the one after the move depends on the game history
+    pub fn get(&self, board: &mut Board, salt: u64, depth: i8) -> Option<EvalMov> {
+        let entry = self.entries.get(&(board.hash ^ salt))?;
+        if entry.check != Self::get_check(board) || entry.depth < depth || Self::depends_on_history(board) {
+            return None;
+        }
+        let legals = board.get_legal_moves(None, Some(true));
+        let mov = Book::decode_move(board, &legals, entry.mov)?;
+        board.make_move(&mov);
+        let repeated = Self::depends_on_history(board);
+        board.revert_move();
+        (!repeated).then_some(EvalMov { mov, eval: Eval::new(entry.score, entry.mate_in) })
+    }
+
+    // Store the best move of the search (made from the given position) and append it to the file.
+    // Deeper entries are never replaced by shallower ones, a full store only takes entries better than its worst one.
+    pub fn insert(&mut self, board: &Board, salt: u64, eval_mov: &EvalMov, depth: i8) -> io::Result<()> {
+        if depth < self.min_depth || Self::depends_on_history(board) {
+            return Ok(());
+        }
+        let entry = StoreEntry {
+            key: board.hash ^ salt,
+            check: Self::get_check(board),
+            mov: Book::encode_move(board, &eval_mov.mov),
+            depth,
+            mate_in: eval_mov.eval.mate_in,
+            score: eval_mov.eval.score,
+            generation: self.generation
+        };
+        match self.entries.get(&entry.key) {
+            Some(old) if old.depth > depth => return Ok(()),
+            Some(_) => (),
+            None if self.entries.len() >= self.max_entries => {
+                let Some(worst) = self.get_worst() else { return Ok(()) };
+                if (worst.depth, worst.generation) >= (depth, self.generation) {
+                    return Ok(());
+                }
+                self.entries.remove(&worst.key);
+                self.worst.pop();
+            },
+            None => ()
+        }
+        self.entries.insert(entry.key, entry);
+        self.worst.push(Reverse((entry.depth, entry.generation, entry.key)));
+        if self.worst.len() > 2 * self.entries.len() {
+            self.index();
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&Self::to_bytes(&entry))?;
+        self.file_entries += 1;
+        Ok(())
+    }
+
+    // Rewrite the file with one entry per position, at most max_entries of them (the deepest and newest ones).
+    // The new file is written aside and renamed, so the old one stays whole if anything goes wrong.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let mut entries: Vec<StoreEntry> = self.entries.values().copied().collect();
+        entries.sort_by_key(|e| (Reverse(e.depth), Reverse(e.generation), e.key));
+        entries.truncate(self.max_entries);
+        let mut bytes: Vec<u8> = Vec::with_capacity(entries.len() * Self::ENTRY_SIZE);
+        for entry in &entries {
+            bytes.extend_from_slice(&Self::to_bytes(entry));
+        }
+        let temp_path = format!("{}.tmp", self.path);
+        fs::write(&temp_path, bytes)?;
+        fs::rename(&temp_path, &self.path)?;
+        self.entries = entries.into_iter().map(|e| (e.key, e)).collect();
+        self.file_entries = self.entries.len();
+        self.index();
+        Ok(())
+    }
+
+    // Position of the game with a repeated one since the last capture or pawn move, or close to the fifty-move rule:
+    // its search is not the search of the position alone
+    fn depends_on_history(board: &Board) -> bool {
+        if board.hmw >= Self::MAX_HMW {
+            return true;
+        }
+        let reversible = &board.history[board.history.len() - min(board.hmw as usize, board.history.len())..];
+        let mut hashes: Vec<u64> = reversible.iter().map(|bmov| bmov.hash).chain(iter::once(board.hash)).collect();
+        hashes.sort_unstable();
+        hashes.windows(2).any(|pair| pair[0] == pair[1])
+    }
+
+    // worst entry (the first to drop from the full store), items of replaced entries are dropped on the way
+    fn get_worst(&mut self) -> Option<StoreEntry> {
+        while let Some(Reverse((depth, generation, key))) = self.worst.peek() {
+            match self.entries.get(key) {
+                Some(entry) if entry.depth == *depth && entry.generation == *generation => return Some(*entry),
+                _ => { self.worst.pop(); }
+            }
+        }
+        None
+    }
+
+    // rebuild the heap of the worst entries from the entries
+    fn index(&mut self) {
+        self.worst = self.entries.values().map(|e| Reverse((e.depth, e.generation, e.key))).collect();
+    }
+
+    fn get_check(board: &Board) -> u32 {
+        VERIFICATION.hash(board) as u32
+    }
+
+    fn to_bytes(entry: &StoreEntry) -> [u8; Self::ENTRY_SIZE] {
+        let mut bytes = [0; Self::ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&entry.key.to_le_bytes());
+        bytes[8..12].copy_from_slice(&entry.check.to_le_bytes());
+        bytes[12..14].copy_from_slice(&entry.mov.to_le_bytes());
+        bytes[14] = entry.depth as u8;
+        bytes[15] = entry.mate_in as u8;
+        bytes[16..20].copy_from_slice(&entry.score.to_le_bytes());
+        bytes[20..24].copy_from_slice(&entry.generation.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(chunk: &[u8]) -> StoreEntry {
+        StoreEntry {
+            key: u64::from_le_bytes(chunk[0..8].try_into().unwrap()),
+            check: u32::from_le_bytes(chunk[8..12].try_into().unwrap()),
+            mov: u16::from_le_bytes(chunk[12..14].try_into().unwrap()),
+            depth: chunk[14] as i8,
+            mate_in: chunk[15] as i8,
+            score: f32::from_le_bytes(chunk[16..20].try_into().unwrap()),
+            generation: u32::from_le_bytes(chunk[20..24].try_into().unwrap())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::{board::board::Board, engine::eval::{Eval, EvalMov}, utils::utils::{move_to_board, move_to_user}};
+    use super::AnalysisStore;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(name);
+        let path = path.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn eval_mov(board: &Board, mov: &str, score: f32) -> EvalMov {
+        EvalMov { mov: move_to_board(board, mov), eval: Eval::new(score, 0) }
+    }
+
+    #[test]
+    fn test_store_insert_open_01() {
+        let path = temp_path("cce_test_store_insert_open_01.bin");
+        let mut board = Board::new();
+        let mut store = AnalysisStore::open(&path, 16).unwrap();
+        store.insert(&board, 1, &eval_mov(&board, "e2e4", 0.3), 6).unwrap();
+        // too shallow to be stored
+        board.make_move(&move_to_board(&board, "e2e4"));
+        store.insert(&board, 1, &eval_mov(&board, "e7e5", -0.3), 2).unwrap();
+        board.revert_move();
+
+        let reopened = AnalysisStore::open(&path, 16).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.len(), 1);
+        let eval_mov = reopened.get(&mut board, 1, 6).unwrap();
+        assert_eq!(move_to_user(&board, &eval_mov.mov), "e2e4");
+        assert!(eval_mov.eval == Eval::new(0.3, 0));
+        // deeper search is asked, or another character
+        assert!(reopened.get(&mut board, 1, 8).is_none());
+        assert!(reopened.get(&mut board, 2, 6).is_none());
+    }
+
+    #[test]
+    fn test_store_compact_01() {
+        let path = temp_path("cce_test_store_compact_01.bin");
+        let mut board = Board::new();
+        let mut store = AnalysisStore::open(&path, 2).unwrap();
+        // the same position goes deeper, shallower result doesn't replace it
+        store.insert(&board, 0, &eval_mov(&board, "e2e4", 0.3), 4).unwrap();
+        store.insert(&board, 0, &eval_mov(&board, "d2d4", 0.4), 6).unwrap();
+        store.insert(&board, 0, &eval_mov(&board, "c2c4", 0.2), 4).unwrap();
+        assert_eq!((store.len(), store.file_len()), (1, 2));
+        // full store drops the shallowest entry
+        for (mov, depth) in [("e2e4", 4), ("d2d4", 8)] {
+            board.make_move(&move_to_board(&board, mov));
+            store.insert(&board, 0, &eval_mov(&board, "g8f6", 0.0), depth).unwrap();
+            board.revert_move();
+        }
+        assert_eq!(store.len(), 2);
+        assert!(store.get(&mut board, 0, 6).is_some());
+        store.compact().unwrap();
+        assert_eq!(store.file_len(), 2);
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, 2 * AnalysisStore::ENTRY_SIZE);
+        let reopened = AnalysisStore::open(&path, 2).unwrap();
+        fs::remove_file(&path).unwrap();
+        let mov = reopened.get(&mut board, 0, 6).unwrap().mov;
+        assert_eq!(move_to_user(&board, &mov), "d2d4");
+    }
+
+    #[test]
+    fn test_store_history_01() {
+        let path = temp_path("cce_test_store_history_01.bin");
+        let mut store = AnalysisStore::open(&path, 16).unwrap();
+        let mut board = Board::new();
+        for mov in ["g1f3", "g8f6", "f3g1"] {
+            board.make_move(&move_to_board(&board, mov));
+        }
+        // the stored move repeats the starting position of this game, but not of the same position without history
+        store.insert(&board, 0, &eval_mov(&board, "f6g8", 0.0), 6).unwrap();
+        assert!(store.get(&mut board, 0, 6).is_none());
+        let mut fresh = Board::parse_fen("rnbqkb1r/pppppppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 3 2");
+        assert!(store.get(&mut fresh, 0, 6).is_some());
+        // repeated position and the one close to the fifty-move rule are not stored
+        board.make_move(&move_to_board(&board, "f6g8"));
+        store.insert(&board, 0, &eval_mov(&board, "e2e4", 0.3), 6).unwrap();
+        let board = Board::parse_fen("4k3/8/8/8/8/8/3R4/4K3 w - - 90 80");
+        store.insert(&board, 0, &eval_mov(&board, "d2d7", 0.0), 6).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(store.len(), 1);
+    }
+}
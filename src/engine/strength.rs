@@ -0,0 +1,149 @@
+use rand::{rngs::StdRng, Rng};
+use crate::board::zobrist::Zobrist;
+use super::eval::{Eval, EvalMov};
+
+// Playing strength below the full one, for sparring: weaker levels search less, see the position a bit blurred
+// and don't always pick the best move. Their mistakes are still the ones a human would make: a close second best move,
+// or the best move of a shallow search that misses the opponent's reply.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Strength {
+    pub elo: u16,
+    // caps of static, mixed and dynamic half-depths (character's own depths are used if they are lower)
+    pub depths: [i8; 3],
+    // positions searched per move, the rest of the tree is evaluated statically
+    pub max_nodes: u64,
+    // standard deviation of the static eval noise, pawns
+    pub noise: f32,
+    // softmax temperature over move scores, pawns: moves that are worse by this much are e (2.718) times less likely
+    pub temperature: f32,
+    // chance to play the best move of a shallow search instead
+    pub blunder_chance: f32,
+    // shallow search move is not played if it loses more than that (pawns), too obvious even for this level
+    pub blunder_margin: f32
+}
+
+impl Strength {
+    pub const MIN_ELO: u16 = 800;
+    pub const MAX_ELO: u16 = 2400;
+    // half-depths of the shallow search: own move and direct recaptures only
+    pub const BLUNDER_DEPTHS: [i8; 3] = [1, 1, 2];
+    // softmax score of a forced mate, so mates are always preferred and mated moves are always avoided
+    const MATE_SCORE: f32 = 1000.0;
+
+    // levels in between are interpolated, MAX_ELO and higher is almost full strength (only the depth caps stay)
+    pub fn from_elo(elo: u16) -> Self {
+        let t = (elo.clamp(Self::MIN_ELO, Self::MAX_ELO) - Self::MIN_ELO) as f32 / (Self::MAX_ELO - Self::MIN_ELO) as f32;
+        let static_half_depth = 2 + (t * 2.0).round() as i8;
+        Self {
+            elo,
+            depths: [static_half_depth, static_half_depth + 2, 4 + (t * 12.0).round() as i8],
+            max_nodes: (1000.0 * 100f32.powf(t)) as u64,
+            noise: 0.4 * (1.0 - t),
+            temperature: 0.5 * (1.0 - t),
+            blunder_chance: 0.15 * (1.0 - t),
+            blunder_margin: 0.5 + 2.5 * (1.0 - t)
+        }
+    }
+
+    // Eval noise of the position: the same for the same key (position hash mixed with some seed), so the search
+    // stays consistent with its cache. Sum of 4 uniform values is close enough to the normal distribution.
+    pub fn get_noise(&self, key: u64) -> f32 {
+        if self.noise == 0.0 {
+            return 0.0;
+        }
+        let x = Zobrist::mix(key);
+        let sum: f32 = (0..4).map(|i| ((x >> (i * 16)) & 0xffff) as f32 / 65535.0).sum();
+        // variance of the sum is 4/12
+        (sum - 2.0) * 3f32.sqrt() * self.noise
+    }
+
+    // move score for softmax from the perspective of the player to move
+    pub fn get_score(eval: &Eval, white: bool) -> f32 {
+        let score = match eval.mate_in {
+            0 => eval.score,
+            mate_in if mate_in > 0 => Self::MATE_SCORE - mate_in as f32,
+            mate_in => -Self::MATE_SCORE - mate_in as f32
+        };
+        if white { score } else { -score }
+    }
+
+    // Index of the move to play, probability is softmax of move scores with the temperature.
+    // Zero temperature means the best move (evals are expected to be sorted from the best one).
+    pub fn choose(&self, evals: &[EvalMov], white: bool, rng: &mut StdRng) -> usize {
+        if evals.len() < 2 || self.temperature <= 0.0 {
+            return 0;
+        }
+        let best = Self::get_score(&evals[0].eval, white);
+        let weights: Vec<f32> = evals.iter().map(|e| ((Self::get_score(&e.eval, white) - best) / self.temperature).exp()).collect();
+        let mut target = rng.gen_range(0.0..weights.iter().sum::<f32>());
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return i;
+            }
+            target -= weight;
+        }
+        0
+    }
+
+    // shallow search move is fine to blunder with, if the full search doesn't find it too bad
+    pub fn is_plausible(&self, best: &Eval, eval: &Eval, white: bool) -> bool {
+        Self::get_score(best, white) - Self::get_score(eval, white) <= self.blunder_margin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use crate::{board::{mov::Mov, coord::Coord}, engine::eval::{Eval, EvalMov}};
+    use super::Strength;
+
+    fn evals(scores: &[(f32, i8)]) -> Vec<EvalMov> {
+        scores.iter().map(|(score, mate_in)| EvalMov { mov: Mov { data: 0, from: Coord::new(0, 0), to: Coord::new(0, 0) }, eval: Eval::new(*score, *mate_in) }).collect()
+    }
+
+    #[test]
+    fn test_strength_from_elo_01() {
+        let weak = Strength::from_elo(800);
+        let strong = Strength::from_elo(2400);
+        assert!(weak.depths[0] < strong.depths[0] && weak.depths[2] < strong.depths[2]);
+        assert!(weak.max_nodes < strong.max_nodes);
+        assert!(weak.noise > 0.0 && weak.temperature > 0.0 && weak.blunder_chance > 0.0);
+        assert_eq!(strong.noise, 0.0);
+        assert_eq!(strong.temperature, 0.0);
+        assert_eq!(strong.blunder_chance, 0.0);
+        // out of range is clamped
+        assert_eq!(Strength::from_elo(100).depths, weak.depths);
+    }
+
+    #[test]
+    fn test_strength_noise_01() {
+        let strength = Strength::from_elo(1200);
+        assert_eq!(strength.get_noise(12345), strength.get_noise(12345));
+        let noises: Vec<f32> = (0..1000u64).map(|key| strength.get_noise(key)).collect();
+        let mean = noises.iter().sum::<f32>() / noises.len() as f32;
+        let deviation = (noises.iter().map(|n| (n - mean) * (n - mean)).sum::<f32>() / noises.len() as f32).sqrt();
+        assert!(mean.abs() < 0.05);
+        assert!((deviation - strength.noise).abs() < 0.05);
+        assert_eq!(Strength::from_elo(2400).get_noise(12345), 0.0);
+    }
+
+    #[test]
+    fn test_strength_choose_01() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let strength = Strength::from_elo(800);
+        // white to move: close second move is played sometimes, much worse one and mated one are (almost) never
+        let evals = evals(&[(1.0, 0), (0.9, 0), (-5.0, 0), (0.0, -1)]);
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[strength.choose(&evals, true, &mut rng)] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[1] > 100);
+        assert_eq!(counts[2] + counts[3], 0);
+        // mate is always found, black perspective
+        let evals = self::evals(&[(0.0, -3), (-9.0, 0)]);
+        assert!((0..100).all(|_| strength.choose(&evals, false, &mut rng) == 0));
+        // full strength is deterministic
+        let evals = self::evals(&[(1.0, 0), (0.99, 0)]);
+        assert!((0..100).all(|_| Strength::from_elo(2400).choose(&evals, true, &mut rng) == 0));
+    }
+}
@@ -0,0 +1,1188 @@
+use std::{cmp::{max, min, Reverse}, collections::HashMap, fs, path::Path};
+use crate::board::board::Board;
+use super::eval::{Eval, EvalMov};
+
+// Syzygy endgame tablebases probing (WDL and DTZ files), see https://github.com/syzygy1/tb
+// Indexing and decompression follow the original probing code, squares are numbered as y * 8 + x (a1 = 0, h8 = 63),
+// pieces are stored in tables as 1..6 for white p, n, b, r, q, k and 9..14 for black ones.
+//
+// WDL values are from the side to move perspective:
+// -2 - loss, -1 - blessed loss (loss, but 50-move rule saves), 0 - draw, 1 - cursed win (win, but 50-move rule spoils), 2 - win
+// DTZ values are the number of half-moves to the next zeroing move (capture or pawn move) with the sign of WDL.
+
+#[derive(Clone, Copy, PartialEq)]
+enum TbType {
+    Wdl,
+    Dtz
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ProbeState {
+    Ok,
+    // table is missing or corrupted
+    Fail,
+    // DTZ table stores the other side to move
+    ChangeStm,
+    // best move is a zeroing one, table value can't be trusted
+    ZeroingBestMove
+}
+
+// table flags
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+const MAGIC_WDL: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const MAGIC_DTZ: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+// 7-men tables are the largest ones that exist
+const TB_PIECES: usize = 7;
+
+// low level indexing information to access compressed table data (offsets are from the file start)
+#[derive(Clone, Default)]
+struct PairsData {
+    flags: u8,
+    max_sym_len: u8,
+    min_sym_len: u8,
+    num_blocks: u32,
+    sizeof_block: u64,
+    span: u64,
+    lowest_sym: usize,
+    btree: usize,
+    block_length: usize,
+    block_length_size: u32,
+    sparse_index: usize,
+    sparse_index_size: u64,
+    data: usize,
+    // base64[l - min_sym_len] is the 64-bit padded lowest symbol of length l
+    base64: Vec<u64>,
+    // number of values (-1) represented by a given symbol
+    symlen: Vec<u8>,
+    // pieces order defines the groups
+    pieces: [u8; TB_PIECES],
+    group_idx: [u64; TB_PIECES + 1],
+    group_len: [usize; TB_PIECES + 1],
+    // file offsets of dtz maps for win, loss, cursed win, blessed loss
+    map_idx: [usize; 4]
+}
+
+// table file content with per side (WDL only) and per leading pawn file (a-d, pawnful tables only) indexing data
+struct TableData {
+    bytes: Vec<u8>,
+    items: Vec<Vec<PairsData>>
+}
+
+// material of the table, as in file name: KRPvKR (first side is white in the table)
+struct Material {
+    key: String,
+    key2: String,
+    piece_count: usize,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    // [leading color, other color]
+    pawn_count: [usize; 2]
+}
+
+struct Table {
+    material: Material,
+    // None - not loaded yet, Some(None) - missing or corrupted file
+    wdl: Option<Option<TableData>>,
+    dtz: Option<Option<TableData>>
+}
+
+// precomputed indexing constants
+struct Indexes {
+    map_pawns: [usize; 64],
+    map_b1h1h7: [usize; 64],
+    map_a1d1d4: [usize; 64],
+    map_kk: [[usize; 64]; 10],
+    binomial: [[u64; 64]; TB_PIECES],
+    lead_pawn_idx: [[u64; 64]; TB_PIECES],
+    lead_pawns_size: [[u64; 4]; TB_PIECES]
+}
+
+pub struct Tablebase {
+    // directories to search tables in
+    paths: Vec<String>,
+    // positions with more pieces (including kings) are never probed
+    max_pieces: usize,
+    tables: Vec<Table>,
+    // table index by material code of both colors
+    keys: HashMap<String, usize>,
+    indexes: Indexes
+}
+
+impl Tablebase {
+    // score of a tablebase win (minus distance to zeroing move), far from any material score
+    pub const WIN_SCORE: f32 = 10000.0;
+    // score of a win (loss) that is spoiled (saved) by the 50-move rule
+    pub const CURSED_SCORE: f32 = 0.01;
+    pub const DEFAULT_MAX_PIECES: u8 = 6;
+
+    // paths are separated by ':' or ';' (as in UCI SyzygyPath), tables are opened on the first access
+    pub fn new(path: &str, max_pieces: u8) -> Self {
+        let mut tablebase = Self {
+            paths: Vec::default(),
+            max_pieces: min(max_pieces as usize, TB_PIECES),
+            tables: Vec::default(),
+            keys: HashMap::new(),
+            indexes: Indexes::init()
+        };
+        for dir in path.split([':', ';']).filter(|dir| !dir.is_empty()) {
+            tablebase.paths.push(dir.to_string());
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Some(code) = name.strip_suffix(".rtbw") {
+                    tablebase.add_table(code);
+                }
+            }
+        }
+        tablebase
+    }
+
+    // amount of WDL tables found
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    // the largest amount of pieces that may be probed
+    pub fn max_pieces(&self) -> usize {
+        min(self.max_pieces, self.tables.iter().map(|table| table.material.piece_count).max().unwrap_or(2))
+    }
+
+    // true if position can be probed at all (castling positions are not stored in tables)
+    pub fn is_probable(&self, board: &Board) -> bool {
+        board.castling == 0 && Self::count_pieces(board) <= self.max_pieces()
+    }
+
+    // WDL score of the position from the side to move perspective
+    pub fn probe_wdl(&mut self, board: &mut Board) -> Option<i32> {
+        if !self.is_probable(board) {
+            return None;
+        }
+        let mut state = ProbeState::Ok;
+        let wdl = self.search(board, false, &mut state);
+        if state == ProbeState::Fail {
+            return None;
+        }
+        Some(wdl)
+    }
+
+    // DTZ score of the position from the side to move perspective (0 for draws)
+    pub fn probe_dtz(&mut self, board: &mut Board) -> Option<i32> {
+        if !self.is_probable(board) {
+            return None;
+        }
+        let mut state = ProbeState::Ok;
+        let dtz = self.probe_dtz_state(board, &mut state);
+        if state == ProbeState::Fail {
+            return None;
+        }
+        Some(dtz)
+    }
+
+    // static evaluation (from white perspective) of the position if it's in tablebases
+    pub fn get_eval(&mut self, board: &mut Board) -> Option<Eval> {
+        let wdl = self.probe_wdl(board)?;
+        let score = Self::wdl_to_score(wdl);
+        Some(Eval::new(if board.white_to_move { score } else { -score }, 0))
+    }
+
+    // All legal moves with their tablebase evaluation (from white perspective), the best first.
+    // Winning moves are ranked by DTZ, so the engine will make progress instead of shuffling pieces.
+    pub fn get_eval_moves(&mut self, board: &mut Board) -> Option<Vec<EvalMov>> {
+        if !self.is_probable(board) {
+            return None;
+        }
+        let hmw = board.hmw as i32;
+        let moves = board.get_legal_moves(None, Some(true));
+        let mut evals: Vec<EvalMov> = Vec::default();
+        for mov in &moves {
+            let mut state = ProbeState::Ok;
+            board.make_move(mov);
+            let mut dtz = if board.hmw == 0 {
+                Self::dtz_before_zeroing(-self.search(board, false, &mut state))
+            } else {
+                let dtz = -self.probe_dtz_state(board, &mut state);
+                dtz + dtz.signum()
+            };
+            // mate is the fastest possible win
+            if dtz == 2 && self.is_checkmate(board) {
+                dtz = 1;
+            }
+            board.revert_move();
+            if state == ProbeState::Fail {
+                return None;
+            }
+            let score = if dtz > 0 {
+                if dtz + hmw <= 100 { Self::WIN_SCORE - dtz as f32 } else { Self::CURSED_SCORE }
+            } else if dtz < 0 {
+                if hmw - dtz <= 100 { -Self::WIN_SCORE - dtz as f32 } else { -Self::CURSED_SCORE }
+            } else {
+                0.0
+            };
+            evals.push(EvalMov{ mov: *mov, eval: Eval::new(if board.white_to_move { score } else { -score }, 0) });
+        }
+        if board.white_to_move {
+            evals.sort_by_key(|eval| Reverse(eval.eval));
+        } else {
+            evals.sort_by_key(|eval| eval.eval);
+        }
+        Some(evals)
+    }
+
+    fn wdl_to_score(wdl: i32) -> f32 {
+        match wdl {
+            2 => Self::WIN_SCORE,
+            1 => Self::CURSED_SCORE,
+            -1 => -Self::CURSED_SCORE,
+            -2 => -Self::WIN_SCORE,
+            _ => 0.0
+        }
+    }
+
+    // DTZ tables don't store scores for zeroing moves, but it's possible to recover the dtz of the previous move by WDL
+    fn dtz_before_zeroing(wdl: i32) -> i32 {
+        match wdl {
+            2 => 1,
+            1 => 101,
+            -1 => -101,
+            -2 => -1,
+            _ => 0
+        }
+    }
+
+    fn count_pieces(board: &Board) -> usize {
+        board.field.iter().flatten().filter(|piece| **piece > 1).count()
+    }
+
+    fn is_checkmate(&self, board: &mut Board) -> bool {
+        let king = *board.get_current_king_coord(true);
+        board.is_under_attack(king.y(), king.x(), !board.white_to_move, [true; 5]) && board.get_legal_moves(None, None).is_empty()
+    }
+
+    // Winning captures (and pawn moves when checking zeroing moves for DTZ) are "don't care" values in tables,
+    // so they must be searched and the best result of them and the table value is the correct one.
+    fn search(&mut self, board: &mut Board, check_zeroing_moves: bool, state: &mut ProbeState) -> i32 {
+        let moves = board.get_legal_moves(None, None);
+        let mut best = -2;
+        let mut count = 0;
+        for mov in &moves {
+            let capture = board.ptpv(mov.data) > 0;
+            let pawn = board.field[mov.from.y() as usize][mov.from.x() as usize] & 254 == board.gpl(&'p');
+            if !capture && (!check_zeroing_moves || !pawn) {
+                continue;
+            }
+            count += 1;
+            board.make_move(mov);
+            let value = -self.search(board, false, state);
+            board.revert_move();
+            if *state == ProbeState::Fail {
+                return 0;
+            }
+            if value > best {
+                best = value;
+                if value >= 2 {
+                    *state = ProbeState::ZeroingBestMove;
+                    return value;
+                }
+            }
+        }
+        // all legal moves were searched, the table may store a wrong value (e.g. for positions with en passant)
+        let no_more_moves = count > 0 && count == moves.len();
+        let value = if no_more_moves {
+            best
+        } else {
+            let value = self.probe_table(board, TbType::Wdl, 0, state);
+            if *state == ProbeState::Fail {
+                return 0;
+            }
+            value
+        };
+        if best >= value {
+            *state = if best > 0 || no_more_moves { ProbeState::ZeroingBestMove } else { ProbeState::Ok };
+            return best;
+        }
+        *state = ProbeState::Ok;
+        value
+    }
+
+    fn probe_dtz_state(&mut self, board: &mut Board, state: &mut ProbeState) -> i32 {
+        *state = ProbeState::Ok;
+        let wdl = self.search(board, true, state);
+        // DTZ tables don't store draws
+        if *state == ProbeState::Fail || wdl == 0 {
+            return 0;
+        }
+        if *state == ProbeState::ZeroingBestMove {
+            return Self::dtz_before_zeroing(wdl);
+        }
+        let dtz = self.probe_table(board, TbType::Dtz, wdl, state);
+        if *state == ProbeState::Fail {
+            return 0;
+        }
+        if *state != ProbeState::ChangeStm {
+            return (dtz + if wdl == 1 || wdl == -1 { 100 } else { 0 }) * wdl.signum();
+        }
+        // DTZ table stores the other side to move, so do a 1-ply search and find the move that minimizes DTZ
+        let mut min_dtz = 0xFFFF;
+        let moves = board.get_legal_moves(None, None);
+        for mov in &moves {
+            let zeroing = board.ptpv(mov.data) > 0 || board.field[mov.from.y() as usize][mov.from.x() as usize] & 254 == board.gpl(&'p');
+            board.make_move(mov);
+            let mut dtz = if zeroing {
+                -Self::dtz_before_zeroing(self.search(board, false, state))
+            } else {
+                -self.probe_dtz_state(board, state)
+            };
+            if dtz == 1 && self.is_checkmate(board) {
+                min_dtz = 1;
+            }
+            if !zeroing {
+                dtz += dtz.signum();
+            }
+            if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                min_dtz = dtz;
+            }
+            board.revert_move();
+            if *state == ProbeState::Fail {
+                return 0;
+            }
+        }
+        // no legal moves - position is a mate
+        if min_dtz == 0xFFFF { -1 } else { min_dtz }
+    }
+
+    fn probe_table(&mut self, board: &Board, kind: TbType, wdl: i32, state: &mut ProbeState) -> i32 {
+        let pieces = Self::get_pieces(board);
+        // KvK
+        if pieces.len() == 2 {
+            return 0;
+        }
+        let code = Self::get_code(&pieces);
+        let index = match self.keys.get(&code) {
+            Some(index) => *index,
+            None => {
+                *state = ProbeState::Fail;
+                return 0;
+            }
+        };
+        if !self.load(index, kind) {
+            *state = ProbeState::Fail;
+            return 0;
+        }
+        let table = &self.tables[index];
+        let data = match kind {
+            TbType::Wdl => table.wdl.as_ref(),
+            TbType::Dtz => table.dtz.as_ref()
+        }.unwrap().as_ref().unwrap();
+        self.do_probe_table(&table.material, data, kind, &pieces, &code, board.white_to_move, wdl, state)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn do_probe_table(&self, material: &Material, data: &TableData, kind: TbType, position: &[(usize, u8)], code: &str, white_to_move: bool, wdl: i32, state: &mut ProbeState) -> i32 {
+        let ix = &self.indexes;
+        let mut squares = [0usize; TB_PIECES];
+        let mut pieces = [0u8; TB_PIECES];
+        let mut size = 0;
+        let mut lead_pawns_cnt = 0;
+        let mut tb_file = 0;
+
+        // KRvK and KvKR are the same table, white is the stronger side in it, so flip colors and squares if necessary
+        // tables of symmetric material store only the white to move case
+        let symmetric_black_to_move = material.key == material.key2 && !white_to_move;
+        let black_stronger = code != material.key;
+        let flip = symmetric_black_to_move || black_stronger;
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip ^ !white_to_move) as usize;
+
+        // pawnful tables are split by the file of the leading pawn (the one closest to the edge, then with the lowest rank)
+        let mut lead_pawn = 0;
+        if material.has_pawns {
+            lead_pawn = data.items[0][0].pieces[0] ^ flip_color;
+            for (square, piece) in position {
+                if *piece == lead_pawn {
+                    squares[size] = square ^ flip_squares;
+                    size += 1;
+                }
+            }
+            lead_pawns_cnt = size;
+            let mut lead = 0;
+            for i in 1..lead_pawns_cnt {
+                if ix.map_pawns[squares[i]] > ix.map_pawns[squares[lead]] {
+                    lead = i;
+                }
+            }
+            squares.swap(0, lead);
+            tb_file = squares[0] & 7;
+            if tb_file > 3 {
+                tb_file = 7 - tb_file;
+            }
+        }
+
+        // DTZ tables are one-sided
+        let d_flags = data.items[0][tb_file].flags;
+        if kind == TbType::Dtz && (d_flags & FLAG_STM) as usize != stm && (material.key != material.key2 || material.has_pawns) {
+            *state = ProbeState::ChangeStm;
+            return 0;
+        }
+
+        for (square, piece) in position {
+            if !material.has_pawns || *piece != lead_pawn {
+                squares[size] = square ^ flip_squares;
+                pieces[size] = piece ^ flip_color;
+                size += 1;
+            }
+        }
+
+        let d = &data.items[stm % data.items.len()][tb_file];
+
+        // reorder pieces to the sequence stored in table
+        for i in lead_pawns_cnt..size - 1 {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        // the leading piece should be in the a1-d1-d4 triangle
+        if squares[0] & 7 > 3 {
+            for square in squares.iter_mut().take(size) {
+                *square ^= 7;
+            }
+        }
+
+        let mut idx: u64;
+        if material.has_pawns {
+            idx = ix.lead_pawn_idx[lead_pawns_cnt][squares[0]];
+            squares[1..lead_pawns_cnt].sort_by_key(|square| ix.map_pawns[*square]);
+            for (i, square) in squares.iter().enumerate().take(lead_pawns_cnt).skip(1) {
+                idx += ix.binomial[i][ix.map_pawns[*square]];
+            }
+        } else {
+            // the leading piece should be below the 5th rank
+            if squares[0] >> 3 > 3 {
+                for square in squares.iter_mut().take(size) {
+                    *square ^= 56;
+                }
+            }
+            // the first piece of the leading group that is not on the a1-h8 diagonal should be below it
+            for i in 0..d.group_len[0] {
+                let off = Self::off_a1h8(squares[i]);
+                if off == 0 {
+                    continue;
+                }
+                if off > 0 {
+                    for square in squares.iter_mut().take(size).skip(i) {
+                        *square = ((*square >> 3) | (*square << 3)) & 63;
+                    }
+                }
+                break;
+            }
+            if material.has_unique_pieces {
+                let adjust1 = (squares[1] > squares[0]) as usize;
+                let adjust2 = (squares[2] > squares[0]) as usize + (squares[2] > squares[1]) as usize;
+                idx = if Self::off_a1h8(squares[0]) != 0 {
+                    ((ix.map_a1d1d4[squares[0]] * 63 + (squares[1] - adjust1)) * 62 + squares[2] - adjust2) as u64
+                } else if Self::off_a1h8(squares[1]) != 0 {
+                    ((6 * 63 + (squares[0] >> 3) * 28 + ix.map_b1h1h7[squares[1]]) * 62 + squares[2] - adjust2) as u64
+                } else if Self::off_a1h8(squares[2]) != 0 {
+                    (6 * 63 * 62 + 4 * 28 * 62 + (squares[0] >> 3) * 7 * 28 + ((squares[1] >> 3) - adjust1) * 28 + ix.map_b1h1h7[squares[2]]) as u64
+                } else {
+                    (6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + (squares[0] >> 3) * 7 * 6 + ((squares[1] >> 3) - adjust1) * 6 + ((squares[2] >> 3) - adjust2)) as u64
+                };
+            } else {
+                idx = ix.map_kk[ix.map_a1d1d4[squares[0]]][squares[1]] as u64;
+            }
+        }
+
+        // encode the remaining groups: pieces of the group are sorted by square
+        idx *= d.group_idx[0];
+        let mut group_start = d.group_len[0];
+        let mut remaining_pawns = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] > 0 {
+            let group_end = group_start + d.group_len[next];
+            squares[group_start..group_end].sort_unstable();
+            let mut n: u64 = 0;
+            for i in 0..d.group_len[next] {
+                let square = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|s| square > **s).count();
+                n += ix.binomial[i + 1][square - adjust - if remaining_pawns { 8 } else { 0 }];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start = group_end;
+            next += 1;
+        }
+
+        let value = Self::decompress_pairs(&data.bytes, d, idx);
+        match kind {
+            TbType::Wdl => value - 2,
+            TbType::Dtz => Self::map_score(&data.bytes, &data.items[0][tb_file], value, wdl)
+        }
+    }
+
+    // DTZ values are stored by frequency of occurrence, so map them back, also convert moves to plies if necessary
+    fn map_score(bytes: &[u8], d: &PairsData, mut value: i32, wdl: i32) -> i32 {
+        // win, loss, cursed win, blessed loss
+        let map_index = match wdl {
+            2 => 0,
+            -2 => 1,
+            1 => 2,
+            _ => 3
+        };
+        if d.flags & FLAG_MAPPED > 0 {
+            let at = d.map_idx[map_index];
+            value = if d.flags & FLAG_WIDE > 0 {
+                read_u16_le(bytes, at + 2 * value as usize) as i32
+            } else {
+                bytes[at + value as usize] as i32
+            };
+        }
+        if (wdl == 2 && d.flags & FLAG_WIN_PLIES == 0) || (wdl == -2 && d.flags & FLAG_LOSS_PLIES == 0) || wdl == 1 || wdl == -1 {
+            value *= 2;
+        }
+        value + 1
+    }
+
+    // find the value at idx in the Huffman-compressed "recursive pairing" data
+    fn decompress_pairs(bytes: &[u8], d: &PairsData, idx: u64) -> i32 {
+        if d.flags & FLAG_SINGLE_VALUE > 0 {
+            return d.min_sym_len as i32;
+        }
+
+        // sparse index points to the block and the offset inside the block for every k * span + span / 2 value
+        let k = (idx / d.span) as usize;
+        let mut block = read_u32_le(bytes, d.sparse_index + 6 * k) as usize;
+        let mut offset = read_u16_le(bytes, d.sparse_index + 6 * k + 4) as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+
+        let block_length = |block: usize| read_u16_le(bytes, d.block_length + 2 * block) as i64;
+        while offset < 0 {
+            block -= 1;
+            offset += block_length(block) + 1;
+        }
+        while offset > block_length(block) {
+            offset -= block_length(block) + 1;
+            block += 1;
+        }
+
+        let mut ptr = d.data + block * d.sizeof_block as usize;
+        let mut buf64 = read_u64_be(bytes, ptr);
+        ptr += 8;
+        let mut buf64_size = 64;
+        let mut sym: usize;
+        loop {
+            let mut len = 0;
+            while buf64 < d.base64[len] {
+                len += 1;
+            }
+            sym = ((buf64 - d.base64[len]) >> (64 - len - d.min_sym_len as usize)) as usize;
+            sym += read_u16_le(bytes, d.lowest_sym + 2 * len) as usize;
+            if offset < d.symlen[sym] as i64 + 1 {
+                break;
+            }
+            offset -= d.symlen[sym] as i64 + 1;
+            len += d.min_sym_len as usize;
+            buf64 = if len < 64 { buf64 << len } else { 0 };
+            buf64_size -= len as i32;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                buf64 |= (read_u32_be(bytes, ptr) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // expand the symbol into the left and right children until reaching a leaf
+        while d.symlen[sym] > 0 {
+            let left = Self::btree_left(bytes, d, sym);
+            if offset < d.symlen[left] as i64 + 1 {
+                sym = left;
+            } else {
+                offset -= d.symlen[left] as i64 + 1;
+                sym = Self::btree_right(bytes, d, sym);
+            }
+        }
+        Self::btree_left(bytes, d, sym) as i32
+    }
+
+    fn btree_left(bytes: &[u8], d: &PairsData, sym: usize) -> usize {
+        let at = d.btree + 3 * sym;
+        (((bytes[at + 1] & 0xF) as usize) << 8) | bytes[at] as usize
+    }
+
+    fn btree_right(bytes: &[u8], d: &PairsData, sym: usize) -> usize {
+        let at = d.btree + 3 * sym;
+        ((bytes[at + 2] as usize) << 4) | (bytes[at + 1] >> 4) as usize
+    }
+
+    fn off_a1h8(square: usize) -> i32 {
+        (square >> 3) as i32 - (square & 7) as i32
+    }
+
+    // pieces of the position sorted by square as (square, table piece code)
+    fn get_pieces(board: &Board) -> Vec<(usize, u8)> {
+        let mut pieces = Vec::default();
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = board.field[y][x];
+                if piece > 1 {
+                    let c = board.gpr(&piece);
+                    let kind = match c.to_ascii_lowercase() {
+                        'p' => 1,
+                        'n' => 2,
+                        'b' => 3,
+                        'r' => 4,
+                        'q' => 5,
+                        _ => 6
+                    };
+                    pieces.push((y * 8 + x, if c.is_ascii_uppercase() { kind } else { kind | 8 }));
+                }
+            }
+        }
+        pieces
+    }
+
+    // material code of the position, white first: KRPvKR
+    fn get_code(pieces: &[(usize, u8)]) -> String {
+        let mut code = String::new();
+        for color in [0, 8] {
+            for (kind, c) in [(6, 'K'), (5, 'Q'), (4, 'R'), (3, 'B'), (2, 'N'), (1, 'P')] {
+                for _ in pieces.iter().filter(|(_, piece)| *piece == kind | color) {
+                    code.push(c);
+                }
+            }
+            if color == 0 {
+                code.push('v');
+            }
+        }
+        code
+    }
+
+    fn add_table(&mut self, code: &str) {
+        let sides: Vec<&str> = code.split('v').collect();
+        if sides.len() != 2 || !code.chars().all(|c| "KQRBNPv".contains(c)) || self.keys.contains_key(code) {
+            return;
+        }
+        let piece_count = code.len() - 1;
+        if piece_count > self.max_pieces || piece_count < 3 {
+            return;
+        }
+        let count = |side: &str, c: char| side.chars().filter(|p| *p == c).count();
+        let has_pawns = code.contains('P');
+        let has_unique_pieces = sides.iter().any(|side| "QRBNP".chars().any(|c| count(side, c) == 1));
+        // leading color is the one with less pawns (but at least one)
+        let (white_pawns, black_pawns) = (count(sides[0], 'P'), count(sides[1], 'P'));
+        let white_leads = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let pawn_count = if white_leads { [white_pawns, black_pawns] } else { [black_pawns, white_pawns] };
+        let key2 = format!("{}v{}", sides[1], sides[0]);
+        let index = self.tables.len();
+        self.keys.insert(code.to_string(), index);
+        self.keys.insert(key2.clone(), index);
+        self.tables.push(Table {
+            material: Material { key: code.to_string(), key2, piece_count, has_pawns, has_unique_pieces, pawn_count },
+            wdl: None,
+            dtz: None
+        });
+    }
+
+    // read the table file on the first access, return false if it's missing or corrupted
+    fn load(&mut self, index: usize, kind: TbType) -> bool {
+        let loaded = match kind {
+            TbType::Wdl => &self.tables[index].wdl,
+            TbType::Dtz => &self.tables[index].dtz
+        };
+        if let Some(data) = loaded {
+            return data.is_some();
+        }
+        let material = &self.tables[index].material;
+        let (extension, magic) = match kind {
+            TbType::Wdl => ("rtbw", MAGIC_WDL),
+            TbType::Dtz => ("rtbz", MAGIC_DTZ)
+        };
+        let mut data = None;
+        for dir in &self.paths {
+            if let Ok(bytes) = fs::read(Path::new(dir).join(format!("{}.{}", material.key, extension))) {
+                if bytes.len() > 5 && bytes[0..4] == magic {
+                    data = self.init_table(material, kind, bytes);
+                }
+                break;
+            }
+        }
+        let available = data.is_some();
+        match kind {
+            TbType::Wdl => self.tables[index].wdl = Some(data),
+            TbType::Dtz => self.tables[index].dtz = Some(data)
+        }
+        available
+    }
+
+    // read indexing information of the table
+    fn init_table(&self, material: &Material, kind: TbType, bytes: Vec<u8>) -> Option<TableData> {
+        const HAS_PAWNS: u8 = 2;
+        let mut at = 4;
+        let flags = bytes[at];
+        if material.has_pawns != (flags & HAS_PAWNS > 0) {
+            return None;
+        }
+        at += 1;
+
+        let sides = if kind == TbType::Wdl && material.key != material.key2 { 2 } else { 1 };
+        let max_file = if material.has_pawns { 4 } else { 1 };
+        let pp = material.has_pawns && material.pawn_count[1] > 0;
+        let mut items: Vec<Vec<PairsData>> = vec![vec![PairsData::default(); max_file]; sides];
+
+        for f in 0..max_file {
+            let order = [
+                [bytes[at] & 0xF, if pp { bytes[at + 1] & 0xF } else { 0xF }],
+                [bytes[at] >> 4, if pp { bytes[at + 1] >> 4 } else { 0xF }]
+            ];
+            at += 1 + pp as usize;
+            for k in 0..material.piece_count {
+                for (i, side) in items.iter_mut().enumerate() {
+                    side[f].pieces[k] = if i > 0 { bytes[at] >> 4 } else { bytes[at] & 0xF };
+                }
+                at += 1;
+            }
+            for (i, side) in items.iter_mut().enumerate() {
+                self.set_groups(material, &mut side[f], order[i], f);
+            }
+        }
+        at += at & 1;
+
+        for f in 0..max_file {
+            for side in items.iter_mut() {
+                at = Self::set_sizes(&bytes, &mut side[f], at)?;
+            }
+        }
+
+        // dtz maps
+        if kind == TbType::Dtz {
+            for d in items[0].iter_mut() {
+                if d.flags & FLAG_MAPPED > 0 {
+                    if d.flags & FLAG_WIDE > 0 {
+                        at += at & 1;
+                        for i in 0..4 {
+                            d.map_idx[i] = at + 2;
+                            at += 2 * read_u16_le(&bytes, at) as usize + 2;
+                        }
+                    } else {
+                        for i in 0..4 {
+                            d.map_idx[i] = at + 1;
+                            at += *bytes.get(at)? as usize + 1;
+                        }
+                    }
+                }
+            }
+            at += at & 1;
+        }
+
+        for f in 0..max_file {
+            for side in items.iter_mut() {
+                side[f].sparse_index = at;
+                at += side[f].sparse_index_size as usize * 6;
+            }
+        }
+        for f in 0..max_file {
+            for side in items.iter_mut() {
+                side[f].block_length = at;
+                at += side[f].block_length_size as usize * 2;
+            }
+        }
+        for f in 0..max_file {
+            for side in items.iter_mut() {
+                at = (at + 0x3F) & !0x3F;
+                side[f].data = at;
+                at += side[f].num_blocks as usize * side[f].sizeof_block as usize;
+            }
+        }
+        if at > bytes.len() {
+            return None;
+        }
+        Some(TableData { bytes, items })
+    }
+
+    // Pieces are encoded by groups (KRRvKN -> KRR and N with unique pieces, KK, RR and N otherwise).
+    // If group g can be placed in N(g) ways, the index will be: g1 * N(g2) * N(g3) + g2 * N(g3) + g3,
+    // but the order of groups is defined by the table.
+    fn set_groups(&self, material: &Material, d: &mut PairsData, order: [u8; 2], f: usize) {
+        let ix = &self.indexes;
+        let mut n = 0;
+        let mut first_len: i32 = if material.has_pawns { 0 } else if material.has_unique_pieces { 3 } else { 2 };
+        d.group_len[n] = 1;
+        for i in 1..material.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        let pp = material.has_pawns && material.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx: u64 = 1;
+        let mut k = 0;
+        while next < n || k == order[0] as usize || k == order[1] as usize {
+            if k == order[0] as usize {
+                d.group_idx[0] = idx;
+                idx *= if material.has_pawns {
+                    ix.lead_pawns_size[d.group_len[0]][f]
+                } else if material.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] as usize {
+                d.group_idx[1] = idx;
+                idx *= ix.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= ix.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    // read the sizes of the compressed data and Huffman symbols, return the position right after them
+    fn set_sizes(bytes: &[u8], d: &mut PairsData, mut at: usize) -> Option<usize> {
+        d.flags = *bytes.get(at)?;
+        at += 1;
+        if d.flags & FLAG_SINGLE_VALUE > 0 {
+            d.min_sym_len = *bytes.get(at)?;
+            return Some(at + 1);
+        }
+
+        // the last group index is the table size
+        let tb_size = d.group_idx[d.group_len.iter().position(|len| *len == 0).unwrap_or(TB_PIECES)];
+        d.sizeof_block = 1 << bytes.get(at)?;
+        d.span = 1 << bytes.get(at + 1)?;
+        d.sparse_index_size = tb_size.div_ceil(d.span);
+        let padding = *bytes.get(at + 2)? as u32;
+        d.num_blocks = read_u32_le(bytes, at + 3);
+        d.block_length_size = d.num_blocks + padding;
+        d.max_sym_len = *bytes.get(at + 7)?;
+        d.min_sym_len = *bytes.get(at + 8)?;
+        at += 9;
+        if d.max_sym_len < d.min_sym_len || d.max_sym_len > 64 {
+            return None;
+        }
+        d.lowest_sym = at;
+
+        // Canonical Huffman code is ordered so that longer symbols have lower numeric value,
+        // so it's possible to find the symbol length by comparing with 64-bit padded lowest symbols of every length.
+        let count = (d.max_sym_len - d.min_sym_len + 1) as usize;
+        d.base64 = vec![0; count];
+        for i in (0..count.saturating_sub(1)).rev() {
+            let lowest = read_u16_le(bytes, d.lowest_sym + 2 * i) as u64;
+            let lowest_next = read_u16_le(bytes, d.lowest_sym + 2 * (i + 1)) as u64;
+            d.base64[i] = (d.base64[i + 1] + lowest - lowest_next) / 2;
+        }
+        for (i, base) in d.base64.iter_mut().enumerate() {
+            let shift = 64 - i - d.min_sym_len as usize;
+            *base = if shift < 64 { *base << shift } else { 0 };
+        }
+        at += count * 2;
+
+        let symbols = read_u16_le(bytes, at) as usize;
+        at += 2;
+        d.btree = at;
+        if d.btree + 3 * symbols > bytes.len() {
+            return None;
+        }
+        d.symlen = vec![0; symbols];
+        let mut visited = vec![false; symbols];
+        for sym in 0..symbols {
+            if !visited[sym] {
+                d.symlen[sym] = Self::set_symlen(bytes, d, sym, &mut visited);
+            }
+        }
+        Some(at + symbols * 3 + (symbols & 1))
+    }
+
+    // every symbol is a pair of children symbols, so count how many values it represents
+    fn set_symlen(bytes: &[u8], d: &mut PairsData, sym: usize, visited: &mut [bool]) -> u8 {
+        visited[sym] = true;
+        let right = Self::btree_right(bytes, d, sym);
+        if right == 0xFFF {
+            return 0;
+        }
+        let left = Self::btree_left(bytes, d, sym);
+        if left >= visited.len() || right >= visited.len() {
+            return 0;
+        }
+        if !visited[left] {
+            d.symlen[left] = Self::set_symlen(bytes, d, left, visited);
+        }
+        if !visited[right] {
+            d.symlen[right] = Self::set_symlen(bytes, d, right, visited);
+        }
+        d.symlen[left].wrapping_add(d.symlen[right]).wrapping_add(1)
+    }
+}
+
+impl Indexes {
+    fn init() -> Self {
+        let off = Tablebase::off_a1h8;
+        let mut ix = Self {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [0; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; TB_PIECES],
+            lead_pawn_idx: [[0; 64]; TB_PIECES],
+            lead_pawns_size: [[0; 4]; TB_PIECES]
+        };
+
+        // squares below a1-h8 diagonal to 0..27
+        let mut code = 0;
+        for s in 0..64 {
+            if off(s) < 0 {
+                ix.map_b1h1h7[s] = code;
+                code += 1;
+            }
+        }
+
+        // squares of a1-d1-d4 triangle to 0..9, diagonal squares are the last ones
+        let mut diagonal = Vec::default();
+        code = 0;
+        for s in 0..28 {
+            if off(s) < 0 && s & 7 <= 3 {
+                ix.map_a1d1d4[s] = code;
+                code += 1;
+            } else if off(s) == 0 && s & 7 <= 3 {
+                diagonal.push(s);
+            }
+        }
+        for s in diagonal {
+            ix.map_a1d1d4[s] = code;
+            code += 1;
+        }
+
+        // 462 legal positions of two kings, where the first one is in a1-d1-d4 triangle
+        // (if the first one is on the diagonal, the second one is not above it)
+        let mut both_on_diagonal = Vec::default();
+        code = 0;
+        for idx in 0..10 {
+            for s1 in 0..28 {
+                if ix.map_a1d1d4[s1] != idx || (idx == 0 && s1 != 1) {
+                    continue;
+                }
+                for s2 in 0..64 {
+                    let adjacent = max(s1 >> 3, s2 >> 3) - min(s1 >> 3, s2 >> 3) < 2 && max(s1 & 7, s2 & 7) - min(s1 & 7, s2 & 7) < 2;
+                    if adjacent || (off(s1) == 0 && off(s2) > 0) {
+                        continue;
+                    }
+                    if off(s1) == 0 && off(s2) == 0 {
+                        both_on_diagonal.push((idx, s2));
+                    } else {
+                        ix.map_kk[idx][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            ix.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        // ways to choose k elements from n
+        ix.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..min(TB_PIECES, n + 1) {
+                ix.binomial[k][n] = if k > 0 { ix.binomial[k - 1][n - 1] } else { 0 } + if k < n { ix.binomial[k][n - 1] } else { 0 };
+            }
+        }
+
+        // a2-h7 squares to 0..47: the amount of available squares for other pawns if the leading one is there
+        let mut available = 48;
+        for lead_pawns_cnt in 1..TB_PIECES - 1 {
+            for f in 0..4 {
+                let mut idx = 0;
+                for r in 1..7 {
+                    let s = r * 8 + f;
+                    if lead_pawns_cnt == 1 {
+                        ix.map_pawns[s] = available - 1;
+                        ix.map_pawns[s ^ 7] = available - 2;
+                        available -= 2;
+                    }
+                    ix.lead_pawn_idx[lead_pawns_cnt][s] = idx;
+                    idx += ix.binomial[lead_pawns_cnt - 1][ix.map_pawns[s]];
+                }
+                ix.lead_pawns_size[lead_pawns_cnt][f] = idx;
+            }
+        }
+        ix
+    }
+}
+
+// out of bounds reads give zeros, so corrupted tables won't panic here
+fn read_u16_le(bytes: &[u8], at: usize) -> u16 {
+    let mut buf = [0; 2];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = *bytes.get(at + i).unwrap_or(&0);
+    }
+    u16::from_le_bytes(buf)
+}
+
+fn read_u32_le(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0; 4];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = *bytes.get(at + i).unwrap_or(&0);
+    }
+    u32::from_le_bytes(buf)
+}
+
+fn read_u32_be(bytes: &[u8], at: usize) -> u32 {
+    let mut buf = [0; 4];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = *bytes.get(at + i).unwrap_or(&0);
+    }
+    u32::from_be_bytes(buf)
+}
+
+fn read_u64_be(bytes: &[u8], at: usize) -> u64 {
+    let mut buf = [0; 8];
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = *bytes.get(at + i).unwrap_or(&0);
+    }
+    u64::from_be_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::board::Board;
+    use super::{Indexes, Tablebase};
+
+    #[test]
+    fn test_syzygy_indexes_01() {
+        let ix = Indexes::init();
+        assert_eq!(ix.map_kk.iter().flatten().max(), Some(&461));
+        assert_eq!(ix.map_b1h1h7.iter().max(), Some(&27));
+        assert_eq!(ix.map_a1d1d4.iter().max(), Some(&9));
+        // b1 is the first square below the diagonal, a1 is the first diagonal one
+        assert_eq!(ix.map_a1d1d4[1], 0);
+        assert_eq!(ix.map_a1d1d4[0], 6);
+    }
+
+    #[test]
+    fn test_syzygy_indexes_02() {
+        let ix = Indexes::init();
+        assert_eq!(ix.binomial[2][5], 10);
+        assert_eq!(ix.binomial[3][48], 17296);
+        // a2 leaves the most squares for other pawns
+        assert_eq!(ix.map_pawns[8], 47);
+        assert_eq!(ix.map_pawns[15], 46);
+        assert_eq!(ix.lead_pawns_size[1][0], 6);
+    }
+
+    #[test]
+    fn test_syzygy_code_01() {
+        let board = Board::parse_fen("8/8/4k3/8/2R5/1P6/8/3K3r w - - 0 1");
+        assert_eq!(Tablebase::get_code(&Tablebase::get_pieces(&board)), "KRPvKR");
+    }
+
+    #[test]
+    fn test_syzygy_missing_01() {
+        let mut tablebase = Tablebase::new("/nonexistent/syzygy", 6);
+        assert!(tablebase.is_empty());
+        let mut board = Board::parse_fen("8/8/4k3/8/2R5/8/8/3K4 w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&mut board), None);
+        assert!(tablebase.get_eval_moves(&mut board).is_none());
+    }
+
+    #[test]
+    fn test_syzygy_missing_02() {
+        // KvK is a draw without any table
+        let mut tablebase = Tablebase::new("", 6);
+        let mut board = Board::parse_fen("8/8/4k3/8/8/8/8/3K4 w - - 0 1");
+        assert_eq!(tablebase.probe_wdl(&mut board), Some(0));
+        assert_eq!(tablebase.probe_dtz(&mut board), Some(0));
+    }
+
+    fn fixtures() -> Tablebase {
+        Tablebase::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/syzygy"), 6)
+    }
+
+    fn probe(tablebase: &mut Tablebase, fen: &str) -> (Option<i32>, Option<i32>) {
+        let mut board = Board::parse_fen(fen);
+        (tablebase.probe_wdl(&mut board), tablebase.probe_dtz(&mut board))
+    }
+
+    #[test]
+    fn test_syzygy_fixture_01() {
+        // KQvK: white to move is stored as a single value, black to move is compressed
+        let mut tablebase = fixtures();
+        assert_eq!(tablebase.len(), 5);
+        assert_eq!(probe(&mut tablebase, "8/8/4k3/8/8/8/8/3K2Q1 w - - 0 1"), (Some(2), Some(17)));
+        assert_eq!(probe(&mut tablebase, "8/8/4k3/8/8/8/8/3K2Q1 b - - 0 1"), (Some(-2), Some(-18)));
+        // the same table with colors flipped
+        assert_eq!(probe(&mut tablebase, "3k2q1/8/8/8/8/4K3/8/8 b - - 0 1"), (Some(2), Some(17)));
+        // the hanging queen is taken, KvK is a draw
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/8/8/5kQ1/3K4 b - - 0 1"), (Some(0), Some(0)));
+        // stalemate
+        assert_eq!(probe(&mut tablebase, "k7/8/1QK5/8/8/8/8/8 b - - 0 1"), (Some(0), Some(0)));
+        // KBvK and KNvK are draws stored as single values
+        assert_eq!(probe(&mut tablebase, "8/8/4k3/8/8/8/8/3K2B1 w - - 0 1"), (Some(0), Some(0)));
+        assert_eq!(probe(&mut tablebase, "8/8/4k3/8/8/8/8/3K2N1 b - - 0 1"), (Some(0), Some(0)));
+    }
+
+    #[test]
+    fn test_syzygy_fixture_02() {
+        // KRvK: DTZ table stores white to move only and maps values by frequency
+        let mut tablebase = fixtures();
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/3k4/8/8/K6R w - - 0 1"), (Some(2), Some(29)));
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/3k4/8/8/K6R b - - 0 1"), (Some(-2), Some(-30)));
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/8/8/8/R3K2k w - - 0 1"), (Some(2), Some(5)));
+        // mate in one and mate
+        assert_eq!(probe(&mut tablebase, "k7/8/1K6/8/8/8/8/7R w - - 0 1"), (Some(2), Some(1)));
+        assert_eq!(probe(&mut tablebase, "k6R/8/1K6/8/8/8/8/8 b - - 0 1"), (Some(-2), Some(-1)));
+        // KvKR
+        assert_eq!(probe(&mut tablebase, "r3k2K/8/8/8/8/8/8/8 b - - 0 1"), (Some(2), Some(5)));
+        assert_eq!(probe(&mut tablebase, "8/8/8/3K4/8/8/8/k6r w - - 0 1"), (Some(-2), Some(-30)));
+    }
+
+    #[test]
+    fn test_syzygy_fixture_03() {
+        // KPvK: DTZ table stores white to move for a and c leading pawn files and black to move for b and d
+        let mut tablebase = fixtures();
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/4k3/K7/P7/8 w - - 0 1"), (Some(2), Some(9)));
+        assert_eq!(probe(&mut tablebase, "8/5k2/8/8/1P6/8/8/1K6 w - - 0 1"), (Some(2), Some(13)));
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/8/2k2P2/6K1/8 b - - 0 1"), (Some(-2), Some(-14)));
+        assert_eq!(probe(&mut tablebase, "8/7k/8/4P3/4K3/8/8/8 b - - 0 1"), (Some(-2), Some(-10)));
+        // the king on the sixth rank in front of the pawn wins with either side to move
+        assert_eq!(probe(&mut tablebase, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"), (Some(2), Some(3)));
+        assert_eq!(probe(&mut tablebase, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"), (Some(-2), Some(-4)));
+        // the rook pawn can't win against the king in the corner
+        assert_eq!(probe(&mut tablebase, "k7/8/8/8/8/8/P7/K7 w - - 0 1"), (Some(0), Some(0)));
+        // promotion is a zeroing move
+        assert_eq!(probe(&mut tablebase, "8/4P3/8/8/8/8/k7/4K3 w - - 0 1"), (Some(2), Some(1)));
+        // KvKP
+        assert_eq!(probe(&mut tablebase, "8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"), (Some(-2), Some(-4)));
+        assert_eq!(probe(&mut tablebase, "1k6/8/8/1p6/8/8/5K2/8 b - - 0 1"), (Some(2), Some(13)));
+    }
+}
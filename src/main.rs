@@ -3,36 +3,84 @@ mod engine;
 mod characters;
 mod utils;
 
-use board::board::Board;
+use board::{board::Board, game::Game};
 
-use characters::generic::Generic;
-use engine::{character::Character};
-use std::{env, io::{stdin, stdout, Write}};
-use crate::{utils::utils::{move_to_user, move_to_board}};
+use characters::{generic::{Generic, GenericConfig, GenericSettings}, tuning::Tuner, personalities::Personality};
+use engine::{character::Character, book::{Book, BookSelection}, syzygy::Tablebase, strength::Strength, store::AnalysisStore};
+use std::{env, fs, io::{stdin, stdout, Write}};
+use crate::{utils::utils::{move_to_user, parse_move}};
 
 pub fn main() {
     env::set_var("RUST_BACKTRACE", "1");
-    test_loop("k3r3/3r4/8/8/8/8/8/5K2 w - - 0 1", &mut Generic::new(&[4, 6, 64]));
+    let args: Vec<String> = env::args().collect();
+    // cce makebook <games.pgn> <book.bin> [max half-moves]
+    if args.len() > 3 && args[1] == "makebook" {
+        let max_ply = args.get(4).map_or(Book::DEFAULT_MAX_PLY, |s| s.parse().expect("Max half-moves must be a number"));
+        make_book(&args[2], &args[3], max_ply);
+        return;
+    }
+    // cce tune <dataset> <output> [config] [max passes]
+    if args.len() > 3 && args[1] == "tune" {
+        let max_passes = args.get(5).map_or(100, |s| s.parse().expect("Max passes must be a number"));
+        tune(&args[2], &args[3], args.get(4), max_passes);
+        return;
+    }
+    // cce weights [character or config] [output]
+    if args.len() > 1 && args[1] == "weights" {
+        dump_weights(args.get(2), args.get(3));
+        return;
+    }
+    // cce store compact <store.bin> [max entries]
+    if args.len() > 3 && args[1] == "store" && args[2] == "compact" {
+        let max_entries = args.get(4).map_or(AnalysisStore::DEFAULT_MAX_ENTRIES, |s| s.parse().expect("Max entries must be a number"));
+        compact_store(&args[3], max_entries);
+        return;
+    }
+    // cce characters
+    if args.len() > 1 && args[1] == "characters" {
+        for personality in &Personality::ALL {
+            println!("{:<12} {}", personality.name, personality.description);
+        }
+        return;
+    }
+    // cce [--character <name>] [--config <weights.toml|weights.json>] [--elo <rating>] [--seed <number>] [--store <store.bin>]
+    // config replaces the weights of the character, its search depths stay; --seed replaces the seed of the config
+    let option = |name: &str| args.iter().position(|arg| arg == name).and_then(|i| args.get(i + 1));
+    let character = option("--character").map_or(Personality::DEFAULT, |name| name.as_str());
+    let personality = Personality::find(character).unwrap_or_else(|| panic!("Unknown character {}, see `cce characters`", character));
+    let mut char = match option("--config") {
+        Some(path) => Generic::from_config(path, &personality.depths).unwrap_or_else(|e| panic!("Cannot load {}: {}", path, e)),
+        None => personality.create()
+    };
+    if let Some(seed) = option("--seed") {
+        char.set_seed(GenericSettings::parse_seed(seed).unwrap_or_else(|| panic!("Seed must be a number from 0 to {}", GenericSettings::MAX_SEED)));
+    }
+    if let Some(path) = option("--store") {
+        char.set_store(Some(AnalysisStore::open(path, AnalysisStore::DEFAULT_MAX_ENTRIES).unwrap_or_else(|e| panic!("Cannot open {}: {}", path, e))));
+    }
+    if let Some(elo) = option("--elo") {
+        char.set_strength(Some(Strength::from_elo(elo.parse().expect("Elo must be a number"))));
+    }
+    test_loop("k3r3/3r4/8/8/8/8/8/5K2 w - - 0 1", &mut char);
 }
 
 // some tests
-pub fn test_loop<Char: Character>(FEN: &str, char: &mut Char) {
-    let mut board = Board::parse_fen(FEN);
+pub fn test_loop<Char: Character>(fen: &str, char: &mut Char) {
+    let mut game = Game::new(Board::parse_fen(fen));
     let mut half_depth: i8 = char.get_static_half_depth();
     loop {
         println!("\n--------------------------------------------------\n");
-        board.print();
+        game.board.print();
         // don't do that!
         // char.clear_cache();
-        let moves = char.get_eval_moves(&mut board).to_vec();
+        let moves = char.get_eval_moves(&mut game.board).to_vec();
         println!("Total moves: {}", moves.len());
         for emov in &moves {
             let mate_in = emov.eval.mate_in.signum() * ((emov.eval.mate_in.abs() + 1) >> 1);
-            println!("{}, score: {}, mate_in: {}", move_to_user(&board, &emov.mov), emov.eval.score, mate_in);
+            println!("{}, score: {}, mate_in: {}", move_to_user(&game.board, &emov.mov), emov.eval.score, mate_in);
         }
         println!();
 
-        let legals = board.get_legal_moves(None, None);
         let mut success: bool = false;
         while !success {
             let mut command = String::new();
@@ -43,10 +91,50 @@ pub fn test_loop<Char: Character>(FEN: &str, char: &mut Char) {
             let mut opt = false;
             if command == "exit" {
                 return;
-            } else if command == "takeback" {
-                board.revert_move();
-                char.takeback();
+            } else if command == "takeback" || command == "back" {
+                success = step_back(&mut game, char);
+                opt = true;
+            } else if command == "forward" {
+                success = step_forward(&mut game, char);
+                opt = true;
+            } else if command == "start" {
+                while step_back(&mut game, char) {}
+                success = true;
+            } else if command == "end" {
+                while step_forward(&mut game, char) {}
                 success = true;
+            } else if let Some(ply) = command.strip_prefix("ply ") {
+                // ply <half-moves from the start> along the current line
+                match ply.parse::<usize>() {
+                    Ok(ply) => {
+                        while game.ply() > ply && step_back(&mut game, char) {}
+                        while game.ply() < ply && step_forward(&mut game, char) {}
+                        success = true;
+                    },
+                    Err(_) => println!("Ply must be a number")
+                }
+                opt = true;
+            } else if command == "line" {
+                print_line(&mut game);
+                opt = true;
+            } else if command == "variations" {
+                let variations: Vec<String> = game.get_variations().iter().enumerate()
+                    .map(|(i, mov)| format!("{}: {}", i, move_to_user(&game.board, mov))).collect();
+                println!("{}", variations.join(", "));
+                opt = true;
+            } else if let Some(index) = command.strip_prefix("variation ") {
+                // enter the continuation by its number in the list of variations
+                if index.parse().is_ok_and(|index| game.enter_variation(index)) {
+                    char.accept_move(&game.board);
+                    success = true;
+                } else {
+                    println!("No such variation: {}", index);
+                }
+                opt = true;
+            } else if command == "promote" {
+                game.promote_variation();
+                println!("current line is now the main one");
+                opt = true;
             } else if command == "depth up" {
                 half_depth += 1;
                 char.set_static_half_depth(half_depth);
@@ -59,21 +147,105 @@ pub fn test_loop<Char: Character>(FEN: &str, char: &mut Char) {
                 char.set_mixed_half_depth(half_depth + 2);
                 println!("depth is now set to: {}", half_depth >> 1);
                 opt = true;
+            } else if command == "book off" {
+                char.set_book(None);
+                println!("book is now disabled");
+                opt = true;
+            } else if let Some(path) = command.strip_prefix("book ") {
+                match Book::open(path, BookSelection::Weighted, Book::DEFAULT_MAX_PLY) {
+                    Ok(book) => {
+                        println!("book is now set to: {} ({} entries)", path, book.len());
+                        char.set_book(Some(book));
+                    },
+                    Err(e) => println!("Cannot open book: {}", e)
+                }
+                opt = true;
+            } else if command == "syzygy off" {
+                char.set_tablebase(None);
+                println!("tablebases are now disabled");
+                opt = true;
+            } else if let Some(params) = command.strip_prefix("syzygy ") {
+                // syzygy <path> [max pieces]
+                let params: Vec<&str> = params.split_whitespace().collect();
+                let max_pieces = params.get(1).and_then(|p| p.parse().ok()).unwrap_or(Tablebase::DEFAULT_MAX_PIECES);
+                let path = params.first().unwrap_or(&"");
+                let tablebase = Tablebase::new(path, max_pieces);
+                println!("tablebases are now set to: {} ({} tables, up to {} pieces)", path, tablebase.len(), tablebase.max_pieces());
+                char.set_tablebase(Some(tablebase));
+                opt = true;
+            } else if command == "store off" {
+                char.set_store(None);
+                println!("analysis store is now disabled");
+                opt = true;
+            } else if let Some(path) = command.strip_prefix("store ") {
+                match AnalysisStore::open(path, AnalysisStore::DEFAULT_MAX_ENTRIES) {
+                    Ok(store) => {
+                        println!("analysis store is now set to: {} ({} entries)", path, store.len());
+                        char.set_store(Some(store));
+                    },
+                    Err(e) => println!("Cannot open analysis store: {}", e)
+                }
+                opt = true;
+            } else if command == "elo off" {
+                char.set_strength(None);
+                println!("strength is now full");
+                opt = true;
+            } else if let Some(elo) = command.strip_prefix("elo ") {
+                match elo.parse() {
+                    Ok(elo) => {
+                        let strength = Strength::from_elo(elo);
+                        println!("strength is now set to: {:?}", strength);
+                        char.set_strength(Some(strength));
+                    },
+                    Err(_) => println!("Elo must be a number")
+                }
+                opt = true;
+            } else if command == "seed" {
+                println!("seed: {}", char.get_seed());
+                opt = true;
+            } else if let Some(seed) = command.strip_prefix("seed ") {
+                match GenericSettings::parse_seed(seed) {
+                    Some(seed) => {
+                        char.set_seed(seed);
+                        println!("seed is now set to: {}", seed);
+                    },
+                    None => println!("Seed must be a number from 0 to {}", GenericSettings::MAX_SEED)
+                }
+                opt = true;
+            } else if command == "explain" {
+                char.explain_eval(&game.board).print();
+                opt = true;
+            } else if command == "explain json" {
+                println!("{}", char.explain_eval(&game.board).to_json());
+                opt = true;
             } else if command == "rethink" {
                 success = true;
+            } else if command == "go" {
+                // engine move at its strength, which is not always the top one
+                if !moves.is_empty() {
+                    let mov = char.get_eval_move(&mut game.board).mov;
+                    println!("engine plays: {}", move_to_user(&game.board, &mov));
+                    game.play(&mov);
+                    char.accept_move(&game.board);
+                    success = true;
+                }
             } else if command == "top" {
                 if !moves.is_empty() {
-                    let mov = moves[0].mov;
-                    board.make_move(&mov);
-                    char.accept_move(&board);
+                    game.play(&moves[0].mov);
+                    char.accept_move(&game.board);
                     success = true;
                 }
-            } else {
-                for lmov in &legals {
-                    if move_to_user(&board, lmov) == command {
-                        board.make_move(&move_to_board(&board, &command));
-                        char.accept_move(&board);
+            } else if let Some(mov) = parse_move(&game.board, &command) {
+                // a move played in the middle of the game starts a variation, the rest of the game is kept
+                match game.board.validate_move(&mov) {
+                    Ok(mov) => {
+                        game.play(&mov);
+                        char.accept_move(&game.board);
                         success = true;
+                    },
+                    Err(e) => {
+                        println!("Illegal move {}: {}", command, e);
+                        opt = true;
                     }
                 }
             }
@@ -83,4 +255,92 @@ pub fn test_loop<Char: Character>(FEN: &str, char: &mut Char) {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// take back a move of the game (the character forgets it), false at the start
+fn step_back<Char: Character>(game: &mut Game, char: &mut Char) -> bool {
+    let moved = game.back();
+    if moved {
+        char.takeback();
+    }
+    moved
+}
+
+// replay the next move of the current line, false at its end
+fn step_forward<Char: Character>(game: &mut Game, char: &mut Char) -> bool {
+    let moved = game.forward();
+    if moved {
+        char.accept_move(&game.board);
+    }
+    moved
+}
+
+// moves of the current line with move numbers, the current position is marked by '*'
+fn print_line(game: &mut Game) {
+    let ply = game.ply();
+    let mut line: Vec<String> = Vec::default();
+    game.go_to_start();
+    let moves = game.get_line();
+    for (i, mov) in moves.iter().enumerate() {
+        if i == ply {
+            line.push("*".to_string());
+        }
+        let number = if game.board.white_to_move { format!("{}. ", (game.board.hno >> 1) + 1) } else if i == 0 { format!("{}... ", (game.board.hno >> 1) + 1) } else { String::new() };
+        line.push(format!("{}{}", number, move_to_user(&game.board, mov)));
+        game.forward();
+    }
+    if ply == moves.len() {
+        line.push("*".to_string());
+    }
+    game.go_to_ply(ply);
+    println!("{}", line.join(" "));
+}
+
+// build polyglot book out of PGN file
+pub fn make_book(pgn_path: &str, book_path: &str, max_ply: u16) {
+    let pgn = fs::read_to_string(pgn_path).expect("Cannot read PGN file");
+    let book = Book::from_pgn(&pgn, BookSelection::Weighted, max_ply);
+    book.save(book_path).expect("Cannot write book file");
+    println!("{} entries saved to {}", book.len(), book_path);
+}
+
+// drop outdated and excess entries of the analysis store file
+pub fn compact_store(path: &str, max_entries: usize) {
+    let mut store = AnalysisStore::open(path, max_entries).unwrap_or_else(|e| panic!("Cannot open {}: {}", path, e));
+    let before = store.file_len();
+    store.compact().expect("Cannot write analysis store");
+    println!("{} entries of {} kept in {}", store.len(), before, path);
+}
+
+// validate weights config (or take the weights of a built-in character) and print them or save to another file
+pub fn dump_weights(source: Option<&String>, output_path: Option<&String>) {
+    let config = match source {
+        Some(name) if Personality::find(name).is_some() => Personality::find(name).unwrap().get_config(),
+        Some(path) => GenericConfig::load(path).unwrap_or_else(|e| panic!("Cannot load {}: {}", path, e)),
+        None => GenericConfig::default()
+    };
+    match output_path {
+        Some(path) => {
+            config.save(path).expect("Cannot write weights file");
+            println!("weights saved to {}", path);
+        },
+        None => print!("{}", config.to_toml())
+    }
+}
+
+// Texel tuning of the weights (default ones or from config) on the dataset of quiet positions with results
+pub fn tune(dataset_path: &str, output_path: &str, config_path: Option<&String>, max_passes: usize) {
+    let mut config = match config_path {
+        Some(path) => GenericConfig::load(path).unwrap_or_else(|e| panic!("Cannot load {}: {}", path, e)),
+        None => GenericConfig::default()
+    };
+    let weights = config.weights.clone();
+    let mut tuner = Tuner::load(dataset_path).unwrap_or_else(|e| panic!("Cannot load {}: {}", dataset_path, e));
+    println!("{} positions, k = {}, error = {}", tuner.len(), tuner.fit_k(&weights), tuner.error(&weights));
+    let tuned = tuner.tune(&weights, max_passes, |pass| println!("pass {}: error {}, step {}", pass.pass, pass.error, pass.step));
+    let error = tuner.error(&tuned);
+    // settings of the config are kept as they are
+    config.weights = tuned;
+    config.save(output_path).expect("Cannot write weights file");
+    println!("error = {}, weights saved to {}", error, output_path);
+}
@@ -5,6 +5,7 @@ use crate::board::{board::Board, mov::Mov, coord::Coord};
 // Please, consider using interface methods.
 
 // warning: this function works assuming that the usermove is already legit!
+//          (parse_move and Board::try_make_move are for the moves that are not)
 //          this function also won't add any check bits to the mov.
 pub fn move_to_board(board: &Board, umov: &str) -> Mov {
     let chars: Vec<char> = umov.chars().collect();
@@ -33,6 +34,18 @@ pub fn move_to_board(board: &Board, umov: &str) -> Mov {
     Mov{data, from, to}
 }
 
+// move_to_board for any user input: None if it's not a move in coordinate notation (e.g. "e2e4", "e7e8q"),
+// otherwise the move still needs Board::validate_move (or try_make_move)
+pub fn parse_move(board: &Board, umov: &str) -> Option<Mov> {
+    let chars: Vec<char> = umov.chars().collect();
+    let square = |file: char, rank: char| ('a'..='h').contains(&file) && ('1'..='8').contains(&rank);
+    if (chars.len() == 4 || (chars.len() == 5 && "qrbn".contains(chars[4]))) && square(chars[0], chars[1]) && square(chars[2], chars[3]) {
+        Some(move_to_board(board, umov))
+    } else {
+        None
+    }
+}
+
 pub fn move_to_user(board: &Board, mov: &Mov) -> String {
     let mut output = String::new();
     output.push((mov.from.x() + 97) as char);
@@ -43,4 +56,64 @@ pub fn move_to_user(board: &Board, mov: &Mov) -> String {
         output.push(board.rtpc(mov.data));
     }
     output
-}
\ No newline at end of file
+}
+// convert SAN (as in PGN, e.g. "Nbd7", "exd6", "e8=Q+", "O-O-O") into the legal board move, if there's one
+pub fn san_to_board(board: &mut Board, san: &str) -> Option<Mov> {
+    let san = san.trim_end_matches(['+', '#', '!', '?']);
+    let legals = board.get_legal_moves(None, Some(true));
+    let color_bit = board.white_to_move as u8;
+
+    // castlings
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let to_x = if san.len() > 3 { 2 } else { 6 };
+        return legals.iter().find(|mov| {
+            board.field[mov.from.y() as usize][mov.from.x() as usize] == board.gpl(&'k') + color_bit && mov.from.x() == 4 && mov.to.x() == to_x
+        }).copied();
+    }
+
+    let mut chars: Vec<char> = san.chars().filter(|c| *c != 'x' && *c != '=').collect();
+    let piece = match chars.first() {
+        Some(c) if "KQRBN".contains(*c) => {
+            let c = chars.remove(0);
+            board.gpl(&c.to_ascii_lowercase()) + color_bit
+        },
+        _ => board.gpl(&'p') + color_bit
+    };
+    let promotion = match chars.last() {
+        Some(c) if "QRBNqrbn".contains(*c) => Some(chars.pop().unwrap().to_ascii_lowercase()),
+        _ => None
+    };
+    if chars.len() < 2 {
+        return None;
+    }
+    let to_y = chars[chars.len() - 1] as i16 - '1' as i16;
+    let to_x = chars[chars.len() - 2] as i16 - 'a' as i16;
+    // disambiguation: file, rank or both
+    let mut from_y: Option<u8> = None;
+    let mut from_x: Option<u8> = None;
+    for c in &chars[..chars.len() - 2] {
+        if ('a'..='h').contains(c) {
+            from_x = Some(*c as u8 - b'a');
+        } else if ('1'..='8').contains(c) {
+            from_y = Some(*c as u8 - b'1');
+        } else {
+            return None;
+        }
+    }
+
+    let mut found = legals.iter().filter(|mov| {
+        board.field[mov.from.y() as usize][mov.from.x() as usize] == piece &&
+        mov.to.y() as i16 == to_y && mov.to.x() as i16 == to_x &&
+        from_y.is_none_or(|y| mov.from.y() == y) && from_x.is_none_or(|x| mov.from.x() == x) &&
+        match promotion {
+            Some(c) => board.is_promotion(mov) && board.rtpc(mov.data) == c,
+            None => !board.is_promotion(mov)
+        }
+    });
+    let mov = found.next().copied();
+    // ambiguous SAN is not a legal SAN
+    if found.next().is_some() {
+        return None;
+    }
+    mov
+}
@@ -0,0 +1,619 @@
+#!/usr/bin/env python3
+# Generates the small Syzygy tables used by tests: KQvK, KRvK, KBvK, KNvK and KPvK (WDL)
+# and KQvK, KRvK, KPvK (DTZ). Positions are solved by retrograde analysis, then tables are encoded
+# in the Syzygy format: canonical indexing, recursive pairing and canonical Huffman compression.
+#
+# Usage: python3 generate.py [output directory]
+
+import bisect
+import heapq
+import os
+import struct
+import sys
+from collections import Counter
+
+WDL_MAGIC = bytes([0x71, 0xE8, 0x23, 0x5D])
+DTZ_MAGIC = bytes([0xD7, 0x66, 0x0C, 0xA5])
+
+FLAG_STM = 1
+FLAG_MAPPED = 2
+FLAG_WIN_PLIES = 4
+FLAG_LOSS_PLIES = 8
+FLAG_SINGLE_VALUE = 128
+
+# table piece codes of white pieces, black ones have 8 added
+CODES = {'P': 1, 'N': 2, 'B': 3, 'R': 4, 'Q': 5, 'K': 6}
+
+BLOCK_SIZE_LOG = 5
+SPAN_LOG = 8
+MAX_BLOCK_VALUES = 60000
+
+
+# board geometry, squares are y * 8 + x (a1 = 0, h8 = 63)
+
+def on_board(x, y):
+    return 0 <= x < 8 and 0 <= y < 8
+
+
+def adjacent(a, b):
+    return max(abs((a & 7) - (b & 7)), abs((a >> 3) - (b >> 3))) <= 1
+
+
+KING_DIRS = [(dx, dy) for dx in (-1, 0, 1) for dy in (-1, 0, 1) if dx or dy]
+KNIGHT_DIRS = [(1, 2), (2, 1), (-1, 2), (-2, 1), (1, -2), (2, -1), (-1, -2), (-2, -1)]
+ROOK_DIRS = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+BISHOP_DIRS = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+
+
+def steps(dirs):
+    table = []
+    for s in range(64):
+        table.append([((s >> 3) + dy) * 8 + (s & 7) + dx for dx, dy in dirs if on_board((s & 7) + dx, (s >> 3) + dy)])
+    return table
+
+
+def rays(dirs):
+    # squares reachable on an empty board with the squares passed on the way
+    table = []
+    for s in range(64):
+        targets = []
+        for dx, dy in dirs:
+            x, y = (s & 7) + dx, (s >> 3) + dy
+            between = []
+            while on_board(x, y):
+                targets.append((y * 8 + x, frozenset(between)))
+                between.append(y * 8 + x)
+                x, y = x + dx, y + dy
+        table.append(targets)
+    return table
+
+
+KING_STEPS = steps(KING_DIRS)
+KNIGHT_STEPS = steps(KNIGHT_DIRS)
+SLIDER_RAYS = {'Q': rays(ROOK_DIRS + BISHOP_DIRS), 'R': rays(ROOK_DIRS), 'B': rays(BISHOP_DIRS)}
+
+
+def pawn_attacks(s):
+    return [s + 8 + dx for dx in (-1, 1) if on_board((s & 7) + dx, (s >> 3) + 1)]
+
+
+def attacks(piece, s, target, blocker):
+    if piece == 'P':
+        return target in pawn_attacks(s)
+    if piece == 'N':
+        return target in KNIGHT_STEPS[s]
+    return any(t == target and blocker not in between for t, between in SLIDER_RAYS[piece][s])
+
+
+def piece_moves(piece, s, occupied):
+    if piece == 'N':
+        return [t for t in KNIGHT_STEPS[s] if t not in occupied]
+    return [t for t, between in SLIDER_RAYS[piece][s] if t not in occupied and not between & occupied]
+
+
+class Solver:
+    """White king and a piece (Q, R, B, N or P) against a lone black king.
+
+    Values are from the side to move perspective: wdl is 2, 0 or -2, dtz is the number of plies
+    to the next zeroing move (a capture or a pawn move, checkmate counts as one) with the sign of wdl.
+    """
+
+    def __init__(self, piece, promotions=None):
+        self.piece = piece
+        self.promotions = promotions or {}
+        self.wdl = [None] * (64 * 64 * 64 * 2)
+        self.dtz = [0] * (64 * 64 * 64 * 2)
+        self.solve()
+
+    @staticmethod
+    def index(wk, x, bk, stm):
+        return ((wk * 64 + x) * 64 + bk) * 2 + stm
+
+    def is_legal(self, wk, x, bk, stm):
+        if len({wk, x, bk}) < 3 or adjacent(wk, bk):
+            return False
+        if self.piece == 'P' and not 1 <= x >> 3 <= 6:
+            return False
+        # the side that is not to move can't be in check
+        return stm == 1 or not attacks(self.piece, x, bk, wk)
+
+    def positions(self):
+        for wk in range(64):
+            for x in range(64):
+                for bk in range(64):
+                    for stm in (0, 1):
+                        if self.is_legal(wk, x, bk, stm):
+                            yield wk, x, bk, stm
+
+    # list of (resulting position or None, its wdl if known, zeroing) for every legal move
+    def moves(self, wk, x, bk, stm):
+        result = []
+        if stm == 0:
+            for t in KING_STEPS[wk]:
+                if t != x and not adjacent(t, bk):
+                    result.append((self.index(t, x, bk, 1), None, False))
+            if self.piece == 'P':
+                ahead = x + 8
+                if ahead not in (wk, bk):
+                    if ahead >> 3 == 7:
+                        for promotion in self.promotions.values():
+                            result.append((None, promotion.wdl[self.index(wk, ahead, bk, 1)], True))
+                    else:
+                        result.append((self.index(wk, ahead, bk, 1), None, True))
+                        if x >> 3 == 1 and ahead + 8 not in (wk, bk):
+                            result.append((self.index(wk, ahead + 8, bk, 1), None, True))
+            else:
+                for t in piece_moves(self.piece, x, {wk, bk}):
+                    result.append((self.index(wk, t, bk, 1), None, False))
+        else:
+            for t in KING_STEPS[bk]:
+                if adjacent(t, wk):
+                    continue
+                if t == x:
+                    # capture of an undefended piece leaves two kings
+                    result.append((None, 0, True))
+                elif not attacks(self.piece, x, t, wk):
+                    result.append((self.index(wk, x, t, 0), None, False))
+        return result
+
+    def in_check(self, wk, x, bk, stm):
+        return stm == 1 and attacks(self.piece, x, bk, wk)
+
+    def solve(self):
+        n = len(self.wdl)
+        preds = [None] * n
+        preds_zeroing = [None] * n
+        remaining = [0] * n
+        remaining_quiet = [0] * n
+        has_draw = [False] * n
+        zeroing_win = [False] * n
+        zeroing_targets = {}
+        mated = set()
+        queue = []
+
+        for wk, x, bk, stm in self.positions():
+            p = self.index(wk, x, bk, stm)
+            moves = self.moves(wk, x, bk, stm)
+            self.wdl[p] = 0
+            if not moves:
+                if self.in_check(wk, x, bk, stm):
+                    mated.add(p)
+                    self.wdl[p] = -2
+                    queue.append(p)
+                continue
+            for q, value, zeroing in moves:
+                if q is None:
+                    if value == -2:
+                        zeroing_win[p] = True
+                    elif value == 0:
+                        has_draw[p] = True
+                    continue
+                remaining[p] += 1
+                if zeroing:
+                    if preds_zeroing[q] is None:
+                        preds_zeroing[q] = []
+                    preds_zeroing[q].append(p)
+                    zeroing_targets.setdefault(p, []).append(q)
+                else:
+                    remaining_quiet[p] += 1
+                    if preds[q] is None:
+                        preds[q] = []
+                    preds[q].append(p)
+            if zeroing_win[p]:
+                self.wdl[p] = 2
+                queue.append(p)
+            elif remaining[p] == 0 and not has_draw[p]:
+                self.wdl[p] = -2
+                queue.append(p)
+        known = set(queue)
+
+        # WDL: a position is won if some move leads to a lost one, lost if all moves lead to won ones
+        while queue:
+            q = queue.pop()
+            for p in (preds[q] or []) + (preds_zeroing[q] or []):
+                if p in known:
+                    continue
+                if self.wdl[q] == -2:
+                    self.wdl[p] = 2
+                    known.add(p)
+                    queue.append(p)
+                else:
+                    remaining[p] -= 1
+                    if remaining[p] == 0 and not has_draw[p]:
+                        self.wdl[p] = -2
+                        known.add(p)
+                        queue.append(p)
+
+        # DTZ: zeroing moves and mates are the closest goals, other positions are solved by levels
+        level = []
+        for p in known:
+            if self.wdl[p] == 2:
+                targets = zeroing_targets.get(p, [])
+                if zeroing_win[p] or any(self.wdl[q] == -2 for q in targets):
+                    self.dtz[p] = 1
+                    level.append(p)
+            elif remaining_quiet[p] == 0:
+                self.dtz[p] = -1
+                level.append(p)
+        for q in mated:
+            for p in preds[q] or []:
+                if self.dtz[p] == 0:
+                    self.dtz[p] = 1
+                    level.append(p)
+        plies = 1
+        while level:
+            next_level = []
+            for q in level:
+                for p in preds[q] or []:
+                    if self.wdl[q] == -2 and self.wdl[p] == 2 and self.dtz[p] == 0:
+                        self.dtz[p] = plies + 1
+                        next_level.append(p)
+                    elif self.wdl[q] == 2 and self.wdl[p] == -2:
+                        remaining_quiet[p] -= 1
+                        if remaining_quiet[p] == 0:
+                            self.dtz[p] = -(plies + 1)
+                            next_level.append(p)
+            level = next_level
+            plies += 1
+
+
+class Indexes:
+    """Indexing constants of the Syzygy format."""
+
+    def __init__(self):
+        self.binomial = [[0] * 64 for _ in range(7)]
+        self.binomial[0][0] = 1
+        for n in range(1, 64):
+            for k in range(min(7, n + 1)):
+                self.binomial[k][n] = (self.binomial[k - 1][n - 1] if k > 0 else 0) + (self.binomial[k][n - 1] if k < n else 0)
+
+        self.map_b1h1h7 = [0] * 64
+        code = 0
+        for s in range(64):
+            if off_a1h8(s) < 0:
+                self.map_b1h1h7[s] = code
+                code += 1
+
+        self.map_a1d1d4 = [0] * 64
+        code = 0
+        for s in range(28):
+            if off_a1h8(s) < 0 and s & 7 <= 3:
+                self.map_a1d1d4[s] = code
+                code += 1
+        for s in range(28):
+            if off_a1h8(s) == 0 and s & 7 <= 3:
+                self.map_a1d1d4[s] = code
+                code += 1
+
+        # leading pawns: a2, h2, a3, h3, ... b2, g2, ... have descending values
+        self.map_pawns = [0] * 64
+        self.lead_pawn_idx = [[0] * 64 for _ in range(6)]
+        self.lead_pawns_size = [[0] * 4 for _ in range(6)]
+        available = 47
+        for count in range(1, 6):
+            for f in range(4):
+                idx = 0
+                for r in range(1, 7):
+                    s = r * 8 + f
+                    if count == 1:
+                        self.map_pawns[s] = available
+                        self.map_pawns[s ^ 7] = available - 1
+                        available -= 2
+                    self.lead_pawn_idx[count][s] = idx
+                    idx += self.binomial[count - 1][self.map_pawns[s]]
+                self.lead_pawns_size[count][f] = idx
+
+
+def off_a1h8(s):
+    return (s >> 3) - (s & 7)
+
+
+class Layout:
+    """Piece sequence and group order of one side of a table, for pawns - of one leading pawn file."""
+
+    def __init__(self, ix, pieces, order, has_pawns, f):
+        self.ix = ix
+        self.pieces = pieces
+        self.has_pawns = has_pawns
+        # the first group is the leading pawn or three unique pieces, other groups are single pieces here
+        first = 1 if has_pawns else 3
+        self.group_len = [first] + [1] * (len(pieces) - first)
+        self.group_idx = [0] * len(self.group_len)
+        size = 1
+        free_squares = 64 - self.group_len[0]
+        rest = iter(range(1, len(self.group_len)))
+        for k in range(len(self.group_len)):
+            if k == order:
+                self.group_idx[0] = size
+                size *= ix.lead_pawns_size[1][f] if has_pawns else 31332
+            else:
+                g = next(rest)
+                self.group_idx[g] = size
+                size *= ix.binomial[1][free_squares]
+                free_squares -= 1
+        self.size = size
+
+    def encode(self, squares):
+        ix = self.ix
+        squares = list(squares)
+        if squares[0] & 7 > 3:
+            squares = [s ^ 7 for s in squares]
+        if self.has_pawns:
+            idx = ix.lead_pawn_idx[1][squares[0]]
+        else:
+            if squares[0] >> 3 > 3:
+                squares = [s ^ 56 for s in squares]
+            for i in range(3):
+                off = off_a1h8(squares[i])
+                if off == 0:
+                    continue
+                if off > 0:
+                    squares = squares[:i] + [((s >> 3) | (s << 3)) & 63 for s in squares[i:]]
+                break
+            s0, s1, s2 = squares[:3]
+            adjust1 = int(s1 > s0)
+            adjust2 = int(s2 > s0) + int(s2 > s1)
+            if off_a1h8(s0):
+                idx = (ix.map_a1d1d4[s0] * 63 + (s1 - adjust1)) * 62 + s2 - adjust2
+            elif off_a1h8(s1):
+                idx = (6 * 63 + (s0 >> 3) * 28 + ix.map_b1h1h7[s1]) * 62 + s2 - adjust2
+            elif off_a1h8(s2):
+                idx = 6 * 63 * 62 + 4 * 28 * 62 + (s0 >> 3) * 7 * 28 + ((s1 >> 3) - adjust1) * 28 + ix.map_b1h1h7[s2]
+            else:
+                idx = 6 * 63 * 62 + 4 * 28 * 62 + 4 * 7 * 28 + (s0 >> 3) * 7 * 6 + ((s1 >> 3) - adjust1) * 6 + (s2 >> 3) - adjust2
+        idx *= self.group_idx[0]
+        start = self.group_len[0]
+        for g in range(1, len(self.group_len)):
+            s = squares[start]
+            adjust = sum(1 for other in squares[:start] if s > other)
+            idx += ix.binomial[1][s - adjust] * self.group_idx[g]
+            start += 1
+        return idx
+
+
+class Compressed:
+    """Values of one table part compressed with recursive pairing and canonical Huffman codes."""
+
+    def __init__(self, values, flags):
+        self.flags = flags
+        if len(set(values)) == 1:
+            self.flags |= FLAG_SINGLE_VALUE
+            self.sizes = bytes([self.flags, values[0]])
+            self.sparse = self.block_lengths = self.data = b''
+            return
+
+        # replace the most frequent adjacent pair by a new symbol while it pays off
+        leaves = sorted(set(values))
+        nodes = [(v, None) for v in leaves]
+        length = [1] * len(leaves)
+        leaf_sym = {v: i for i, v in enumerate(leaves)}
+        seq = [leaf_sym[v] for v in values]
+        while len(nodes) < 1000:
+            pair = next((p for p, n in Counter(zip(seq, seq[1:])).most_common()
+                         if n >= 8 and length[p[0]] + length[p[1]] <= 256), None)
+            if pair is None:
+                break
+            sym = len(nodes)
+            nodes.append(pair)
+            length.append(length[pair[0]] + length[pair[1]])
+            out = []
+            i = 0
+            while i < len(seq):
+                if i + 1 < len(seq) and (seq[i], seq[i + 1]) == pair:
+                    out.append(sym)
+                    i += 2
+                else:
+                    out.append(seq[i])
+                    i += 1
+            seq = out
+
+        # Huffman code lengths, symbols are renumbered so that longer codes come first
+        freq = Counter(seq)
+        code_len = Counter()
+        if len(freq) == 1:
+            code_len[seq[0]] = 1
+        else:
+            heap = [(n, sym, [sym]) for sym, n in freq.items()]
+            heapq.heapify(heap)
+            while len(heap) > 1:
+                n1, t1, s1 = heapq.heappop(heap)
+                n2, t2, s2 = heapq.heappop(heap)
+                for sym in s1 + s2:
+                    code_len[sym] += 1
+                heapq.heappush(heap, (n1 + n2, min(t1, t2), s1 + s2))
+        max_len, min_len = max(code_len.values()), min(code_len.values())
+        assert max_len <= 32
+        coded = sorted(code_len, key=lambda sym: (-code_len[sym], sym))
+        order = coded + [sym for sym in range(len(nodes)) if sym not in code_len]
+        new_id = {sym: i for i, sym in enumerate(order)}
+        assert len(order) < 0xFFF
+
+        lowest = {l: sum(1 for sym in coded if code_len[sym] > l) for l in range(min_len, max_len + 1)}
+        base = {max_len: 0}
+        for l in range(max_len - 1, min_len - 1, -1):
+            base[l] = (base[l + 1] + lowest[l] - lowest[l + 1]) // 2
+        codes = {sym: (base[code_len[sym]] + new_id[sym] - lowest[code_len[sym]], code_len[sym]) for sym in coded}
+
+        btree = bytearray()
+        for sym in order:
+            left, right = nodes[sym]
+            if right is None:
+                right = 0xFFF
+            else:
+                left, right = new_id[left], new_id[right]
+            btree += bytes([left & 0xFF, (left >> 8) | ((right & 0xF) << 4), right >> 4])
+        if len(order) & 1:
+            btree.append(0)
+
+        # pack the symbols into blocks, a symbol never crosses a block boundary
+        block_bits = 8 << BLOCK_SIZE_LOG
+        blocks = []
+        bits = []
+        used = count = 0
+        for sym in seq:
+            code, l = codes[sym]
+            if used + l > block_bits or count + length[sym] > MAX_BLOCK_VALUES:
+                blocks.append((bits, count))
+                bits, used, count = [], 0, 0
+            bits.append((code, l))
+            used += l
+            count += length[sym]
+        blocks.append((bits, count))
+
+        self.data = bytearray()
+        for bits, _ in blocks:
+            value = used = 0
+            for code, l in bits:
+                value = (value << l) | code
+                used += l
+            self.data += (value << (block_bits - used)).to_bytes(block_bits // 8, 'big')
+        self.block_lengths = b''.join(struct.pack('<H', count - 1) for _, count in blocks)
+
+        # sparse index points to the block and the offset in it of every k * span + span / 2 value
+        starts = [0]
+        for _, count in blocks:
+            starts.append(starts[-1] + count)
+        span = 1 << SPAN_LOG
+        self.sparse = bytearray()
+        for k in range((len(values) + span - 1) // span):
+            i = k * span + span // 2
+            block = min(bisect.bisect_right(starts, i) - 1, len(blocks) - 1)
+            self.sparse += struct.pack('<IH', block, i - starts[block])
+
+        self.sizes = bytes([self.flags, BLOCK_SIZE_LOG, SPAN_LOG, 0]) + struct.pack('<I', len(blocks))
+        self.sizes += bytes([max_len, min_len])
+        self.sizes += b''.join(struct.pack('<H', lowest[l]) for l in range(min_len, max_len + 1))
+        self.sizes += struct.pack('<H', len(order)) + btree
+
+
+def fill_gaps(values):
+    # positions that never happen are stored as the previous value, so they compress well
+    known = next(v for v in values if v is not None)
+    for i, value in enumerate(values):
+        if value is None:
+            values[i] = known
+        else:
+            known = value
+    return values
+
+
+class Table:
+    """Table of a white king with a piece against a black king, encoded from the solved positions."""
+
+    def __init__(self, ix, solver, pieces, orders):
+        self.ix = ix
+        self.solver = solver
+        self.has_pawns = solver.piece == 'P'
+        self.files = 4 if self.has_pawns else 1
+        self.pieces = pieces
+        self.orders = orders
+        self.layouts = [[Layout(ix, pieces[side], orders[side], self.has_pawns, f) for f in range(self.files)]
+                        for side in (0, 1)]
+
+    def code(self):
+        return 'K' + self.solver.piece + 'vK'
+
+    def encode(self, wk, x, bk, side):
+        squares = {CODES['K']: wk, CODES['K'] | 8: bk, CODES[self.solver.piece]: x}
+        f = min(x & 7, 7 - (x & 7)) if self.has_pawns else 0
+        layout = self.layouts[side][f]
+        return f, layout.encode([squares[piece] for piece in layout.pieces])
+
+    # values[side][file] for every index, None for indexes of illegal positions
+    def collect(self, value_of, sides):
+        values = {side: [[None] * self.layouts[side][f].size for f in range(self.files)] for side in sides}
+        for wk, x, bk, stm in self.solver.positions():
+            if stm in sides:
+                p = Solver.index(wk, x, bk, stm)
+                f, idx = self.encode(wk, x, bk, stm)
+                value = value_of(p, f)
+                if value is not None:
+                    assert values[stm][f][idx] in (None, value)
+                    values[stm][f][idx] = value
+        return values
+
+    # sides[f] are the sides to move stored for the file, DTZ tables store one of them
+    def header(self, magic, sides):
+        out = bytearray(magic)
+        out.append(1 | (2 if self.has_pawns else 0))
+        for f in range(self.files):
+            first, second = sides[f][0], sides[f][-1]
+            out.append(self.orders[first] | (self.orders[second] << 4))
+            for k in range(len(self.pieces[first])):
+                out.append(self.pieces[first][k] | (self.pieces[second][k] << 4))
+        if len(out) & 1:
+            out.append(0)
+        return out
+
+    @staticmethod
+    def body(out, parts, maps=None):
+        for part in parts:
+            out += part.sizes
+        if maps is not None:
+            out += maps
+            if len(out) & 1:
+                out.append(0)
+        for part in parts:
+            out += part.sparse
+        for part in parts:
+            out += part.block_lengths
+        for part in parts:
+            out += bytes(-len(out) % 64)
+            out += part.data
+        return bytes(out)
+
+    def wdl(self):
+        wdl = self.solver.wdl
+        values = self.collect(lambda p, f: wdl[p] + 2, (0, 1))
+        parts = [Compressed(fill_gaps(values[side][f]), 0) for f in range(self.files) for side in (0, 1)]
+        return self.body(self.header(WDL_MAGIC, [(0, 1)] * self.files), parts)
+
+    # DTZ is stored for one side to move per file, in plies, win and loss values are mapped by frequency if asked
+    def dtz(self, stm_by_file, mapped):
+        wdl, dtz = self.solver.wdl, self.solver.dtz
+        parts = []
+        maps = bytearray()
+        for f, stm in enumerate(stm_by_file):
+            # draws are never looked up in DTZ tables
+            values = self.collect(lambda p, file: (wdl[p], abs(dtz[p]) - 1) if wdl[p] and file == f else None, (stm,))[stm][f]
+            flags = (FLAG_STM if stm else 0) | FLAG_WIN_PLIES | FLAG_LOSS_PLIES
+            if mapped:
+                flags |= FLAG_MAPPED
+                # win, loss, cursed win and blessed loss maps, there are no 50-move rule results in these tables
+                tables = [[v for v, _ in Counter(v for w, v in filter(None, values) if w == result).most_common()] for result in (2, -2)]
+                for table in tables + [[], []]:
+                    maps.append(len(table))
+                    maps += bytes(table)
+                values = [value and (value[0], tables[value[0] < 0].index(value[1])) for value in values]
+            parts.append(Compressed(fill_gaps([value and value[1] for value in values]), flags))
+        return self.body(self.header(DTZ_MAGIC, [(stm,) for stm in stm_by_file]), parts, bytes(maps))
+
+
+def main():
+    out_dir = sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__))
+    ix = Indexes()
+    K, k = CODES['K'], CODES['K'] | 8
+    solvers = {piece: Solver(piece) for piece in 'QRBN'}
+    solvers['P'] = Solver('P', {piece: solvers[piece] for piece in 'QRBN'})
+
+    def write(name, data):
+        with open(os.path.join(out_dir, name), 'wb') as file:
+            file.write(data)
+
+    for piece in 'QRBN':
+        x = CODES[piece]
+        # the sides use different piece sequences, so both ways of normalizing squares are covered
+        table = Table(ix, solvers[piece], [[K, k, x], [x, K, k]], [0, 0])
+        write(table.code() + '.rtbw', table.wdl())
+    write('KQvK.rtbz', Table(ix, solvers['Q'], [[CODES['Q'], K, k]] * 2, [0, 0]).dtz([1], True))
+    write('KRvK.rtbz', Table(ix, solvers['R'], [[K, k, CODES['R']]] * 2, [0, 0]).dtz([0], True))
+
+    pawn = CODES['P']
+    table = Table(ix, solvers['P'], [[pawn, K, k], [pawn, k, K]], [0, 2])
+    write('KPvK.rtbw', table.wdl())
+    # files a and c store white to move, files b and d - black to move
+    write('KPvK.rtbz', table.dtz([0, 1, 0, 1], False))
+
+
+if __name__ == '__main__':
+    main()